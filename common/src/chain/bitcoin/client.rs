@@ -1,11 +1,8 @@
 use base64::{engine::general_purpose, Engine as _};
-use bitcoin::{Block, BlockHash, Transaction, Txid};
-use bitcoincore_rpc_json::{
-	bitcoin::{
-		consensus::{Decodable, ReadExt},
-		hashes::hex::HexIterator,
-	},
-	GetBlockchainInfoResult,
+use bitcoin::{Block, BlockHash, BlockHeader, Transaction, TxMerkleNode, Txid};
+use bitcoincore_rpc_json::bitcoin::{
+	consensus::{Decodable, ReadExt},
+	hashes::hex::HexIterator,
 };
 use derive_more::{Display, Error};
 use eyre::{eyre, Result};
@@ -21,6 +18,9 @@ const RPC_IN_WARMUP: i32 = -28;
 const RETRY_ATTEMPTS: u32 = 13;
 const RPC_TIMEOUT: u64 = 250;
 
+// merge-mined blocks (dogecoin, etc) signal an auxpow section after the header with this bit
+const VERSION_AUXPOW: i32 = 1 << 8;
+
 #[derive(Debug, Display, Error)]
 pub enum ClientError {
 	#[display(fmt = "{message}")]
@@ -52,26 +52,39 @@ struct Response {
 	id: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct UtxoScan {
+	pub height: u64,
+	pub unspents: Vec<Unspent>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Unspent {
+	pub amount: f64,
+}
+
 // @NOTE using custom client because `bitcoincore-rpc@0.16.0` is not async + doesn't support https
 pub struct Client {
 	url: String,
 	auth: Auth,
 	id: AtomicUsize,
 	with_retry: bool,
+	has_auxpow: bool,
 }
 
 impl Client {
-	pub fn new(url: &str, auth: Auth) -> Self {
-		Self { url: url.to_string(), auth, id: AtomicUsize::new(1), with_retry: true }
+	pub fn new(url: &str, auth: Auth, has_auxpow: bool) -> Self {
+		Self { url: url.to_string(), auth, id: AtomicUsize::new(1), with_retry: true, has_auxpow }
 	}
 
 	pub fn new_without_retry(url: &str, auth: Auth) -> Self {
-		Self { url: url.to_string(), auth, id: AtomicUsize::new(1), with_retry: false }
-	}
-
-	pub async fn get_blockchain_info(&self) -> Result<GetBlockchainInfoResult> {
-		let result = self.request("getblockchaininfo", &[]).await?;
-		Ok(serde_json::from_value(result)?)
+		Self {
+			url: url.to_string(),
+			auth,
+			id: AtomicUsize::new(1),
+			with_retry: false,
+			has_auxpow: false,
+		}
 	}
 
 	pub async fn get_block_count(&self) -> Result<u64> {
@@ -87,7 +100,25 @@ impl Client {
 	pub async fn get_block(&self, hash: &BlockHash) -> Result<Block> {
 		let result =
 			self.request("getblock", &[JsonValue::from(hash.to_string()), 0.into()]).await?;
-		deserialize_hex(result.as_str().unwrap())
+
+		if self.has_auxpow {
+			deserialize_auxpow_block_hex(result.as_str().unwrap())
+		} else {
+			deserialize_hex(result.as_str().unwrap())
+		}
+	}
+
+	pub async fn get_block_header(&self, hash: &BlockHash) -> Result<BlockHeader> {
+		let result = self
+			.request("getblockheader", &[JsonValue::from(hash.to_string()), false.into()])
+			.await?;
+
+		if self.has_auxpow {
+			// auxpow data (if any) trails the header, no need to read it
+			Ok(BlockHeader::consensus_decode(&mut HexIterator::new(result.as_str().unwrap())?)?)
+		} else {
+			deserialize_hex(result.as_str().unwrap())
+		}
 	}
 
 	pub async fn get_raw_transaction(
@@ -104,6 +135,14 @@ impl Client {
 		deserialize_hex(result.as_str().unwrap())
 	}
 
+	// @NOTE scans the whole utxo set at the tip, so this is slow
+	pub async fn scan_tx_out_set(&self, address: &str) -> Result<UtxoScan> {
+		let result = self
+			.request("scantxoutset", &["start".into(), json!([format!("addr({address})")])])
+			.await?;
+		Ok(serde_json::from_value(result)?)
+	}
+
 	async fn request(&self, method: &str, params: &[JsonValue]) -> Result<JsonValue> {
 		let client = reqwest::Client::new();
 		let mut req = client.post(&self.url);
@@ -165,3 +204,28 @@ fn deserialize_hex<T: Decodable>(hex: &str) -> Result<T> {
 		Ok(object)
 	}
 }
+
+fn deserialize_auxpow_block_hex(hex: &str) -> Result<Block> {
+	let mut reader = HexIterator::new(hex)?;
+	let header = BlockHeader::consensus_decode(&mut reader)?;
+
+	// skip auxpow: parent coinbase tx, parent block hash, coinbase merkle branch + index,
+	// blockchain merkle branch + index and the parent block header
+	if header.version & VERSION_AUXPOW != 0 {
+		Transaction::consensus_decode(&mut reader)?;
+		BlockHash::consensus_decode(&mut reader)?;
+		for _ in 0..2 {
+			Vec::<TxMerkleNode>::consensus_decode(&mut reader)?;
+			i32::consensus_decode(&mut reader)?;
+		}
+		BlockHeader::consensus_decode(&mut reader)?;
+	}
+
+	let txdata = Vec::<Transaction>::consensus_decode(&mut reader)?;
+
+	if reader.read_u8().is_ok() {
+		Err(eyre!("could not deserialize output"))
+	} else {
+		Ok(Block { header, txdata })
+	}
+}
@@ -1,6 +1,6 @@
 use async_trait::async_trait;
 use bitcoin::{
-	blockdata::transaction::Transaction, hash_types::Txid, util::address::Address,
+	blockdata::transaction::Transaction, hash_types::Txid, util::address::Address, Amount,
 	Network as BitcoinNetwork,
 };
 use eyre::Result;
@@ -10,18 +10,28 @@ use url::Url;
 
 use crate::{
 	cache::CacheKey,
-	chain::{ChainTrait, ModuleId, ModuleTrait, WarehouseData},
+	chain::{BlockHeader, ChainTrait, ModuleId, ModuleTrait, WarehouseData, U256},
 	models::Network,
 	utils, BlockHeight, Cache, RateLimiter,
 };
 use client::{Auth, Client};
 use modules::{
 	BitcoinBalance, BitcoinCoinbase, BitcoinModuleTrait, BitcoinRelationBalanceTransfer,
-	BitcoinRelationNoChange, BitcoinTransfer,
+	BitcoinRelationCoSpend, BitcoinRelationNoChange, BitcoinTransfer,
 };
+use params::ChainParams;
 
 mod client;
 mod modules;
+mod params;
+
+// fee going to the block's coinbase recipient; for the coinbase tx itself, the amount is the total
+// of all fees collected in the block
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Fee {
+	pub recipient: String,
+	pub amount: u64,
+}
 
 pub struct Bitcoin {
 	cache: Arc<RwLock<Cache>>,
@@ -29,6 +39,7 @@ pub struct Bitcoin {
 	rpc: Option<String>,
 	client: Option<Arc<Client>>,
 	bitcoin_network: BitcoinNetwork,
+	chain_params: Option<ChainParams>,
 	rate_limiter: Option<Arc<RateLimiter>>,
 	modules: Vec<Box<dyn BitcoinModuleTrait>>,
 }
@@ -46,12 +57,14 @@ impl Bitcoin {
 			client: None,
 			bitcoin_network: BitcoinNetwork::from_magic(chain_id)
 				.unwrap_or(BitcoinNetwork::Bitcoin),
+			chain_params: ChainParams::from_magic(chain_id),
 			rate_limiter: utils::get_rate_limiter(rps),
 			modules: vec![
 				Box::new(BitcoinTransfer::new(network_id)),
 				Box::new(BitcoinBalance::new(network_id)),
 				Box::new(BitcoinRelationBalanceTransfer::new(network_id)),
 				Box::new(BitcoinRelationNoChange::new(network_id)),
+				Box::new(BitcoinRelationCoSpend::new(network_id)),
 				Box::new(BitcoinCoinbase::new(network_id)),
 			],
 		}
@@ -78,8 +91,9 @@ impl ChainTrait for Bitcoin {
 				}
 
 				let client = Client::new_without_retry(&url, auth.clone());
-				if client.get_blockchain_info().await.is_ok() {
-					self.client = Some(Arc::new(Client::new(&url, auth)));
+				if client.get_block_count().await.is_ok() {
+					let has_auxpow = self.chain_params.as_ref().map_or(false, |p| p.has_auxpow);
+					self.client = Some(Arc::new(Client::new(&url, auth, has_auxpow)));
 					self.rpc = Some(url);
 
 					break;
@@ -111,6 +125,10 @@ impl ChainTrait for Bitcoin {
 	}
 
 	fn format_address(&self, address: &str) -> String {
+		if let Some(chain_params) = &self.chain_params {
+			return chain_params.format_address(address);
+		}
+
 		match Address::from_str(address) {
 			Ok(parsed_address) => parsed_address.to_string(),
 			_ => address.to_string(),
@@ -122,6 +140,23 @@ impl ChainTrait for Bitcoin {
 		Ok(self.client.as_ref().unwrap().get_block_count().await?)
 	}
 
+	async fn get_block_header(&self, block_height: BlockHeight) -> Result<Option<BlockHeader>> {
+		let mut ret = None;
+
+		self.rate_limit().await;
+		if let Ok(block_hash) = self.client.as_ref().unwrap().get_block_hash(block_height).await {
+			self.rate_limit().await;
+			let header = self.client.as_ref().unwrap().get_block_header(&block_hash).await?;
+
+			ret = Some(BlockHeader {
+				hash: block_hash.to_string(),
+				parent_hash: header.prev_blockhash.to_string(),
+			});
+		}
+
+		Ok(ret)
+	}
+
 	async fn process_block(
 		&self,
 		block_height: BlockHeight,
@@ -135,12 +170,27 @@ impl ChainTrait for Bitcoin {
 			if let Ok(block) = self.client.as_ref().unwrap().get_block(&block_hash).await {
 				let mut warehouse_data = WarehouseData::new();
 
-				for tx in block.txdata.into_iter() {
+				// coinbase tx goes last, once all the fees in the block are known
+				let mut txs = block.txdata;
+				let coinbase_tx = match txs.first() {
+					Some(tx) if tx.is_coin_base() => Some(txs.remove(0)),
+					_ => None,
+				};
+
+				let fee_recipient = match &coinbase_tx {
+					Some(tx) => self.get_fee_recipient(tx)?,
+					None => None,
+				};
+
+				let mut block_fees = 0;
+				for tx in txs.into_iter().chain(coinbase_tx) {
 					warehouse_data += self
 						.process_transaction(
 							block_height,
 							block.header.time,
 							tx,
+							fee_recipient.clone(),
+							&mut block_fees,
 							module_ids.clone(),
 						)
 						.await?;
@@ -152,6 +202,36 @@ impl ChainTrait for Bitcoin {
 
 		Ok(ret)
 	}
+
+	fn get_balance_module_id(&self, asset_address: &str) -> Option<ModuleId> {
+		asset_address.is_empty().then_some(ModuleId::BitcoinBalance)
+	}
+
+	async fn get_balance(
+		&self,
+		block_height: BlockHeight,
+		address: &str,
+		asset_address: &str,
+	) -> Result<Option<U256>> {
+		if !asset_address.is_empty() || !self.is_valid_address(address) {
+			return Ok(None);
+		}
+
+		self.rate_limit().await;
+		let utxo_scan = self.client.as_ref().unwrap().scan_tx_out_set(address).await?;
+
+		// the utxo set is only available at the tip
+		if utxo_scan.height != block_height {
+			return Ok(None);
+		}
+
+		let mut balance = 0;
+		for unspent in utxo_scan.unspents.into_iter() {
+			balance += Amount::from_btc(unspent.amount)?.to_sat();
+		}
+
+		Ok(Some(U256::from(balance)))
+	}
 }
 
 impl Bitcoin {
@@ -160,6 +240,8 @@ impl Bitcoin {
 		block_height: BlockHeight,
 		block_time: u32,
 		tx: Transaction,
+		fee_recipient: Option<String>,
+		block_fees: &mut u64,
 		module_ids: Vec<ModuleId>,
 	) -> Result<WarehouseData> {
 		let mut ret = WarehouseData::new();
@@ -197,9 +279,30 @@ impl Bitcoin {
 		let outputs =
 			get_unique_addresses(self.index_transaction_outputs(block_height, &tx).await?);
 
+		let fee_amount = match tx.is_coin_base() {
+			true => *block_fees,
+			_ => {
+				let input_amount_total: u64 = inputs.values().sum();
+				let output_amount_total: u64 = outputs.values().sum();
+				let fee_amount = input_amount_total.saturating_sub(output_amount_total);
+
+				*block_fees += fee_amount;
+				fee_amount
+			}
+		};
+		let fee = fee_recipient.map(|recipient| Fee { recipient, amount: fee_amount });
+
 		for module in self.modules.iter().filter(|m| module_ids.contains(&m.get_id())) {
 			ret += module
-				.run(self, block_height, block_time, tx.clone(), inputs.clone(), outputs.clone())
+				.run(
+					self,
+					block_height,
+					block_time,
+					tx.clone(),
+					inputs.clone(),
+					outputs.clone(),
+					fee.clone(),
+				)
 				.await?;
 		}
 
@@ -258,9 +361,15 @@ impl Bitcoin {
 		let mut ret = None;
 
 		if vout < tx.output.len() as u32 {
-			if let Ok(address) =
-				Address::from_script(&tx.output[vout as usize].script_pubkey, self.bitcoin_network)
-			{
+			let script_pubkey = &tx.output[vout as usize].script_pubkey;
+
+			if let Some(chain_params) = &self.chain_params {
+				ret = Some(
+					chain_params
+						.get_address(script_pubkey)
+						.unwrap_or_else(|| format!("{}:{}", tx.txid().as_hash(), vout)),
+				);
+			} else if let Ok(address) = Address::from_script(script_pubkey, self.bitcoin_network) {
 				ret = Some(address.to_string());
 			} else {
 				ret = Some(format!("{}:{}", tx.txid().as_hash(), vout));
@@ -270,7 +379,29 @@ impl Bitcoin {
 		Ok(ret)
 	}
 
+	// the largest coinbase output is where the miner collects their reward
+	fn get_fee_recipient(&self, coinbase_tx: &Transaction) -> Result<Option<String>> {
+		// reversed, so that the first output wins a tie
+		let vout = coinbase_tx
+			.output
+			.iter()
+			.enumerate()
+			.rev()
+			.filter(|(_, txout)| txout.value > 0)
+			.max_by_key(|(_, txout)| txout.value)
+			.map(|(i, _)| i as u32);
+
+		match vout {
+			Some(vout) => self.get_address(coinbase_tx, vout),
+			None => Ok(None),
+		}
+	}
+
 	fn is_valid_address(&self, address: &str) -> bool {
-		!address.contains(':')
+		// unrecognized outputs are stored as `txid:vout` (cashaddr also contains a colon)
+		match address.split_once(':') {
+			Some((txid, _)) => Txid::from_str(txid).is_err(),
+			None => true,
+		}
 	}
 }
@@ -5,7 +5,8 @@ use std::collections::HashMap;
 
 use crate::{
 	chain::{
-		bitcoin::modules::BitcoinModuleTrait, Bitcoin, ModuleId, ModuleTrait, WarehouseData, U256,
+		bitcoin::{modules::BitcoinModuleTrait, Fee},
+		Bitcoin, ModuleId, ModuleTrait, WarehouseData, U256,
 	},
 	models::{Amount, PrimaryId},
 	BlockHeight,
@@ -35,6 +36,7 @@ impl BitcoinModuleTrait for BitcoinBalance {
 		tx: Transaction,
 		inputs: HashMap<String, u64>,
 		outputs: HashMap<String, u64>,
+		fee: Option<Fee>,
 	) -> Result<WarehouseData> {
 		let mut ret = WarehouseData::new();
 		let mut balance_map = HashMap::<String, (u64, u64)>::new();
@@ -57,6 +59,16 @@ impl BitcoinModuleTrait for BitcoinBalance {
 			}
 		}
 
+		// fees are credited to the miner by the tx paying them, so take them out of the coinbase
+		// to keep the minted amount equal to the block subsidy
+		if let Some(fee) = fee.filter(|f| f.amount > 0) {
+			let amounts = balance_map.entry(fee.recipient).or_insert((0, 0));
+			match tx.is_coin_base() {
+				true => amounts.1 += fee.amount,
+				_ => amounts.0 += fee.amount,
+			}
+		}
+
 		let tx_hash = tx.txid().as_hash().to_string();
 
 		for (address, (amount_in, amount_out)) in balance_map.into_iter() {
@@ -65,6 +77,7 @@ impl BitcoinModuleTrait for BitcoinBalance {
 				self.network_id,
 				block_height,
 				&tx_hash.clone(),
+				0,
 				&address,
 				None,
 				U256::from_str_radix(&amount_in.to_string(), 10)?,
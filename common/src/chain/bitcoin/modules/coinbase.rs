@@ -5,7 +5,8 @@ use std::collections::HashMap;
 
 use crate::{
 	chain::{
-		bitcoin::modules::BitcoinModuleTrait, Bitcoin, ModuleId, ModuleTrait, WarehouseData, U256,
+		bitcoin::{modules::BitcoinModuleTrait, Fee},
+		Bitcoin, ModuleId, ModuleTrait, WarehouseData, U256,
 	},
 	models::{PrimaryId, Transfer},
 	BlockHeight,
@@ -33,8 +34,9 @@ impl BitcoinModuleTrait for BitcoinCoinbase {
 		block_height: BlockHeight,
 		block_time: u32,
 		tx: Transaction,
-		_inputs: HashMap<String, u64>,
+		inputs: HashMap<String, u64>,
 		outputs: HashMap<String, u64>,
+		fee: Option<Fee>,
 	) -> Result<WarehouseData> {
 		let mut ret = WarehouseData::new();
 
@@ -49,6 +51,7 @@ impl BitcoinModuleTrait for BitcoinCoinbase {
 					self.network_id,
 					block_height,
 					&tx_hash.clone(),
+					0,
 					"",
 					&to,
 					None,
@@ -57,6 +60,36 @@ impl BitcoinModuleTrait for BitcoinCoinbase {
 					block_time,
 				));
 			}
+		} else if let Some(fee) = fee.filter(|f| f.amount > 0) {
+			// fee goes to the miner, split among inputs the same way outputs are
+			let tx_hash = tx.txid().as_hash().to_string();
+			let input_amount_total: u64 = inputs.clone().into_values().sum();
+			let batch_amount = U256::from_str_radix(&fee.amount.to_string(), 10)?;
+
+			for (from, input_amount) in inputs.into_iter() {
+				if from != fee.recipient {
+					let amount = match input_amount_total > 0 {
+						true => ((input_amount as f64 / input_amount_total as f64) *
+							fee.amount as f64)
+							.round(),
+						_ => 0.0,
+					};
+
+					ret.transfers.insert(Transfer::new(
+						self.get_id(),
+						self.network_id,
+						block_height,
+						&tx_hash.clone(),
+						0,
+						&from,
+						&fee.recipient,
+						None,
+						U256::from_str_radix(&amount.to_string(), 10)?,
+						batch_amount,
+						block_time,
+					));
+				}
+			}
 		}
 
 		Ok(ret)
@@ -4,18 +4,20 @@ use eyre::Result;
 use std::collections::HashMap;
 
 use crate::{
-	chain::{Bitcoin, ModuleTrait, WarehouseData},
+	chain::{bitcoin::Fee, Bitcoin, ModuleTrait, WarehouseData},
 	BlockHeight,
 };
 pub use balance::BitcoinBalance;
 pub use coinbase::BitcoinCoinbase;
 pub use relation_balance_transfer::BitcoinRelationBalanceTransfer;
+pub use relation_co_spend::BitcoinRelationCoSpend;
 pub use relation_no_change::BitcoinRelationNoChange;
 pub use transfer::BitcoinTransfer;
 
 mod balance;
 mod coinbase;
 mod relation_balance_transfer;
+mod relation_co_spend;
 mod relation_no_change;
 mod transfer;
 
@@ -29,5 +31,6 @@ pub trait BitcoinModuleTrait: ModuleTrait + Send + Sync {
 		tx: Transaction,
 		inputs: HashMap<String, u64>,
 		outputs: HashMap<String, u64>,
+		fee: Option<Fee>,
 	) -> Result<WarehouseData>;
 }
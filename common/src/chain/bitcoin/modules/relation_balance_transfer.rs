@@ -4,7 +4,10 @@ use eyre::Result;
 use std::collections::HashMap;
 
 use crate::{
-	chain::{bitcoin::modules::BitcoinModuleTrait, Bitcoin, ModuleId, ModuleTrait, WarehouseData},
+	chain::{
+		bitcoin::{modules::BitcoinModuleTrait, Fee},
+		Bitcoin, ModuleId, ModuleTrait, WarehouseData,
+	},
 	models::{PrimaryId, Relation, RelationReason},
 	BlockHeight,
 };
@@ -33,6 +36,7 @@ impl BitcoinModuleTrait for BitcoinRelationBalanceTransfer {
 		tx: Transaction,
 		inputs: HashMap<String, u64>,
 		outputs: HashMap<String, u64>,
+		_fee: Option<Fee>,
 	) -> Result<WarehouseData> {
 		let mut ret = WarehouseData::new();
 
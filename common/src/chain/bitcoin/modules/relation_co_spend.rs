@@ -0,0 +1,84 @@
+use async_trait::async_trait;
+use bitcoin::blockdata::transaction::Transaction;
+use eyre::Result;
+use std::collections::HashMap;
+
+use crate::{
+	chain::{
+		bitcoin::{modules::BitcoinModuleTrait, Fee},
+		Bitcoin, ModuleId, ModuleTrait, WarehouseData,
+	},
+	models::{PrimaryId, Relation, RelationReason},
+	BlockHeight,
+};
+
+// outputs of the same value (typical for coinjoins, where inputs belong to different owners)
+const MAX_EQUAL_OUTPUTS: usize = 2;
+
+pub struct BitcoinRelationCoSpend {
+	network_id: PrimaryId,
+}
+
+impl ModuleTrait for BitcoinRelationCoSpend {
+	fn new(network_id: PrimaryId) -> Self {
+		Self { network_id }
+	}
+
+	fn get_id(&self) -> ModuleId {
+		ModuleId::BitcoinRelationCoSpend
+	}
+}
+
+#[async_trait]
+impl BitcoinModuleTrait for BitcoinRelationCoSpend {
+	async fn run(
+		&self,
+		bitcoin: &Bitcoin,
+		block_height: BlockHeight,
+		block_time: u32,
+		tx: Transaction,
+		inputs: HashMap<String, u64>,
+		_outputs: HashMap<String, u64>,
+		_fee: Option<Fee>,
+	) -> Result<WarehouseData> {
+		let mut ret = WarehouseData::new();
+
+		if tx.is_coin_base() {
+			return Ok(ret);
+		}
+
+		let looks_like_coinjoin = {
+			let mut counts = HashMap::<u64, usize>::new();
+			for amount in tx.output.iter().map(|o| o.value) {
+				*counts.entry(amount).or_default() += 1;
+			}
+
+			counts.into_values().any(|count| count > MAX_EQUAL_OUTPUTS)
+		};
+
+		let mut addresses =
+			inputs.into_keys().filter(|a| bitcoin.is_valid_address(a)).collect::<Vec<String>>();
+
+		// all inputs are assumed to be controlled by the same owner, so linking every input to the
+		// first one is enough for clustering
+		if !looks_like_coinjoin && addresses.len() > 1 {
+			addresses.sort_unstable();
+
+			let tx_hash = tx.txid().as_hash().to_string();
+			for address in addresses.iter().skip(1) {
+				ret.relations.insert(Relation::new(
+					self.get_id(),
+					self.network_id,
+					block_height,
+					&tx_hash,
+					&addresses[0],
+					address,
+					RelationReason::CommonInputOwnership,
+					block_time,
+				));
+			}
+		}
+
+		Ok(ret)
+	}
+}
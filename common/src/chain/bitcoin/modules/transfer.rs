@@ -5,7 +5,8 @@ use std::collections::HashMap;
 
 use crate::{
 	chain::{
-		bitcoin::modules::BitcoinModuleTrait, Bitcoin, ModuleId, ModuleTrait, WarehouseData, U256,
+		bitcoin::{modules::BitcoinModuleTrait, Fee},
+		Bitcoin, ModuleId, ModuleTrait, WarehouseData, U256,
 	},
 	models::{PrimaryId, Transfer},
 	BlockHeight,
@@ -35,6 +36,7 @@ impl BitcoinModuleTrait for BitcoinTransfer {
 		tx: Transaction,
 		inputs: HashMap<String, u64>,
 		outputs: HashMap<String, u64>,
+		_fee: Option<Fee>,
 	) -> Result<WarehouseData> {
 		let mut ret = WarehouseData::new();
 
@@ -47,6 +49,8 @@ impl BitcoinModuleTrait for BitcoinTransfer {
 		let output_amount_total: u64 = outputs.clone().into_values().sum();
 		let batch_amount = U256::from_str_radix(&output_amount_total.to_string(), 10)?;
 
+		// inputs & outputs are grouped by address, so each pair shows up once per tx (which
+		// makes its position 0)
 		for input in inputs.iter() {
 			for output in outputs.iter() {
 				let (from, to) = (input.0.clone(), output.0.clone());
@@ -62,6 +66,7 @@ impl BitcoinModuleTrait for BitcoinTransfer {
 						self.network_id,
 						block_height,
 						&tx_hash.clone(),
+						0,
 						&from,
 						&to,
 						None,
@@ -0,0 +1,197 @@
+use bitcoin::{
+	bech32::{self, u5, ToBase32, Variant},
+	blockdata::script::Script,
+	util::{
+		address::{Payload, WitnessVersion},
+		base58,
+	},
+};
+
+const CASHADDR_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+// Address encoding for utxo forks that `bitcoin::Address` cannot produce. Networks are matched by
+// their message start bytes (same as `chain_id` for regular bitcoin networks)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainParams {
+	pub p2pkh_prefix: u8,
+	pub p2sh_prefix: u8,
+	pub bech32_hrp: Option<&'static str>,
+	pub cashaddr_prefix: Option<&'static str>,
+	pub has_auxpow: bool,
+}
+
+impl ChainParams {
+	pub fn from_magic(magic: u32) -> Option<Self> {
+		let params = |p2pkh_prefix, p2sh_prefix, bech32_hrp, cashaddr_prefix, has_auxpow| Self {
+			p2pkh_prefix,
+			p2sh_prefix,
+			bech32_hrp,
+			cashaddr_prefix,
+			has_auxpow,
+		};
+
+		match magic {
+			// litecoin
+			0xdbb6c0fb => Some(params(0x30, 0x32, Some("ltc"), None, false)),
+			0xf1c8d2fd => Some(params(0x6f, 0x3a, Some("tltc"), None, false)),
+			// dogecoin
+			0xc0c0c0c0 => Some(params(0x1e, 0x16, None, None, true)),
+			0xdcb7c1fc => Some(params(0x71, 0xc4, None, None, true)),
+			// bitcoin cash
+			0xe8f3e1e3 => Some(params(0x00, 0x05, None, Some("bitcoincash"), false)),
+			0xf4f3e5f4 => Some(params(0x6f, 0xc4, None, Some("bchtest"), false)),
+			_ => None,
+		}
+	}
+
+	pub fn get_address(&self, script: &Script) -> Option<String> {
+		match Payload::from_script(script).ok()? {
+			Payload::PubkeyHash(hash) => Some(match self.cashaddr_prefix {
+				Some(prefix) => cashaddr_encode(prefix, 0, &hash[..]),
+				None => base58::check_encode_slice(&[&[self.p2pkh_prefix], &hash[..]].concat()),
+			}),
+			Payload::ScriptHash(hash) => Some(match self.cashaddr_prefix {
+				Some(prefix) => cashaddr_encode(prefix, 1, &hash[..]),
+				None => base58::check_encode_slice(&[&[self.p2sh_prefix], &hash[..]].concat()),
+			}),
+			Payload::WitnessProgram { version, program } => {
+				let variant = match version {
+					WitnessVersion::V0 => Variant::Bech32,
+					_ => Variant::Bech32m,
+				};
+
+				let mut data = vec![u5::try_from_u8(version.to_num()).ok()?];
+				data.extend(program.to_base32());
+
+				bech32::encode(self.bech32_hrp?, data, variant).ok()
+			}
+		}
+	}
+
+	pub fn format_address(&self, address: &str) -> String {
+		let lowercased = address.to_lowercase();
+
+		// bech32 & cashaddr are case-insensitive, so store them lowercased
+		match (self.bech32_hrp, self.cashaddr_prefix) {
+			(Some(hrp), _) if lowercased.starts_with(&format!("{hrp}1")) => lowercased,
+			(_, Some(prefix)) if lowercased.starts_with(&format!("{prefix}:")) => lowercased,
+			(_, Some(prefix)) if cashaddr_decodes(prefix, &lowercased) => {
+				format!("{prefix}:{lowercased}")
+			}
+			_ => address.to_string(),
+		}
+	}
+}
+
+// source: `https://github.com/bitcoincashorg/bitcoincash.org/blob/master/spec/cashaddr.md`
+fn cashaddr_polymod(values: &[u8]) -> u64 {
+	let generators = [0x98f2bc8e61, 0x79b76d99e2, 0xf33e5fb3c4, 0xae2eabe2a8, 0x1e4f43e470];
+
+	let mut c: u64 = 1;
+	for value in values.iter() {
+		let c0 = (c >> 35) as u8;
+		c = ((c & 0x07ffffffff) << 5) ^ (*value as u64);
+
+		for (i, generator) in generators.iter().enumerate() {
+			if c0 & (1 << i) != 0 {
+				c ^= generator;
+			}
+		}
+	}
+
+	c ^ 1
+}
+
+fn cashaddr_encode(prefix: &str, address_type: u8, hash: &[u8]) -> String {
+	// version byte is `type << 3 | size`, where size 0 means a 160-bit hash
+	let payload = [&[address_type << 3], hash]
+		.concat()
+		.to_base32()
+		.into_iter()
+		.map(|v| v.to_u8())
+		.collect::<Vec<u8>>();
+
+	let mut values: Vec<u8> = prefix.bytes().map(|b| b & 0x1f).collect();
+	values.push(0);
+	values.extend(&payload);
+	values.extend([0; 8]);
+
+	let checksum = cashaddr_polymod(&values);
+
+	let mut ret = format!("{prefix}:");
+	for v in payload.into_iter().chain((0..8).map(|i| ((checksum >> (5 * (7 - i))) & 0x1f) as u8)) {
+		ret.push(CASHADDR_CHARSET[v as usize] as char);
+	}
+
+	ret
+}
+
+fn cashaddr_decodes(prefix: &str, address: &str) -> bool {
+	let mut values: Vec<u8> = prefix.bytes().map(|b| b & 0x1f).collect();
+	values.push(0);
+
+	for c in address.bytes() {
+		match CASHADDR_CHARSET.iter().position(|&v| v == c) {
+			Some(v) => values.push(v as u8),
+			None => return false,
+		}
+	}
+
+	address.len() > 8 && cashaddr_polymod(&values) == 0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bitcoin::hashes::hex::FromHex;
+
+	#[test]
+	fn test_get_address() {
+		let p2pkh = Script::from_hex("76a91476a04053bda0a88bda5177b86a15c3b29f55987388ac").unwrap();
+		let p2wpkh = Script::from_hex("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+
+		let bitcoin_cash = ChainParams::from_magic(0xe8f3e1e3).unwrap();
+		assert_eq!(
+			bitcoin_cash.get_address(&p2pkh),
+			Some("bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a".to_string())
+		);
+		assert_eq!(bitcoin_cash.get_address(&p2wpkh), None);
+
+		let litecoin = ChainParams::from_magic(0xdbb6c0fb).unwrap();
+		assert_eq!(
+			litecoin.get_address(&p2pkh),
+			Some("LW3ByJXVHpiJsuy3u2sdieFQkXHtuk93Yi".to_string())
+		);
+		assert_eq!(
+			litecoin.get_address(&p2wpkh),
+			Some("ltc1qw508d6qejxtdg4y5r3zarvary0c5xw7kgmn4n9".to_string())
+		);
+
+		let dogecoin = ChainParams::from_magic(0xc0c0c0c0).unwrap();
+		assert_eq!(
+			dogecoin.get_address(&p2pkh),
+			Some("DFxLFMAJWaNYA7TVTUstzPMFRSevAwTSLq".to_string())
+		);
+
+		assert_eq!(ChainParams::from_magic(0xd9b4bef9), None);
+	}
+
+	#[test]
+	fn test_format_address() {
+		let bitcoin_cash = ChainParams::from_magic(0xe8f3e1e3).unwrap();
+		let address = "bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a";
+
+		assert_eq!(bitcoin_cash.format_address(&address.to_uppercase()), address);
+		assert_eq!(bitcoin_cash.format_address(&address[12..]), address);
+
+		let litecoin = ChainParams::from_magic(0xdbb6c0fb).unwrap();
+		assert_eq!(
+			litecoin.format_address("LTC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KGMN4N9"),
+			"ltc1qw508d6qejxtdg4y5r3zarvary0c5xw7kgmn4n9"
+		);
+		assert_eq!(
+			litecoin.format_address("LW3ByJXVHpiJsuy3u2sdieFQkXHtuk93Yi"),
+			"LW3ByJXVHpiJsuy3u2sdieFQkXHtuk93Yi"
+		);
+	}
+}
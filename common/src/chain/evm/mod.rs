@@ -3,30 +3,101 @@ use ethers::{
 	self,
 	abi::AbiDecode,
 	prelude::*,
-	types::{Address, Log, Transaction, TransactionReceipt, U256, U64},
+	types::{
+		Action, Address, CallType, Log, Res, Transaction, TransactionReceipt, H256, U256, U64,
+	},
 	utils::hex::ToHex,
 };
 use eyre::Result;
-use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{collections::HashMap, sync::Arc};
 use tokio::sync::RwLock;
 
 use crate::{
 	cache::CacheKey,
-	chain::{ChainTrait, ModuleId, ModuleTrait, WarehouseData},
+	chain::{BlockHeader, ChainTrait, ModuleId, ModuleTrait, WarehouseData},
 	models::Network,
 	utils, BlockHeight, Cache, RateLimiter,
 };
-use modules::{EvmBalance, EvmModuleTrait, EvmTokenBalance, EvmTokenTransfer, EvmTransfer};
+use modules::{
+	EvmBalance, EvmErc1155Transfer, EvmErc721Transfer, EvmInternalTransfer, EvmModuleTrait,
+	EvmTokenBalance, EvmTokenTransfer, EvmTransfer,
+};
 
 mod modules;
 
 static TRANSFER_FROM_TO_AMOUNT: &str =
 	"ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+static TRANSFER_SINGLE: &str = "c3d58168c5ae7397731d063d5bbf3d657854427343f4c083240f7aacaa2d0f62";
+static TRANSFER_BATCH: &str = "4a39dc06d4c0dbc64b70af90fd698a233a518aa5d07e595d983b8c0526c8f7fb";
+const BALANCE_OF: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum EvmTopic {
 	Unknown,
 	TokenTransfer(Address, Address, U256),
+	NftTransfer(Address, Address, U256),
+	MultiTokenTransfer(Address, Address, Vec<(U256, U256)>),
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum TraceMethod {
+	TraceBlock,
+	DebugTraceBlock,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InternalTransfer {
+	pub from: Address,
+	pub to: Address,
+	pub value: U256,
+}
+
+// block-level details needed to account for gas fees
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct BlockFees {
+	pub author: Option<Address>,
+	pub base_fee_per_gas: Option<U256>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DebugTrace {
+	result: CallFrame,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CallFrame {
+	#[serde(rename = "type")]
+	call_type: String,
+	from: Address,
+	to: Option<Address>,
+	value: Option<U256>,
+	error: Option<String>,
+	#[serde(default)]
+	calls: Vec<CallFrame>,
+}
+
+impl CallFrame {
+	fn collect_transfers(&self, ret: &mut Vec<InternalTransfer>) {
+		// nothing inside a reverted frame took effect
+		if self.error.is_some() {
+			return;
+		}
+
+		if let (Some(to), Some(value)) = (self.to, self.value) {
+			let moves_value =
+				["CALL", "CREATE", "CREATE2", "SELFDESTRUCT"].contains(&self.call_type.as_str());
+
+			if moves_value && !value.is_zero() && self.from != to {
+				ret.push(InternalTransfer { from: self.from, to, value });
+			}
+		}
+
+		for call in self.calls.iter() {
+			call.collect_transfers(ret);
+		}
+	}
 }
 
 pub struct Evm {
@@ -34,6 +105,7 @@ pub struct Evm {
 	network: Network,
 	rpc: Option<String>,
 	provider: Option<Arc<Provider<RetryClient<Http>>>>,
+	trace_method: Option<TraceMethod>,
 	rate_limiter: Option<Arc<RateLimiter>>,
 	modules: Vec<Box<dyn EvmModuleTrait>>,
 }
@@ -48,12 +120,16 @@ impl Evm {
 			network,
 			rpc: None,
 			provider: None,
+			trace_method: None,
 			rate_limiter: utils::get_rate_limiter(rps),
 			modules: vec![
 				Box::new(EvmTransfer::new(network_id)),
+				Box::new(EvmInternalTransfer::new(network_id)),
 				Box::new(EvmBalance::new(network_id)),
 				Box::new(EvmTokenTransfer::new(network_id)),
 				Box::new(EvmTokenBalance::new(network_id)),
+				Box::new(EvmErc721Transfer::new(network_id)),
+				Box::new(EvmErc1155Transfer::new(network_id)),
 			],
 		}
 	}
@@ -71,7 +147,8 @@ impl ChainTrait for Evm {
 					rate_limiter.until_ready().await;
 				}
 
-				if provider.get_block_number().await.is_ok() {
+				if let Ok(block_number) = provider.get_block_number().await {
+					self.trace_method = self.get_trace_method(&provider, block_number).await;
 					self.rpc = Some(url);
 					self.provider = Some(Arc::new(provider));
 
@@ -118,6 +195,17 @@ impl ChainTrait for Evm {
 		Ok(self.provider.as_ref().unwrap().get_block_number().await?.as_u64())
 	}
 
+	async fn get_block_header(&self, block_height: BlockHeight) -> Result<Option<BlockHeader>> {
+		self.rate_limit().await;
+		Ok(match self.provider.as_ref().unwrap().get_block(block_height).await? {
+			Some(block) if block.hash.is_some() => Some(BlockHeader {
+				hash: ethers::abi::AbiEncode::encode_hex(block.hash.unwrap()),
+				parent_hash: ethers::abi::AbiEncode::encode_hex(block.parent_hash),
+			}),
+			_ => None,
+		})
+	}
+
 	async fn process_block(
 		&self,
 		block_height: BlockHeight,
@@ -130,6 +218,20 @@ impl ChainTrait for Evm {
 		match provider.get_block_with_txs(block_height).await? {
 			Some(block) if block.number.is_some() => {
 				let mut warehouse_data = WarehouseData::new();
+				let block_fees =
+					BlockFees { author: block.author, base_fee_per_gas: block.base_fee_per_gas };
+
+				// value moved by contracts is only visible in traces
+				let mut internal_transfers = HashMap::new();
+				if module_ids
+					.iter()
+					.any(|m| [ModuleId::EvmInternalTransfer, ModuleId::EvmBalance].contains(m))
+				{
+					let tx_hashes: Vec<H256> =
+						block.transactions.iter().map(|tx| tx.hash).collect();
+					internal_transfers =
+						self.get_internal_transfers(block_height, &tx_hashes).await?;
+				}
 
 				for tx in block.transactions.into_iter() {
 					// skip if pending
@@ -140,21 +242,28 @@ impl ChainTrait for Evm {
 					// process tx only if receipt exists
 					self.rate_limit().await;
 					if let Some(receipt) = provider.get_transaction_receipt(tx.hash()).await? {
-						// skip if tx reverted
-						if let Some(status) = receipt.status {
-							if status == U64::zero() {
-								continue;
-							}
-						}
+						// reverted txs still pay for gas, so only balances are affected
+						let module_ids = match receipt.status {
+							Some(status) if status == U64::zero() => module_ids
+								.iter()
+								.filter(|m| **m == ModuleId::EvmBalance)
+								.copied()
+								.collect(),
+							_ => module_ids.clone(),
+						};
 
 						// process tx
+						let internal_transfers =
+							internal_transfers.remove(&tx.hash).unwrap_or_default();
 						warehouse_data += self
 							.process_transaction(
 								block_height,
 								block.timestamp.as_u32(),
 								tx,
 								receipt,
-								module_ids.clone(),
+								internal_transfers,
+								block_fees.clone(),
+								module_ids,
 							)
 							.await?;
 					}
@@ -167,6 +276,48 @@ impl ChainTrait for Evm {
 
 		Ok(ret)
 	}
+
+	fn get_balance_module_id(&self, asset_address: &str) -> Option<ModuleId> {
+		Some(match asset_address.is_empty() {
+			true => ModuleId::EvmBalance,
+			_ => ModuleId::EvmTokenBalance,
+		})
+	}
+
+	async fn get_balance(
+		&self,
+		block_height: BlockHeight,
+		address: &str,
+		asset_address: &str,
+	) -> Result<Option<U256>> {
+		let provider = self.provider.as_ref().unwrap();
+		let block_id = Some(BlockId::from(block_height));
+
+		let address: Address = match address.get(2..).and_then(|a| a.parse().ok()) {
+			Some(address) => address,
+			_ => return Ok(None),
+		};
+
+		if asset_address.is_empty() {
+			self.rate_limit().await;
+			return Ok(Some(provider.get_balance(address, block_id).await?));
+		}
+
+		let token_address: Address = match asset_address.get(2..).and_then(|a| a.parse().ok()) {
+			Some(token_address) => token_address,
+			_ => return Ok(None),
+		};
+
+		// `balanceOf(address)`
+		let tx = TransactionRequest::new()
+			.to(token_address)
+			.data([&BALANCE_OF[..], H256::from(address).as_bytes()].concat());
+
+		self.rate_limit().await;
+		let ret = provider.call(&tx.into(), block_id).await?;
+
+		Ok((ret.len() >= 32).then(|| U256::from_big_endian(&ret[..32])))
+	}
 }
 
 impl Evm {
@@ -176,12 +327,129 @@ impl Evm {
 		block_time: u32,
 		tx: Transaction,
 		receipt: TransactionReceipt,
+		internal_transfers: Vec<InternalTransfer>,
+		block_fees: BlockFees,
 		module_ids: Vec<ModuleId>,
 	) -> Result<WarehouseData> {
 		let mut ret = WarehouseData::new();
 
 		for module in self.modules.iter().filter(|m| module_ids.contains(&m.get_id())) {
-			ret += module.run(self, block_height, block_time, tx.clone(), receipt.clone()).await?;
+			ret += module
+				.run(
+					self,
+					block_height,
+					block_time,
+					tx.clone(),
+					receipt.clone(),
+					internal_transfers.clone(),
+					block_fees.clone(),
+				)
+				.await?;
+		}
+
+		Ok(ret)
+	}
+
+	async fn get_trace_method(
+		&self,
+		provider: &Provider<RetryClient<Http>>,
+		block_number: U64,
+	) -> Option<TraceMethod> {
+		self.rate_limit().await;
+		if provider.trace_block(BlockNumber::Number(block_number)).await.is_ok() {
+			return Some(TraceMethod::TraceBlock);
+		}
+
+		self.rate_limit().await;
+		provider
+			.request::<_, Vec<DebugTrace>>(
+				"debug_traceBlockByNumber",
+				(block_number, json!({ "tracer": "callTracer" })),
+			)
+			.await
+			.ok()
+			.map(|_| TraceMethod::DebugTraceBlock)
+	}
+
+	// top-level transfers are left out since they're already covered by `tx.value`
+	async fn get_internal_transfers(
+		&self,
+		block_height: BlockHeight,
+		tx_hashes: &[H256],
+	) -> Result<HashMap<H256, Vec<InternalTransfer>>> {
+		let mut ret = HashMap::<H256, Vec<InternalTransfer>>::new();
+		let provider = self.provider.as_ref().unwrap();
+		let block_number = U64::from(block_height);
+
+		match self.trace_method {
+			Some(TraceMethod::TraceBlock) => {
+				let mut reverted = vec![];
+
+				self.rate_limit().await;
+				for trace in provider.trace_block(BlockNumber::Number(block_number)).await? {
+					let tx_hash = match trace.transaction_hash {
+						Some(tx_hash) => tx_hash,
+						None => continue, // block rewards
+					};
+
+					// traces come parent-first, so reverted frames are known before their children
+					if trace.error.is_some() {
+						reverted.push((tx_hash, trace.trace_address.clone()));
+					}
+					if trace.trace_address.is_empty() ||
+						reverted
+							.iter()
+							.any(|(h, a)| *h == tx_hash && trace.trace_address.starts_with(a))
+					{
+						continue;
+					}
+
+					let transfer = match (trace.action, trace.result) {
+						(Action::Call(call), _) if call.call_type == CallType::Call => {
+							Some((call.from, call.to, call.value))
+						}
+						(Action::Create(create), Some(Res::Create(result))) => {
+							Some((create.from, result.address, create.value))
+						}
+						(Action::Suicide(suicide), _) => {
+							Some((suicide.address, suicide.refund_address, suicide.balance))
+						}
+						_ => None,
+					};
+
+					if let Some((from, to, value)) = transfer {
+						if !value.is_zero() && from != to {
+							ret.entry(tx_hash).or_default().push(InternalTransfer {
+								from,
+								to,
+								value,
+							});
+						}
+					}
+				}
+			}
+			Some(TraceMethod::DebugTraceBlock) => {
+				self.rate_limit().await;
+				let traces: Vec<DebugTrace> = provider
+					.request(
+						"debug_traceBlockByNumber",
+						(block_number, json!({ "tracer": "callTracer" })),
+					)
+					.await?;
+
+				// results are in the same order as the block's txs
+				for (tx_hash, trace) in tx_hashes.iter().zip(traces) {
+					let mut transfers = vec![];
+					for call in trace.result.calls.iter() {
+						call.collect_transfers(&mut transfers);
+					}
+
+					if !transfers.is_empty() {
+						ret.insert(*tx_hash, transfers);
+					}
+				}
+			}
+			None => {}
 		}
 
 		Ok(ret)
@@ -215,6 +483,79 @@ impl Evm {
 			return Ok(EvmTopic::TokenTransfer(from, to, amount));
 		}
 
+		// erc-721 `Transfer` has the same signature as erc-20, but the token id is indexed
+		if log.topics.len() == 4 && log.topics[0].encode_hex::<String>() == *TRANSFER_FROM_TO_AMOUNT
+		{
+			let from = Address::from(log.topics[1]);
+			let to = Address::from(log.topics[2]);
+			let token_id = U256::from_big_endian(log.topics[3].as_bytes());
+
+			return Ok(EvmTopic::NftTransfer(from, to, token_id));
+		}
+
+		// erc-1155 `TransferSingle` & `TransferBatch` (first indexed topic is the operator)
+		if log.topics.len() == 4 {
+			let topic = log.topics[0].encode_hex::<String>();
+			let from = Address::from(log.topics[2]);
+			let to = Address::from(log.topics[3]);
+
+			if topic == *TRANSFER_SINGLE {
+				if let Ok((id, value)) = <(U256, U256)>::decode(log.data.clone()) {
+					return Ok(EvmTopic::MultiTokenTransfer(from, to, vec![(id, value)]));
+				}
+			} else if topic == *TRANSFER_BATCH {
+				if let Ok((ids, values)) = <(Vec<U256>, Vec<U256>)>::decode(log.data.clone()) {
+					let tokens = ids.into_iter().zip(values).collect();
+					return Ok(EvmTopic::MultiTokenTransfer(from, to, tokens));
+				}
+			}
+		}
+
 		Ok(EvmTopic::Unknown)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_collect_transfers() {
+		let (a, b, c) = (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+
+		let frame: CallFrame = serde_json::from_value(json!({
+			"type": "CALL",
+			"from": a,
+			"to": b,
+			"value": "0x1",
+			"calls": [
+				{ "type": "CALL", "from": b, "to": c, "value": "0x2" },
+				{ "type": "DELEGATECALL", "from": b, "to": c, "value": "0x3" },
+				{ "type": "STATICCALL", "from": b, "to": c },
+				{
+					"type": "CALL",
+					"from": b,
+					"to": c,
+					"value": "0x4",
+					"error": "execution reverted",
+					"calls": [{ "type": "CALL", "from": c, "to": a, "value": "0x5" }],
+				},
+				{ "type": "SELFDESTRUCT", "from": c, "to": a, "value": "0x6" },
+			],
+		}))
+		.unwrap();
+
+		let mut transfers = vec![];
+		for call in frame.calls.iter() {
+			call.collect_transfers(&mut transfers);
+		}
+
+		assert_eq!(
+			transfers,
+			vec![
+				InternalTransfer { from: b, to: c, value: U256::from(2) },
+				InternalTransfer { from: c, to: a, value: U256::from(6) },
+			]
+		);
+	}
+}
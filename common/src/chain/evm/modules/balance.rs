@@ -1,13 +1,17 @@
 use async_trait::async_trait;
 use ethers::{
 	abi::AbiEncode,
-	types::{Transaction, TransactionReceipt},
+	types::{Address, Transaction, TransactionReceipt, U256 as EthersU256, U64},
 	utils,
 };
 use eyre::Result;
+use std::collections::HashMap;
 
 use crate::{
-	chain::{evm::modules::EvmModuleTrait, Evm, ModuleId, ModuleTrait, WarehouseData, U256},
+	chain::{
+		evm::{modules::EvmModuleTrait, BlockFees, InternalTransfer},
+		Evm, ModuleId, ModuleTrait, WarehouseData, U256,
+	},
 	models::{Amount, PrimaryId},
 	BlockHeight,
 };
@@ -34,47 +38,72 @@ impl EvmModuleTrait for EvmBalance {
 		block_height: BlockHeight,
 		block_time: u32,
 		tx: Transaction,
-		_receipt: TransactionReceipt,
+		receipt: TransactionReceipt,
+		internal_transfers: Vec<InternalTransfer>,
+		block_fees: BlockFees,
 	) -> Result<WarehouseData> {
 		let mut ret = WarehouseData::new();
 
-		// skip if no asset transfer
-		if tx.value.is_zero() {
-			return Ok(ret);
-		}
+		// amounts in & out per address (rows would get deduped otherwise)
+		let mut amounts: HashMap<Address, (EthersU256, EthersU256)> = HashMap::new();
+
+		// nothing but gas is paid for a reverted tx
+		if receipt.status != Some(U64::zero()) {
+			let mut transfers = vec![];
+
+			// skip if no asset transfer, contract deploy call or sending to self
+			if let Some(to) = tx.to {
+				if !tx.value.is_zero() && tx.from != to {
+					transfers.push((tx.from, to, tx.value));
+				}
+			}
 
-		// skip if contract deploy call
-		if tx.to.is_none() {
-			return Ok(ret);
+			// value moved by contracts during this tx
+			for internal_transfer in internal_transfers.into_iter() {
+				transfers.push((
+					internal_transfer.from,
+					internal_transfer.to,
+					internal_transfer.value,
+				));
+			}
+
+			for (from, to, value) in transfers.into_iter() {
+				amounts.entry(from).or_default().1 += value;
+				amounts.entry(to).or_default().0 += value;
+			}
 		}
 
-		// skip if sending to self
-		if tx.from == tx.to.unwrap() {
-			return Ok(ret);
+		// sender pays for all gas used; the base fee is burned (eip-1559) and the rest is
+		// the tip that goes to the block author
+		let gas_used = receipt.gas_used.unwrap_or_default();
+		let gas_price = receipt.effective_gas_price.or(tx.gas_price).unwrap_or_default();
+		let fee = gas_used * gas_price;
+		if !fee.is_zero() {
+			amounts.entry(tx.from).or_default().1 += fee;
+
+			let base_fee_per_gas = block_fees.base_fee_per_gas.unwrap_or_default();
+			let tip = gas_used * gas_price.saturating_sub(base_fee_per_gas);
+			if let Some(author) = block_fees.author {
+				if !tip.is_zero() {
+					amounts.entry(author).or_default().0 += tip;
+				}
+			}
 		}
 
-		ret.amounts.insert(Amount::new(
-			self.get_id(),
-			self.network_id,
-			block_height,
-			&tx.hash.encode_hex(),
-			&utils::to_checksum(&tx.from, None),
-			None,
-			U256::zero(),
-			U256::from_str_radix(&tx.value.to_string(), 10)?,
-			block_time,
-		));
-		ret.amounts.insert(Amount::new(
-			self.get_id(),
-			self.network_id,
-			block_height,
-			&tx.hash.encode_hex(),
-			&utils::to_checksum(&tx.to.unwrap(), None),
-			None,
-			U256::from_str_radix(&tx.value.to_string(), 10)?,
-			U256::zero(),
-			block_time,
-		));
+		for (address, (amount_in, amount_out)) in amounts.into_iter() {
+			ret.amounts.insert(Amount::new(
+				self.get_id(),
+				self.network_id,
+				block_height,
+				&tx.hash.encode_hex(),
+				0,
+				&utils::to_checksum(&address, None),
+				None,
+				U256::from_str_radix(&amount_in.to_string(), 10)?,
+				U256::from_str_radix(&amount_out.to_string(), 10)?,
+				block_time,
+			));
+		}
 
 		Ok(ret)
 	}
@@ -0,0 +1,79 @@
+use async_trait::async_trait;
+use ethers::{
+	abi::AbiEncode,
+	types::{Transaction, TransactionReceipt},
+	utils,
+};
+use eyre::Result;
+
+use crate::{
+	chain::{
+		evm::{modules::EvmModuleTrait, BlockFees, EvmTopic, InternalTransfer},
+		Evm, ModuleId, ModuleTrait, WarehouseData, U256,
+	},
+	models::{PrimaryId, Transfer},
+	BlockHeight,
+};
+
+pub struct EvmErc1155Transfer {
+	network_id: PrimaryId,
+}
+
+impl ModuleTrait for EvmErc1155Transfer {
+	fn new(network_id: PrimaryId) -> Self {
+		Self { network_id }
+	}
+
+	fn get_id(&self) -> ModuleId {
+		ModuleId::EvmErc1155Transfer
+	}
+}
+
+#[async_trait]
+impl EvmModuleTrait for EvmErc1155Transfer {
+	async fn run(
+		&self,
+		evm: &Evm,
+		block_height: BlockHeight,
+		block_time: u32,
+		tx: Transaction,
+		receipt: TransactionReceipt,
+		_internal_transfers: Vec<InternalTransfer>,
+		_block_fees: BlockFees,
+	) -> Result<WarehouseData> {
+		let mut ret = WarehouseData::new();
+
+		for (i, log) in receipt.logs.into_iter().enumerate() {
+			// if log was removed, it's not valid
+			if let Some(removed) = log.removed {
+				if removed {
+					continue;
+				}
+			}
+
+			// process `TransferSingle` & `TransferBatch` events
+			if let EvmTopic::MultiTokenTransfer(from, to, tokens) = evm.get_topic(&log)? {
+				for (token_id, amount) in tokens.into_iter().filter(|(_, a)| *a > U256::zero()) {
+					ret.transfers.insert(
+						Transfer::new(
+							self.get_id(),
+							self.network_id,
+							block_height,
+							&tx.hash.encode_hex(),
+							i as u64,
+							&utils::to_checksum(&from, None),
+							&utils::to_checksum(&to, None),
+							Some(utils::to_checksum(&log.address, None)),
+							amount,
+							amount,
+							block_time,
+						)
+						.with_token_id(token_id.to_string()),
+					);
+				}
+			}
+		}
+
+		Ok(ret)
+	}
+}
@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use ethers::{
+	abi::AbiEncode,
+	types::{Transaction, TransactionReceipt},
+	utils,
+};
+use eyre::Result;
+
+use crate::{
+	chain::{
+		evm::{modules::EvmModuleTrait, BlockFees, EvmTopic, InternalTransfer},
+		Evm, ModuleId, ModuleTrait, WarehouseData, U256,
+	},
+	models::{PrimaryId, Transfer},
+	BlockHeight,
+};
+
+pub struct EvmErc721Transfer {
+	network_id: PrimaryId,
+}
+
+impl ModuleTrait for EvmErc721Transfer {
+	fn new(network_id: PrimaryId) -> Self {
+		Self { network_id }
+	}
+
+	fn get_id(&self) -> ModuleId {
+		ModuleId::EvmErc721Transfer
+	}
+}
+
+#[async_trait]
+impl EvmModuleTrait for EvmErc721Transfer {
+	async fn run(
+		&self,
+		evm: &Evm,
+		block_height: BlockHeight,
+		block_time: u32,
+		tx: Transaction,
+		receipt: TransactionReceipt,
+		_internal_transfers: Vec<InternalTransfer>,
+		_block_fees: BlockFees,
+	) -> Result<WarehouseData> {
+		let mut ret = WarehouseData::new();
+
+		for (i, log) in receipt.logs.into_iter().enumerate() {
+			// if log was removed, it's not valid
+			if let Some(removed) = log.removed {
+				if removed {
+					continue;
+				}
+			}
+
+			// process nft `transfer` event
+			if let EvmTopic::NftTransfer(from, to, token_id) = evm.get_topic(&log)? {
+				ret.transfers.insert(
+					Transfer::new(
+						self.get_id(),
+						self.network_id,
+						block_height,
+						&tx.hash.encode_hex(),
+						i as u64,
+						&utils::to_checksum(&from, None),
+						&utils::to_checksum(&to, None),
+						Some(utils::to_checksum(&log.address, None)),
+						U256::one(),
+						U256::one(),
+						block_time,
+					)
+					.with_token_id(token_id.to_string()),
+				);
+			}
+		}
+
+		Ok(ret)
+	}
+}
@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use ethers::{
+	abi::AbiEncode,
+	types::{Transaction, TransactionReceipt},
+	utils,
+};
+use eyre::Result;
+
+use crate::{
+	chain::{
+		evm::{modules::EvmModuleTrait, BlockFees, InternalTransfer},
+		Evm, ModuleId, ModuleTrait, WarehouseData, U256,
+	},
+	models::{PrimaryId, Transfer},
+	BlockHeight,
+};
+
+pub struct EvmInternalTransfer {
+	network_id: PrimaryId,
+}
+
+impl ModuleTrait for EvmInternalTransfer {
+	fn new(network_id: PrimaryId) -> Self {
+		Self { network_id }
+	}
+
+	fn get_id(&self) -> ModuleId {
+		ModuleId::EvmInternalTransfer
+	}
+}
+
+#[async_trait]
+impl EvmModuleTrait for EvmInternalTransfer {
+	async fn run(
+		&self,
+		_evm: &Evm,
+		block_height: BlockHeight,
+		block_time: u32,
+		tx: Transaction,
+		_receipt: TransactionReceipt,
+		internal_transfers: Vec<InternalTransfer>,
+		_block_fees: BlockFees,
+	) -> Result<WarehouseData> {
+		let mut ret = WarehouseData::new();
+
+		// @NOTE empty unless the rpc supports `trace_block` or `debug_traceBlockByNumber`
+		for (i, internal_transfer) in internal_transfers.into_iter().enumerate() {
+			let amount = U256::from_str_radix(&internal_transfer.value.to_string(), 10)?;
+
+			ret.transfers.insert(Transfer::new(
+				self.get_id(),
+				self.network_id,
+				block_height,
+				&tx.hash.encode_hex(),
+				i as u64,
+				&utils::to_checksum(&internal_transfer.from, None),
+				&utils::to_checksum(&internal_transfer.to, None),
+				None,
+				amount,
+				amount,
+				block_time,
+			));
+		}
+
+		Ok(ret)
+	}
+}
@@ -3,15 +3,24 @@ use ethers::types::{Transaction, TransactionReceipt};
 use eyre::Result;
 
 use crate::{
-	chain::{Evm, ModuleTrait, WarehouseData},
+	chain::{
+		evm::{BlockFees, InternalTransfer},
+		Evm, ModuleTrait, WarehouseData,
+	},
 	BlockHeight,
 };
 pub use balance::EvmBalance;
+pub use erc1155_transfer::EvmErc1155Transfer;
+pub use erc721_transfer::EvmErc721Transfer;
+pub use internal_transfer::EvmInternalTransfer;
 pub use token_balance::EvmTokenBalance;
 pub use token_transfer::EvmTokenTransfer;
 pub use transfer::EvmTransfer;
 
 mod balance;
+mod erc1155_transfer;
+mod erc721_transfer;
+mod internal_transfer;
 mod token_balance;
 mod token_transfer;
 mod transfer;
@@ -25,5 +34,7 @@ pub trait EvmModuleTrait: ModuleTrait + Send + Sync {
 		block_time: u32,
 		tx: Transaction,
 		receipt: TransactionReceipt,
+		internal_transfers: Vec<InternalTransfer>,
+		block_fees: BlockFees,
 	) -> Result<WarehouseData>;
 }
@@ -8,7 +8,7 @@ use eyre::Result;
 
 use crate::{
 	chain::{
-		evm::{modules::EvmModuleTrait, EvmTopic},
+		evm::{modules::EvmModuleTrait, BlockFees, EvmTopic, InternalTransfer},
 		Evm, ModuleId, ModuleTrait, WarehouseData, U256,
 	},
 	models::{Amount, PrimaryId},
@@ -38,10 +38,12 @@ impl EvmModuleTrait for EvmTokenBalance {
 		block_time: u32,
 		tx: Transaction,
 		receipt: TransactionReceipt,
+		_internal_transfers: Vec<InternalTransfer>,
+		_block_fees: BlockFees,
 	) -> Result<WarehouseData> {
 		let mut ret = WarehouseData::new();
 
-		for log in receipt.logs.into_iter() {
+		for (i, log) in receipt.logs.into_iter().enumerate() {
 			// if log was removed, it's not valid
 			if let Some(removed) = log.removed {
 				if removed {
@@ -57,6 +59,7 @@ impl EvmModuleTrait for EvmTokenBalance {
 						self.network_id,
 						block_height,
 						&tx.hash.encode_hex(),
+						i as u64,
 						&utils::to_checksum(&from, None),
 						Some(utils::to_checksum(&log.address, None)),
 						U256::zero(),
@@ -68,6 +71,7 @@ impl EvmModuleTrait for EvmTokenBalance {
 						self.network_id,
 						block_height,
 						&tx.hash.encode_hex(),
+						i as u64,
 						&utils::to_checksum(&to, None),
 						Some(utils::to_checksum(&log.address, None)),
 						amount,
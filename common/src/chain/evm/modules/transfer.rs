@@ -7,7 +7,10 @@ use ethers::{
 use eyre::Result;
 
 use crate::{
-	chain::{evm::modules::EvmModuleTrait, Evm, ModuleId, ModuleTrait, WarehouseData, U256},
+	chain::{
+		evm::{modules::EvmModuleTrait, BlockFees, InternalTransfer},
+		Evm, ModuleId, ModuleTrait, WarehouseData, U256,
+	},
 	models::{PrimaryId, Transfer},
 	BlockHeight,
 };
@@ -35,6 +38,8 @@ impl EvmModuleTrait for EvmTransfer {
 		block_time: u32,
 		tx: Transaction,
 		_receipt: TransactionReceipt,
+		_internal_transfers: Vec<InternalTransfer>,
+		_block_fees: BlockFees,
 	) -> Result<WarehouseData> {
 		let mut ret = WarehouseData::new();
 
@@ -58,6 +63,7 @@ impl EvmModuleTrait for EvmTransfer {
 			self.network_id,
 			block_height,
 			&tx.hash.encode_hex(),
+			0,
 			&utils::to_checksum(&tx.from, None),
 			&utils::to_checksum(&tx.to.unwrap(), None),
 			None,
@@ -2,23 +2,33 @@ use async_trait::async_trait;
 use chrono::NaiveDateTime;
 use derive_more::Display;
 use eyre::Result;
-use std::{collections::HashSet, ops::AddAssign, sync::Arc};
+use std::{collections::HashSet, mem, ops::AddAssign, sync::Arc};
 use tokio::task::JoinSet;
 
 pub use crate::chain::bitcoin::Bitcoin;
 use crate::{
-	models::{Amount, Link, Network, Relation, Transfer},
-	utils, BlockHeight, PrimaryId, RateLimiter, Warehouse,
+	models::{Amount, DownstreamLink, Link, Network, Relation, Transfer},
+	utils, BlockHeight, PrimaryId, RateLimiter, Settings, Warehouse,
 };
 pub use evm::Evm;
+pub use solana::Solana;
+pub use tron::Tron;
 pub use u256::U256;
 
 pub mod bitcoin;
 pub mod evm;
+pub mod solana;
+pub mod tron;
 pub mod u256;
 
 pub type BoxedChain = Box<dyn ChainTrait>;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockHeader {
+	pub hash: String,
+	pub parent_hash: String,
+}
+
 #[repr(u16)]
 #[derive(Display, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ModuleId {
@@ -27,10 +37,22 @@ pub enum ModuleId {
 	BitcoinBalance = 103,
 	BitcoinRelationBalanceTransfer = 104,
 	BitcoinRelationNoChange = 105,
+	BitcoinRelationCoSpend = 106,
 	EvmTransfer = 201,
 	EvmBalance = 202,
 	EvmTokenTransfer = 203,
 	EvmTokenBalance = 204,
+	EvmInternalTransfer = 205,
+	EvmErc721Transfer = 206,
+	EvmErc1155Transfer = 207,
+	TronTransfer = 301,
+	TronBalance = 302,
+	TronTokenTransfer = 303,
+	TronTokenBalance = 304,
+	SolanaTransfer = 401,
+	SolanaBalance = 402,
+	SolanaTokenTransfer = 403,
+	SolanaTokenBalance = 404,
 }
 
 #[async_trait]
@@ -45,6 +67,7 @@ pub trait ChainTrait: Send + Sync {
 	fn get_rate_limiter(&self) -> Option<Arc<RateLimiter>>;
 
 	async fn get_block_height(&self) -> Result<BlockHeight>;
+	async fn get_block_header(&self, block_height: BlockHeight) -> Result<Option<BlockHeader>>;
 
 	async fn process_block(
 		&self,
@@ -52,6 +75,22 @@ pub trait ChainTrait: Send + Sync {
 		modules: Vec<ModuleId>,
 	) -> Result<Option<WarehouseData>>;
 
+	// which module's balances can be reconciled against `get_balance` for this asset (none by
+	// default)
+	fn get_balance_module_id(&self, _asset_address: &str) -> Option<ModuleId> {
+		None
+	}
+
+	// balance as reported by the node itself at `block_height` (`None` if it cannot be fetched)
+	async fn get_balance(
+		&self,
+		_block_height: BlockHeight,
+		_address: &str,
+		_asset_address: &str,
+	) -> Result<Option<U256>> {
+		Ok(None)
+	}
+
 	async fn rate_limit(&self) {
 		if let Some(rate_limiter) = &self.get_rate_limiter() {
 			rate_limiter.until_ready().await;
@@ -74,6 +113,7 @@ pub struct WarehouseData {
 	pub amounts: HashSet<Amount>,
 	pub relations: HashSet<Relation>,
 	pub links: HashSet<Link>,
+	pub downstream_links: HashSet<DownstreamLink>,
 }
 
 impl WarehouseData {
@@ -82,52 +122,66 @@ impl WarehouseData {
 	}
 
 	pub fn len(&self) -> usize {
-		self.transfers.len() + self.amounts.len() + self.relations.len() + self.links.len()
+		self.transfers.len() +
+			self.amounts.len() +
+			self.relations.len() +
+			self.links.len() +
+			self.downstream_links.len()
 	}
 
 	pub fn is_empty(&self) -> bool {
 		self.len() == 0
 	}
 
-	pub fn should_commit(&self, force: bool) -> bool {
-		let (min_secs, max_secs) = (1, 10);
-
+	// flush when forced, when rows have been sitting around for long enough, or when the buffer
+	// is over the limit (but no more than once a second)
+	pub fn should_commit(&self, force: bool, settings: &Settings) -> bool {
 		let manually_required = force && !self.is_empty();
-		let lengthy_break = utils::ago_in_seconds(max_secs) > self.saved_at && !self.is_empty();
-		let buffer_is_full = utils::ago_in_seconds(min_secs) > self.saved_at && self.len() > 50_000;
+		let lengthy_break = utils::ago_in_seconds(settings.warehouse_buffer_timeout) >
+			self.saved_at &&
+			!self.is_empty();
+		let buffer_is_full =
+			utils::ago_in_seconds(1) > self.saved_at && self.len() > settings.warehouse_buffer_size;
 
 		manually_required || lengthy_break || buffer_is_full
 	}
 
+	// rows are moved out of the buffer & streamed to the warehouse as they're inserted, so
+	// nothing is held in memory twice
 	pub async fn commit(&mut self, warehouse: Arc<Warehouse>) -> Result<()> {
+		// the buffer is only emptied once every insert went through, so a failed commit can be
+		// retried with the same rows
+		let Self { transfers, amounts, relations, links, downstream_links, .. } = self.clone();
 		let mut set = JoinSet::new();
 
-		if !self.transfers.is_empty() {
+		if !transfers.is_empty() {
 			set.spawn({
 				let w = warehouse.clone();
-				let t = self.transfers.clone().into_iter().collect();
-				async move { Transfer::create_many(&w, t).await }
+				async move { Transfer::create_many(&w, transfers).await }
 			});
 		}
-		if !self.amounts.is_empty() {
+		if !amounts.is_empty() {
 			set.spawn({
 				let w = warehouse.clone();
-				let a = self.amounts.clone().into_iter().collect();
-				async move { Amount::create_many(&w, a).await }
+				async move { Amount::create_many(&w, amounts).await }
 			});
 		}
-		if !self.relations.is_empty() {
+		if !relations.is_empty() {
 			set.spawn({
 				let w = warehouse.clone();
-				let r = self.relations.clone().into_iter().collect();
-				async move { Relation::create_many(&w, r).await }
+				async move { Relation::create_many(&w, relations).await }
 			});
 		}
-		if !self.links.is_empty() {
+		if !links.is_empty() {
 			set.spawn({
 				let w = warehouse.clone();
-				let l = self.links.clone().into_iter().collect();
-				async move { Link::create_many(&w, l).await }
+				async move { Link::create_many(&w, links).await }
+			});
+		}
+		if !downstream_links.is_empty() {
+			set.spawn({
+				let w = warehouse.clone();
+				async move { DownstreamLink::create_many(&w, downstream_links).await }
 			});
 		}
 
@@ -135,17 +189,24 @@ impl WarehouseData {
 			res??;
 		}
 
-		self.clear();
+		self.take();
+
 		Ok(())
 	}
 
-	pub fn clear(&mut self) {
-		self.saved_at = utils::now();
+	// hand over buffered rows, leaving an empty buffer behind
+	pub fn take(&mut self) -> Self {
+		mem::replace(self, Self::new())
+	}
+
+	pub fn truncate(&mut self, network_id: PrimaryId, block_height: BlockHeight) {
+		let nid = network_id as u64;
 
-		self.transfers.clear();
-		self.amounts.clear();
-		self.relations.clear();
-		self.links.clear();
+		self.transfers.retain(|t| t.network_id != nid || t.block_height <= block_height);
+		self.amounts.retain(|a| a.network_id != nid || a.block_height <= block_height);
+		self.relations.retain(|r| r.network_id != nid || r.block_height <= block_height);
+		self.links.retain(|l| l.network_id != nid || l.block_height <= block_height);
+		self.downstream_links.retain(|l| l.network_id != nid || l.block_height <= block_height);
 	}
 }
 
@@ -155,5 +216,6 @@ impl AddAssign for WarehouseData {
 		self.amounts.extend(rhs.amounts);
 		self.relations.extend(rhs.relations);
 		self.links.extend(rhs.links);
+		self.downstream_links.extend(rhs.downstream_links);
 	}
 }
@@ -0,0 +1,219 @@
+use derive_more::{Display, Error};
+use eyre::Result;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::time::{sleep, Duration};
+
+// source: `https://github.com/solana-labs/solana/blob/master/rpc-client-api/src/custom_error.rs`
+const RPC_BLOCK_NOT_AVAILABLE: i64 = -32004;
+const RPC_NODE_UNHEALTHY: i64 = -32005;
+const RPC_SLOT_SKIPPED: i64 = -32007;
+const RPC_LONG_TERM_STORAGE_SLOT_SKIPPED: i64 = -32009;
+
+const RETRY_ATTEMPTS: u32 = 13;
+const RPC_TIMEOUT: u64 = 250;
+
+#[derive(Debug, Display, Error)]
+pub enum ClientError {
+	#[display(fmt = "{message}")]
+	General { message: String },
+	#[display(fmt = "Could not connect to rpc endpoint")]
+	Connection,
+	#[display(fmt = "RPC error: {message}")]
+	Rpc { message: String },
+	#[display(fmt = "Nonce mismatch")]
+	NonceMismatch,
+	#[display(fmt = "Slot was skipped")]
+	SlotSkipped,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+	code: i64,
+	message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Response {
+	#[serde(default)]
+	result: JsonValue,
+	error: Option<RpcError>,
+	id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Block {
+	pub blockhash: String,
+	pub previous_blockhash: String,
+	pub block_time: Option<i64>,
+	#[serde(default)]
+	pub transactions: Vec<TransactionWithMeta>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TransactionWithMeta {
+	pub transaction: Transaction,
+	pub meta: Option<TransactionMeta>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Transaction {
+	pub signatures: Vec<String>,
+	pub message: Message,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+	pub account_keys: Vec<AccountKey>,
+	#[serde(default)]
+	pub instructions: Vec<Instruction>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AccountKey {
+	pub pubkey: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Instruction {
+	#[serde(default)]
+	pub program: String,
+	#[serde(default)]
+	pub parsed: JsonValue,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ParsedInstruction {
+	#[serde(rename = "type")]
+	pub instruction_type: String,
+	pub info: JsonValue,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionMeta {
+	pub err: Option<JsonValue>,
+	#[serde(default)]
+	pub pre_balances: Vec<u64>,
+	#[serde(default)]
+	pub post_balances: Vec<u64>,
+	#[serde(default)]
+	pub pre_token_balances: Vec<TokenBalance>,
+	#[serde(default)]
+	pub post_token_balances: Vec<TokenBalance>,
+	#[serde(default)]
+	pub inner_instructions: Vec<InnerInstructions>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenBalance {
+	pub account_index: usize,
+	pub mint: String,
+	pub owner: Option<String>,
+	pub ui_token_amount: TokenAmount,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TokenAmount {
+	pub amount: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InnerInstructions {
+	pub instructions: Vec<Instruction>,
+}
+
+pub struct Client {
+	url: String,
+	id: AtomicUsize,
+	with_retry: bool,
+}
+
+impl Client {
+	pub fn new(url: &str) -> Self {
+		Self { url: url.to_string(), id: AtomicUsize::new(1), with_retry: true }
+	}
+
+	pub fn new_without_retry(url: &str) -> Self {
+		Self { url: url.to_string(), id: AtomicUsize::new(1), with_retry: false }
+	}
+
+	pub async fn get_slot(&self) -> Result<u64> {
+		let result = self.request("getSlot", &[json!({ "commitment": "finalized" })]).await?;
+		Ok(serde_json::from_value(result)?)
+	}
+
+	pub async fn get_block(&self, slot: u64, with_transactions: bool) -> Result<Option<Block>> {
+		let config = json!({
+			"commitment": "finalized",
+			"encoding": "jsonParsed",
+			"maxSupportedTransactionVersion": 0,
+			"transactionDetails": if with_transactions { "full" } else { "none" },
+			"rewards": false,
+		});
+
+		match self.request("getBlock", &[slot.into(), config]).await {
+			Ok(result) => Ok(Some(serde_json::from_value(result)?)),
+			Err(e) if matches!(e.downcast_ref(), Some(ClientError::SlotSkipped)) => Ok(None),
+			Err(e) => Err(e),
+		}
+	}
+
+	async fn request(&self, method: &str, params: &[JsonValue]) -> Result<JsonValue> {
+		let client = reqwest::Client::new();
+		let req = client.post(&self.url);
+
+		let retry_attempts = if self.with_retry { RETRY_ATTEMPTS } else { 1 };
+
+		for attempt in 0..retry_attempts {
+			let id = self.id.fetch_add(1, Ordering::Relaxed).to_string();
+			let timeout = Duration::from_millis(RPC_TIMEOUT * 2_i32.pow(attempt) as u64);
+
+			let body = json!({
+				"jsonrpc": "2.0",
+				"method": method,
+				"params": params,
+				"id": id,
+			});
+
+			match req.try_clone().unwrap().json(&body).send().await {
+				Ok(response) => {
+					let json = response.json::<Response>().await?;
+					match json.error {
+						Some(error)
+							if error.code == RPC_BLOCK_NOT_AVAILABLE ||
+								error.code == RPC_NODE_UNHEALTHY =>
+						{
+							sleep(timeout).await;
+							continue;
+						}
+						Some(error)
+							if error.code == RPC_SLOT_SKIPPED ||
+								error.code == RPC_LONG_TERM_STORAGE_SLOT_SKIPPED =>
+						{
+							return Err(ClientError::SlotSkipped.into())
+						}
+						Some(error) => {
+							return Err(ClientError::Rpc { message: error.message }.into())
+						}
+						None if json.id.is_none() || json.id.unwrap() != id => {
+							return Err(ClientError::NonceMismatch.into())
+						}
+						None => return Ok(json.result),
+					}
+				}
+				Err(e) if e.is_connect() => {
+					sleep(timeout).await;
+					continue;
+				}
+				Err(e) => return Err(ClientError::General { message: e.to_string() }.into()),
+			}
+		}
+
+		Err(ClientError::Connection.into())
+	}
+}
@@ -0,0 +1,502 @@
+use async_trait::async_trait;
+use eyre::Result;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::RwLock;
+
+use crate::{
+	chain::{BlockHeader, ChainTrait, ModuleId, ModuleTrait, WarehouseData, U256},
+	models::Network,
+	utils, BlockHeight, Cache, RateLimiter,
+};
+use client::{Client, ParsedInstruction, TokenBalance, TransactionWithMeta};
+use modules::{
+	SolanaBalance, SolanaModuleTrait, SolanaTokenBalance, SolanaTokenTransfer, SolanaTransfer,
+};
+
+mod client;
+mod modules;
+
+static SYSTEM_PROGRAM: &str = "system";
+static TOKEN_PROGRAMS: [&str; 2] = ["spl-token", "spl-token-2022"];
+
+pub struct Solana {
+	_cache: Arc<RwLock<Cache>>,
+	network: Network,
+	rpc: Option<String>,
+	client: Option<Arc<Client>>,
+	rate_limiter: Option<Arc<RateLimiter>>,
+	modules: Vec<Box<dyn SolanaModuleTrait>>,
+}
+
+impl Solana {
+	pub fn new(cache: Arc<RwLock<Cache>>, network: Network) -> Self {
+		let rps = network.rps as u32;
+		let network_id = network.network_id;
+
+		Self {
+			_cache: cache,
+			network,
+			rpc: None,
+			client: None,
+			rate_limiter: utils::get_rate_limiter(rps),
+			modules: vec![
+				Box::new(SolanaTransfer::new(network_id)),
+				Box::new(SolanaBalance::new(network_id)),
+				Box::new(SolanaTokenTransfer::new(network_id)),
+				Box::new(SolanaTokenBalance::new(network_id)),
+			],
+		}
+	}
+}
+
+#[async_trait]
+impl ChainTrait for Solana {
+	async fn connect(&mut self) -> Result<bool> {
+		let rpc_endpoints: Vec<String> =
+			serde_json::from_value(self.network.rpc_endpoints.clone())?;
+
+		for url in rpc_endpoints.into_iter() {
+			if let Some(rate_limiter) = &self.rate_limiter {
+				rate_limiter.until_ready().await;
+			}
+
+			let client = Client::new_without_retry(&url);
+			if client.get_slot().await.is_ok() {
+				self.client = Some(Arc::new(Client::new(&url)));
+				self.rpc = Some(url);
+
+				break;
+			}
+		}
+
+		Ok(self.is_connected())
+	}
+
+	fn is_connected(&self) -> bool {
+		self.client.is_some()
+	}
+
+	fn get_network(&self) -> Network {
+		self.network.clone()
+	}
+
+	fn get_rpc(&self) -> Option<String> {
+		self.rpc.clone()
+	}
+
+	fn get_module_ids(&self) -> Vec<ModuleId> {
+		self.modules.iter().map(|m| m.get_id()).collect()
+	}
+
+	fn get_rate_limiter(&self) -> Option<Arc<RateLimiter>> {
+		self.rate_limiter.clone()
+	}
+
+	fn format_address(&self, address: &str) -> String {
+		// base58 is case-sensitive, so there is nothing to normalize
+		address.trim().to_string()
+	}
+
+	// @NOTE block heights are slots; only finalized slots are requested, skipped ones are empty
+	async fn get_block_height(&self) -> Result<BlockHeight> {
+		self.rate_limit().await;
+		self.client.as_ref().unwrap().get_slot().await
+	}
+
+	async fn get_block_header(&self, block_height: BlockHeight) -> Result<Option<BlockHeader>> {
+		self.rate_limit().await;
+		Ok(self.client.as_ref().unwrap().get_block(block_height, false).await?.map(|block| {
+			BlockHeader { hash: block.blockhash, parent_hash: block.previous_blockhash }
+		}))
+	}
+
+	async fn process_block(
+		&self,
+		block_height: BlockHeight,
+		module_ids: Vec<ModuleId>,
+	) -> Result<Option<WarehouseData>> {
+		let mut warehouse_data = WarehouseData::new();
+
+		self.rate_limit().await;
+		if let Some(block) = self.client.as_ref().unwrap().get_block(block_height, true).await? {
+			let block_time = block.block_time.unwrap_or_default() as u32;
+
+			for tx in block.transactions.into_iter() {
+				// skip if there's nothing to compare balances against
+				if tx.meta.is_none() || tx.transaction.signatures.is_empty() {
+					continue;
+				}
+
+				warehouse_data += self
+					.process_transaction(block_height, block_time, tx, module_ids.clone())
+					.await?;
+			}
+		}
+
+		Ok(Some(warehouse_data))
+	}
+}
+
+impl Solana {
+	async fn process_transaction(
+		&self,
+		block_height: BlockHeight,
+		block_time: u32,
+		tx: TransactionWithMeta,
+		module_ids: Vec<ModuleId>,
+	) -> Result<WarehouseData> {
+		let mut ret = WarehouseData::new();
+
+		for module in self.modules.iter().filter(|m| module_ids.contains(&m.get_id())) {
+			ret += module.run(self, block_height, block_time, tx.clone()).await?;
+		}
+
+		Ok(ret)
+	}
+
+	fn is_successful(tx: &TransactionWithMeta) -> bool {
+		matches!(&tx.meta, Some(meta) if meta.err.is_none())
+	}
+
+	fn get_instructions(tx: &TransactionWithMeta) -> Vec<(String, ParsedInstruction)> {
+		let mut instructions = tx.transaction.message.instructions.clone();
+		if let Some(meta) = &tx.meta {
+			for inner_instructions in meta.inner_instructions.iter() {
+				instructions.extend(inner_instructions.instructions.clone());
+			}
+		}
+
+		// unparsed (unknown program) instructions are skipped
+		instructions
+			.into_iter()
+			.filter_map(|i| Some((i.program, serde_json::from_value(i.parsed).ok()?)))
+			.collect()
+	}
+
+	// token account -> (owner, mint)
+	fn get_token_accounts(tx: &TransactionWithMeta) -> HashMap<String, (String, String)> {
+		let mut ret = HashMap::new();
+
+		if let Some(meta) = &tx.meta {
+			for balance in meta.pre_token_balances.iter().chain(meta.post_token_balances.iter()) {
+				if let Some(account) =
+					tx.transaction.message.account_keys.get(balance.account_index)
+				{
+					let owner = balance.owner.clone().unwrap_or_else(|| account.pubkey.clone());
+					ret.insert(account.pubkey.clone(), (owner, balance.mint.clone()));
+				}
+			}
+		}
+
+		ret
+	}
+
+	// native transfers as (from, to, amount)
+	fn get_transfers(tx: &TransactionWithMeta) -> Vec<(String, String, U256)> {
+		let mut ret = vec![];
+
+		if !Self::is_successful(tx) {
+			return ret;
+		}
+
+		for (program, instruction) in Self::get_instructions(tx).into_iter() {
+			if program == SYSTEM_PROGRAM &&
+				["transfer", "transferWithSeed"].contains(&instruction.instruction_type.as_str())
+			{
+				let info = instruction.info;
+				if let (Some(from), Some(to), Some(lamports)) = (
+					info["source"].as_str(),
+					info["destination"].as_str(),
+					info["lamports"].as_u64(),
+				) {
+					ret.push((from.to_string(), to.to_string(), U256::from(lamports)));
+				}
+			}
+		}
+
+		ret
+	}
+
+	// token transfers as (from owner, to owner, mint, amount)
+	fn get_token_transfers(tx: &TransactionWithMeta) -> Vec<(String, String, String, U256)> {
+		let mut ret = vec![];
+
+		if !Self::is_successful(tx) {
+			return ret;
+		}
+
+		let token_accounts = Self::get_token_accounts(tx);
+		for (program, instruction) in Self::get_instructions(tx).into_iter() {
+			if !TOKEN_PROGRAMS.contains(&program.as_str()) {
+				continue;
+			}
+
+			let info = instruction.info;
+			let amount = match instruction.instruction_type.as_str() {
+				"transfer" => info["amount"].as_str(),
+				"transferChecked" => info["tokenAmount"]["amount"].as_str(),
+				_ => None,
+			};
+
+			if let (Some(source), Some(destination), Some(amount)) =
+				(info["source"].as_str(), info["destination"].as_str(), amount)
+			{
+				let (from, source_mint) = token_accounts
+					.get(source)
+					.cloned()
+					.unwrap_or_else(|| (source.to_string(), String::new()));
+				let (to, destination_mint) = token_accounts
+					.get(destination)
+					.cloned()
+					.unwrap_or_else(|| (destination.to_string(), String::new()));
+
+				let mint = match info["mint"].as_str() {
+					Some(mint) => mint.to_string(),
+					None if !source_mint.is_empty() => source_mint,
+					None => destination_mint,
+				};
+
+				if let (false, Ok(amount)) = (mint.is_empty(), U256::from_dec_str(amount)) {
+					ret.push((from, to, mint, amount));
+				}
+			}
+		}
+
+		ret
+	}
+
+	// native balance changes as (address, amount in, amount out)
+	fn get_balance_changes(tx: &TransactionWithMeta) -> Vec<(String, U256, U256)> {
+		let mut ret = vec![];
+
+		if let Some(meta) = &tx.meta {
+			for (i, account) in tx.transaction.message.account_keys.iter().enumerate() {
+				let pre = meta.pre_balances.get(i).copied().unwrap_or_default();
+				let post = meta.post_balances.get(i).copied().unwrap_or_default();
+
+				if post > pre {
+					ret.push((account.pubkey.clone(), U256::from(post - pre), U256::zero()));
+				} else if pre > post {
+					ret.push((account.pubkey.clone(), U256::zero(), U256::from(pre - post)));
+				}
+			}
+		}
+
+		ret
+	}
+
+	// token balance changes as (owner, mint, amount in, amount out)
+	fn get_token_balance_changes(tx: &TransactionWithMeta) -> Vec<(String, String, U256, U256)> {
+		let mut ret = vec![];
+
+		if let Some(meta) = &tx.meta {
+			let mut balances = HashMap::<(String, String), (U256, U256)>::new();
+
+			let mut add = |token_balance: &TokenBalance, is_post: bool| {
+				let account = tx.transaction.message.account_keys.get(token_balance.account_index);
+				let owner = match (&token_balance.owner, account) {
+					(Some(owner), _) => owner.clone(),
+					(None, Some(account)) => account.pubkey.clone(),
+					_ => return,
+				};
+
+				let amount =
+					U256::from_dec_str(&token_balance.ui_token_amount.amount).unwrap_or_default();
+				let entry = balances.entry((owner, token_balance.mint.clone())).or_default();
+				if is_post {
+					entry.1 += amount;
+				} else {
+					entry.0 += amount;
+				}
+			};
+
+			meta.pre_token_balances.iter().for_each(|b| add(b, false));
+			meta.post_token_balances.iter().for_each(|b| add(b, true));
+
+			for ((owner, mint), (pre, post)) in balances.into_iter() {
+				if post > pre {
+					ret.push((owner, mint, post - pre, U256::zero()));
+				} else if pre > post {
+					ret.push((owner, mint, U256::zero(), pre - post));
+				}
+			}
+		}
+
+		ret
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::{json, Value as JsonValue};
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpListener,
+	};
+
+	// minimal json-rpc server that answers each method with a canned result (or error)
+	async fn mock_rpc(responses: HashMap<&'static str, JsonValue>) -> String {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = format!("http://{}", listener.local_addr().unwrap());
+
+		tokio::spawn(async move {
+			loop {
+				let (mut socket, _) = listener.accept().await.unwrap();
+				let responses = responses.clone();
+
+				tokio::spawn(async move {
+					let mut buf = vec![];
+					let body = loop {
+						let mut chunk = [0; 4096];
+						let n = socket.read(&mut chunk).await.unwrap();
+						buf.extend_from_slice(&chunk[..n]);
+
+						let request = String::from_utf8_lossy(&buf).to_string();
+						if let Some((head, body)) = request.split_once("\r\n\r\n") {
+							let content_length = head
+								.lines()
+								.find_map(|l| {
+									l.to_lowercase()
+										.strip_prefix("content-length: ")
+										.map(|v| v.parse::<usize>().unwrap())
+								})
+								.unwrap_or_default();
+
+							if body.len() >= content_length {
+								break body.to_string();
+							}
+						}
+					};
+
+					let request: JsonValue = serde_json::from_str(&body).unwrap();
+					let mut response = json!({ "jsonrpc": "2.0", "id": request["id"] });
+					match &responses[request["method"].as_str().unwrap()] {
+						JsonValue::Object(o) if o.contains_key("code") => {
+							response["error"] = JsonValue::Object(o.clone())
+						}
+						result => response["result"] = result.clone(),
+					}
+
+					let response = response.to_string();
+					let http = format!(
+						"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+						 Content-Length: {}\r\nConnection: close\r\n\r\n{response}",
+						response.len()
+					);
+					socket.write_all(http.as_bytes()).await.unwrap();
+				});
+			}
+		});
+
+		url
+	}
+
+	fn mock_block() -> JsonValue {
+		json!({
+			"blockhash": "EkSnNWid2cvwEVnVx9aBqawnmiCNiDgp3gUdkDPTKN1N",
+			"previousBlockhash": "5xkKhnB8sDPAqyMz5DQqxwKTtybETdXqAf3J1YH4NAYN",
+			"blockTime": 1_676_000_000,
+			"transactions": [{
+				"transaction": {
+					"signatures": ["sig1"],
+					"message": {
+						"accountKeys": [
+							{ "pubkey": "alice" },
+							{ "pubkey": "bob" },
+							{ "pubkey": "alice_usdc" },
+							{ "pubkey": "bob_usdc" },
+						],
+						"instructions": [{
+							"program": "system",
+							"parsed": {
+								"type": "transfer",
+								"info": { "source": "alice", "destination": "bob", "lamports": 1_000 },
+							},
+						}],
+					},
+				},
+				"meta": {
+					"err": null,
+					"preBalances": [10_000, 0, 2_039_280, 2_039_280],
+					"postBalances": [8_995, 1_000, 2_039_280, 2_039_280],
+					"preTokenBalances": [
+						{ "accountIndex": 2, "mint": "usdc", "owner": "alice", "uiTokenAmount": { "amount": "500" } },
+					],
+					"postTokenBalances": [
+						{ "accountIndex": 2, "mint": "usdc", "owner": "alice", "uiTokenAmount": { "amount": "200" } },
+						{ "accountIndex": 3, "mint": "usdc", "owner": "bob", "uiTokenAmount": { "amount": "300" } },
+					],
+					"innerInstructions": [{
+						"instructions": [{
+							"program": "spl-token",
+							"parsed": {
+								"type": "transfer",
+								"info": { "source": "alice_usdc", "destination": "bob_usdc", "amount": "300" },
+							},
+						}],
+					}],
+				},
+			}],
+		})
+	}
+
+	#[tokio::test]
+	async fn test_client() {
+		let url =
+			mock_rpc(HashMap::from([("getSlot", json!(42)), ("getBlock", mock_block())])).await;
+
+		let client = Client::new(&url);
+		assert_eq!(client.get_slot().await.unwrap(), 42);
+
+		let block = client.get_block(42, true).await.unwrap().unwrap();
+		assert_eq!(block.previous_blockhash, "5xkKhnB8sDPAqyMz5DQqxwKTtybETdXqAf3J1YH4NAYN");
+		assert_eq!(block.transactions.len(), 1);
+
+		let skipped_url = mock_rpc(HashMap::from([(
+			"getBlock",
+			json!({ "code": -32007, "message": "Slot 42 was skipped" }),
+		)]))
+		.await;
+		assert!(Client::new(&skipped_url).get_block(42, true).await.unwrap().is_none());
+	}
+
+	#[test]
+	fn test_transfers_and_balances() {
+		let block: client::Block = serde_json::from_value(mock_block()).unwrap();
+		let tx = &block.transactions[0];
+
+		assert_eq!(
+			Solana::get_transfers(tx),
+			vec![("alice".to_string(), "bob".to_string(), U256::from(1_000))]
+		);
+		assert_eq!(
+			Solana::get_token_transfers(tx),
+			vec![("alice".to_string(), "bob".to_string(), "usdc".to_string(), U256::from(300))]
+		);
+		assert_eq!(
+			Solana::get_balance_changes(tx),
+			vec![
+				("alice".to_string(), U256::zero(), U256::from(1_005)),
+				("bob".to_string(), U256::from(1_000), U256::zero()),
+			]
+		);
+
+		let mut token_balance_changes = Solana::get_token_balance_changes(tx);
+		token_balance_changes.sort();
+		assert_eq!(
+			token_balance_changes,
+			vec![
+				("alice".to_string(), "usdc".to_string(), U256::zero(), U256::from(300)),
+				("bob".to_string(), "usdc".to_string(), U256::from(300), U256::zero()),
+			]
+		);
+
+		// failed txs move no funds, but fees still change balances
+		let mut failed_tx = tx.clone();
+		failed_tx.meta.as_mut().unwrap().err = Some(json!({ "InstructionError": [0, "Custom"] }));
+		assert!(Solana::get_transfers(&failed_tx).is_empty());
+		assert!(Solana::get_token_transfers(&failed_tx).is_empty());
+		assert_eq!(Solana::get_balance_changes(&failed_tx).len(), 2);
+	}
+}
@@ -0,0 +1,27 @@
+use async_trait::async_trait;
+use eyre::Result;
+
+use crate::{
+	chain::{solana::client::TransactionWithMeta, ModuleTrait, Solana, WarehouseData},
+	BlockHeight,
+};
+pub use balance::SolanaBalance;
+pub use token_balance::SolanaTokenBalance;
+pub use token_transfer::SolanaTokenTransfer;
+pub use transfer::SolanaTransfer;
+
+mod balance;
+mod token_balance;
+mod token_transfer;
+mod transfer;
+
+#[async_trait]
+pub trait SolanaModuleTrait: ModuleTrait + Send + Sync {
+	async fn run(
+		&self,
+		solana: &Solana,
+		block_height: BlockHeight,
+		block_time: u32,
+		tx: TransactionWithMeta,
+	) -> Result<WarehouseData>;
+}
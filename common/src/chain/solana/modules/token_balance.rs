@@ -0,0 +1,57 @@
+use async_trait::async_trait;
+use eyre::Result;
+
+use crate::{
+	chain::{
+		solana::{client::TransactionWithMeta, modules::SolanaModuleTrait},
+		ChainTrait, ModuleId, ModuleTrait, Solana, WarehouseData,
+	},
+	models::{Amount, PrimaryId},
+	BlockHeight,
+};
+
+pub struct SolanaTokenBalance {
+	network_id: PrimaryId,
+}
+
+impl ModuleTrait for SolanaTokenBalance {
+	fn new(network_id: PrimaryId) -> Self {
+		Self { network_id }
+	}
+
+	fn get_id(&self) -> ModuleId {
+		ModuleId::SolanaTokenBalance
+	}
+}
+
+#[async_trait]
+impl SolanaModuleTrait for SolanaTokenBalance {
+	async fn run(
+		&self,
+		solana: &Solana,
+		block_height: BlockHeight,
+		block_time: u32,
+		tx: TransactionWithMeta,
+	) -> Result<WarehouseData> {
+		let mut ret = WarehouseData::new();
+
+		for (owner, mint, amount_in, amount_out) in
+			Solana::get_token_balance_changes(&tx).into_iter()
+		{
+			ret.amounts.insert(Amount::new(
+				self.get_id(),
+				self.network_id,
+				block_height,
+				&tx.transaction.signatures[0],
+				0,
+				&solana.format_address(&owner),
+				Some(solana.format_address(&mint)),
+				amount_in,
+				amount_out,
+				block_time,
+			));
+		}
+
+		Ok(ret)
+	}
+}
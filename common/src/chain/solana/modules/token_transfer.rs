@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+use eyre::Result;
+
+use crate::{
+	chain::{
+		solana::{client::TransactionWithMeta, modules::SolanaModuleTrait},
+		ChainTrait, ModuleId, ModuleTrait, Solana, WarehouseData,
+	},
+	models::{PrimaryId, Transfer},
+	BlockHeight,
+};
+
+pub struct SolanaTokenTransfer {
+	network_id: PrimaryId,
+}
+
+impl ModuleTrait for SolanaTokenTransfer {
+	fn new(network_id: PrimaryId) -> Self {
+		Self { network_id }
+	}
+
+	fn get_id(&self) -> ModuleId {
+		ModuleId::SolanaTokenTransfer
+	}
+}
+
+#[async_trait]
+impl SolanaModuleTrait for SolanaTokenTransfer {
+	async fn run(
+		&self,
+		solana: &Solana,
+		block_height: BlockHeight,
+		block_time: u32,
+		tx: TransactionWithMeta,
+	) -> Result<WarehouseData> {
+		let mut ret = WarehouseData::new();
+
+		// token accounts are resolved to their owners; the mint is the asset
+		for (i, (from, to, mint, amount)) in
+			Solana::get_token_transfers(&tx).into_iter().enumerate()
+		{
+			// skip if no asset transfer or sending to self
+			if amount.is_zero() || from == to {
+				continue;
+			}
+
+			ret.transfers.insert(Transfer::new(
+				self.get_id(),
+				self.network_id,
+				block_height,
+				&tx.transaction.signatures[0],
+				i as u64,
+				&solana.format_address(&from),
+				&solana.format_address(&to),
+				Some(solana.format_address(&mint)),
+				amount,
+				amount,
+				block_time,
+			));
+		}
+
+		Ok(ret)
+	}
+}
@@ -0,0 +1,163 @@
+use derive_more::{Display, Error};
+use eyre::Result;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value as JsonValue};
+use tokio::time::{sleep, Duration};
+
+const RETRY_ATTEMPTS: u32 = 13;
+const RPC_TIMEOUT: u64 = 250;
+
+#[derive(Debug, Display, Error)]
+pub enum ClientError {
+	#[display(fmt = "{message}")]
+	General { message: String },
+	#[display(fmt = "Could not connect to rpc endpoint")]
+	Connection,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Block {
+	#[serde(rename = "blockID", default)]
+	pub block_id: String,
+	#[serde(default)]
+	pub block_header: BlockHeader,
+	#[serde(default)]
+	pub transactions: Vec<Transaction>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BlockHeader {
+	#[serde(default)]
+	pub raw_data: BlockHeaderRawData,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BlockHeaderRawData {
+	#[serde(default)]
+	pub number: u64,
+	#[serde(rename = "parentHash", default)]
+	pub parent_hash: String,
+	#[serde(default)]
+	pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Transaction {
+	#[serde(rename = "txID")]
+	pub tx_id: String,
+	#[serde(default)]
+	pub ret: Vec<TransactionResult>,
+	#[serde(default)]
+	pub raw_data: TransactionRawData,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TransactionResult {
+	#[serde(rename = "contractRet", default)]
+	pub contract_ret: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TransactionRawData {
+	#[serde(default)]
+	pub contract: Vec<Contract>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Contract {
+	#[serde(rename = "type")]
+	pub contract_type: String,
+	pub parameter: ContractParameter,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ContractParameter {
+	pub value: JsonValue,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TransferContract {
+	pub owner_address: String,
+	pub to_address: String,
+	#[serde(default)]
+	pub amount: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TransactionInfo {
+	pub id: String,
+	#[serde(default)]
+	pub log: Vec<Log>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Log {
+	pub address: String,
+	#[serde(default)]
+	pub topics: Vec<String>,
+	#[serde(default)]
+	pub data: String,
+}
+
+// @NOTE talks to the node's http api (`/wallet/*`) since its json-rpc does not expose native txs
+pub struct Client {
+	url: String,
+	with_retry: bool,
+}
+
+impl Client {
+	pub fn new(url: &str) -> Self {
+		Self { url: url.trim_end_matches('/').to_string(), with_retry: true }
+	}
+
+	pub fn new_without_retry(url: &str) -> Self {
+		Self { url: url.trim_end_matches('/').to_string(), with_retry: false }
+	}
+
+	pub async fn get_now_block(&self) -> Result<Block> {
+		self.request("wallet/getnowblock", json!({})).await
+	}
+
+	pub async fn get_block_by_num(&self, block_height: u64) -> Result<Option<Block>> {
+		let block: Block =
+			self.request("wallet/getblockbynum", json!({ "num": block_height })).await?;
+		Ok((!block.block_id.is_empty()).then_some(block))
+	}
+
+	pub async fn get_transaction_info_by_block_num(
+		&self,
+		block_height: u64,
+	) -> Result<Vec<TransactionInfo>> {
+		let result: JsonValue = self
+			.request("wallet/gettransactioninfobyblocknum", json!({ "num": block_height }))
+			.await?;
+
+		// empty blocks come back as an empty object instead of an empty list
+		Ok(match result {
+			JsonValue::Array(_) => serde_json::from_value(result)?,
+			_ => vec![],
+		})
+	}
+
+	async fn request<T: DeserializeOwned>(&self, path: &str, body: JsonValue) -> Result<T> {
+		let client = reqwest::Client::new();
+		let req = client.post(format!("{}/{path}", self.url));
+
+		let retry_attempts = if self.with_retry { RETRY_ATTEMPTS } else { 1 };
+
+		for attempt in 0..retry_attempts {
+			let timeout = Duration::from_millis(RPC_TIMEOUT * 2_i32.pow(attempt) as u64);
+
+			match req.try_clone().unwrap().json(&body).send().await {
+				Ok(response) => return Ok(response.json::<T>().await?),
+				Err(e) if e.is_connect() || e.is_timeout() => {
+					sleep(timeout).await;
+					continue;
+				}
+				Err(e) => return Err(ClientError::General { message: e.to_string() }.into()),
+			}
+		}
+
+		Err(ClientError::Connection.into())
+	}
+}
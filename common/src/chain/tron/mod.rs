@@ -0,0 +1,330 @@
+use async_trait::async_trait;
+use bitcoin::util::base58;
+use ethers::utils::hex;
+use eyre::Result;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::RwLock;
+
+use crate::{
+	chain::{BlockHeader, ChainTrait, ModuleId, ModuleTrait, WarehouseData, U256},
+	models::Network,
+	utils, BlockHeight, Cache, RateLimiter,
+};
+use client::{Client, Log, Transaction, TransactionInfo, TransferContract};
+use modules::{TronBalance, TronModuleTrait, TronTokenBalance, TronTokenTransfer, TronTransfer};
+
+mod client;
+mod modules;
+
+static TRANSFER_FROM_TO_AMOUNT: &str =
+	"ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+// every mainnet & testnet address starts with this byte (hence the leading "T")
+const ADDRESS_PREFIX: u8 = 0x41;
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum TronTopic {
+	Unknown,
+	TokenTransfer(String, String, U256),
+}
+
+pub struct Tron {
+	_cache: Arc<RwLock<Cache>>,
+	network: Network,
+	rpc: Option<String>,
+	client: Option<Arc<Client>>,
+	rate_limiter: Option<Arc<RateLimiter>>,
+	modules: Vec<Box<dyn TronModuleTrait>>,
+}
+
+impl Tron {
+	pub fn new(cache: Arc<RwLock<Cache>>, network: Network) -> Self {
+		let rps = network.rps as u32;
+		let network_id = network.network_id;
+
+		Self {
+			_cache: cache,
+			network,
+			rpc: None,
+			client: None,
+			rate_limiter: utils::get_rate_limiter(rps),
+			modules: vec![
+				Box::new(TronTransfer::new(network_id)),
+				Box::new(TronBalance::new(network_id)),
+				Box::new(TronTokenTransfer::new(network_id)),
+				Box::new(TronTokenBalance::new(network_id)),
+			],
+		}
+	}
+}
+
+#[async_trait]
+impl ChainTrait for Tron {
+	async fn connect(&mut self) -> Result<bool> {
+		let rpc_endpoints: Vec<String> =
+			serde_json::from_value(self.network.rpc_endpoints.clone())?;
+
+		for url in rpc_endpoints.into_iter() {
+			if let Some(rate_limiter) = &self.rate_limiter {
+				rate_limiter.until_ready().await;
+			}
+
+			let client = Client::new_without_retry(&url);
+			if client.get_now_block().await.is_ok() {
+				self.rpc = Some(url.clone());
+				self.client = Some(Arc::new(Client::new(&url)));
+
+				break;
+			}
+		}
+
+		Ok(self.is_connected())
+	}
+
+	fn is_connected(&self) -> bool {
+		self.client.is_some()
+	}
+
+	fn get_network(&self) -> Network {
+		self.network.clone()
+	}
+
+	fn get_rpc(&self) -> Option<String> {
+		self.rpc.clone()
+	}
+
+	fn get_module_ids(&self) -> Vec<ModuleId> {
+		self.modules.iter().map(|m| m.get_id()).collect()
+	}
+
+	fn get_rate_limiter(&self) -> Option<Arc<RateLimiter>> {
+		self.rate_limiter.clone()
+	}
+
+	fn format_address(&self, address: &str) -> String {
+		Self::get_address(address)
+	}
+
+	async fn get_block_height(&self) -> Result<BlockHeight> {
+		self.rate_limit().await;
+		Ok(self.client.as_ref().unwrap().get_now_block().await?.block_header.raw_data.number)
+	}
+
+	async fn get_block_header(&self, block_height: BlockHeight) -> Result<Option<BlockHeader>> {
+		self.rate_limit().await;
+		Ok(self.client.as_ref().unwrap().get_block_by_num(block_height).await?.map(|block| {
+			BlockHeader {
+				hash: block.block_id,
+				parent_hash: block.block_header.raw_data.parent_hash,
+			}
+		}))
+	}
+
+	async fn process_block(
+		&self,
+		block_height: BlockHeight,
+		module_ids: Vec<ModuleId>,
+	) -> Result<Option<WarehouseData>> {
+		let mut ret = None;
+		let client = self.client.as_ref().unwrap();
+
+		self.rate_limit().await;
+		if let Some(block) = client.get_block_by_num(block_height).await? {
+			let mut warehouse_data = WarehouseData::new();
+
+			// logs are not part of the block, so fetch them all at once
+			let mut tx_infos = HashMap::new();
+			if !block.transactions.is_empty() {
+				self.rate_limit().await;
+				for tx_info in client.get_transaction_info_by_block_num(block_height).await? {
+					tx_infos.insert(tx_info.id.clone(), tx_info);
+				}
+			}
+
+			let block_time = (block.block_header.raw_data.timestamp / 1_000) as u32;
+			for tx in block.transactions.into_iter() {
+				// skip if tx failed or reverted
+				if tx.ret.first().map(|r| r.contract_ret.as_str()) != Some("SUCCESS") {
+					continue;
+				}
+
+				// process tx
+				let tx_info = tx_infos.remove(&tx.tx_id).unwrap_or_default();
+				warehouse_data += self
+					.process_transaction(block_height, block_time, tx, tx_info, module_ids.clone())
+					.await?;
+			}
+
+			ret = Some(warehouse_data);
+		}
+
+		Ok(ret)
+	}
+}
+
+impl Tron {
+	// base58check form of an address (left as is if it's not a valid tron address)
+	fn get_address(address: &str) -> String {
+		// already in base58 form
+		if let Ok(bytes) = base58::from_check(address) {
+			if bytes.len() == 21 && bytes[0] == ADDRESS_PREFIX {
+				return address.to_string();
+			}
+		}
+
+		// hex form, either `41…` or evm-style `0x…` (as found in event logs)
+		let hex_address = address.trim_start_matches("0x");
+		if let Ok(bytes) = hex::decode(hex_address) {
+			match bytes.len() {
+				20 => return base58::check_encode_slice(&[&[ADDRESS_PREFIX], &bytes[..]].concat()),
+				21 if bytes[0] == ADDRESS_PREFIX => return base58::check_encode_slice(&bytes),
+				_ => {}
+			}
+		}
+
+		address.to_string()
+	}
+
+	async fn process_transaction(
+		&self,
+		block_height: BlockHeight,
+		block_time: u32,
+		tx: Transaction,
+		tx_info: TransactionInfo,
+		module_ids: Vec<ModuleId>,
+	) -> Result<WarehouseData> {
+		let mut ret = WarehouseData::new();
+
+		for module in self.modules.iter().filter(|m| module_ids.contains(&m.get_id())) {
+			ret += module.run(self, block_height, block_time, tx.clone(), tx_info.clone()).await?;
+		}
+
+		Ok(ret)
+	}
+
+	fn get_transfer(tx: &Transaction) -> Result<Option<(String, String, U256)>> {
+		for contract in tx.raw_data.contract.iter() {
+			if contract.contract_type == "TransferContract" {
+				let transfer: TransferContract =
+					serde_json::from_value(contract.parameter.value.clone())?;
+
+				return Ok(Some((
+					Self::get_address(&transfer.owner_address),
+					Self::get_address(&transfer.to_address),
+					U256::from(transfer.amount),
+				)));
+			}
+		}
+
+		Ok(None)
+	}
+
+	fn get_topic(log: &Log) -> Result<TronTopic> {
+		if log.topics.len() == 3 &&
+			log.topics.iter().all(|t| t.len() == 64) &&
+			log.topics[0] == TRANSFER_FROM_TO_AMOUNT &&
+			log.data.len() >= 64
+		{
+			// topics are 32-byte words, addresses are the last 20 bytes
+			let from = Self::get_address(&log.topics[1][24..]);
+			let to = Self::get_address(&log.topics[2][24..]);
+			let amount = U256::from_big_endian(&hex::decode(&log.data[..64])?);
+
+			return Ok(TronTopic::TokenTransfer(from, to, amount));
+		}
+
+		Ok(TronTopic::Unknown)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	const USDT: &str = "TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t";
+	const USDT_HEX: &str = "a614f803b6fd780986a42c78ec9c7f77e6ded13c";
+
+	#[test]
+	fn test_get_address() {
+		assert_eq!(Tron::get_address(USDT), USDT);
+		assert_eq!(Tron::get_address(&format!("41{USDT_HEX}")), USDT);
+		assert_eq!(Tron::get_address(&format!("0x{USDT_HEX}")), USDT);
+		assert_eq!(Tron::get_address(USDT_HEX), USDT);
+
+		// bad checksum, wrong prefix & wrong length are not valid addresses
+		for address in [
+			"TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6u",
+			"42a614f803b6fd780986a42c78ec9c7f77e6ded13c",
+			"0xa614f803b6fd780986a42c78ec9c7f77e6ded1",
+			"not an address",
+		] {
+			assert_eq!(Tron::get_address(address), address);
+		}
+	}
+
+	#[test]
+	fn test_get_transfer() {
+		let tx: Transaction = serde_json::from_value(json!({
+			"txID": "abc",
+			"raw_data": {
+				"contract": [{
+					"type": "TransferContract",
+					"parameter": {
+						"value": {
+							"owner_address": format!("41{}", "11".repeat(20)),
+							"to_address": format!("41{}", "22".repeat(20)),
+							"amount": 1_000,
+						},
+					},
+				}],
+			},
+		}))
+		.unwrap();
+
+		assert_eq!(
+			Tron::get_transfer(&tx).unwrap(),
+			Some((
+				"TBXSw8fM4jpQkGc6zZjsVABFpVN7UvXPdV".to_string(),
+				"TD5gsCwxykWsLN9aPrq2TAfNjByuZKYp4E".to_string(),
+				U256::from(1_000)
+			))
+		);
+
+		let mut tx = tx;
+		tx.raw_data.contract[0].contract_type = "TriggerSmartContract".to_string();
+		assert_eq!(Tron::get_transfer(&tx).unwrap(), None);
+	}
+
+	#[test]
+	fn test_get_topic() {
+		let log = Log {
+			address: format!("41{USDT_HEX}"),
+			topics: vec![
+				TRANSFER_FROM_TO_AMOUNT.to_string(),
+				format!("{}{}", "0".repeat(24), "11".repeat(20)),
+				format!("{}{}", "0".repeat(24), "22".repeat(20)),
+			],
+			data: format!("{:064x}", 1_000_000),
+		};
+
+		assert_eq!(
+			Tron::get_topic(&log).unwrap(),
+			TronTopic::TokenTransfer(
+				"TBXSw8fM4jpQkGc6zZjsVABFpVN7UvXPdV".to_string(),
+				"TD5gsCwxykWsLN9aPrq2TAfNjByuZKYp4E".to_string(),
+				U256::from(1_000_000)
+			)
+		);
+
+		// erc-721 style transfers index the token id too, so they're not trc-20 transfers
+		let mut nft_log = log.clone();
+		nft_log.topics.push("0".repeat(64));
+		assert_eq!(Tron::get_topic(&nft_log).unwrap(), TronTopic::Unknown);
+
+		let mut approval_log = log;
+		approval_log.topics[0] =
+			"8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925".to_string();
+		assert_eq!(Tron::get_topic(&approval_log).unwrap(), TronTopic::Unknown);
+	}
+}
@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use eyre::Result;
+
+use crate::{
+	chain::{
+		tron::{
+			client::{Transaction, TransactionInfo},
+			modules::TronModuleTrait,
+		},
+		ModuleId, ModuleTrait, Tron, WarehouseData, U256,
+	},
+	models::{Amount, PrimaryId},
+	BlockHeight,
+};
+
+pub struct TronBalance {
+	network_id: PrimaryId,
+}
+
+impl ModuleTrait for TronBalance {
+	fn new(network_id: PrimaryId) -> Self {
+		Self { network_id }
+	}
+
+	fn get_id(&self) -> ModuleId {
+		ModuleId::TronBalance
+	}
+}
+
+#[async_trait]
+impl TronModuleTrait for TronBalance {
+	async fn run(
+		&self,
+		_tron: &Tron,
+		block_height: BlockHeight,
+		block_time: u32,
+		tx: Transaction,
+		_tx_info: TransactionInfo,
+	) -> Result<WarehouseData> {
+		let mut ret = WarehouseData::new();
+
+		if let Some((from, to, amount)) = Tron::get_transfer(&tx)? {
+			// skip if no asset transfer or sending to self
+			if amount.is_zero() || from == to {
+				return Ok(ret);
+			}
+
+			ret.amounts.insert(Amount::new(
+				self.get_id(),
+				self.network_id,
+				block_height,
+				&tx.tx_id,
+				0,
+				&from,
+				None,
+				U256::zero(),
+				amount,
+				block_time,
+			));
+			ret.amounts.insert(Amount::new(
+				self.get_id(),
+				self.network_id,
+				block_height,
+				&tx.tx_id,
+				0,
+				&to,
+				None,
+				amount,
+				U256::zero(),
+				block_time,
+			));
+		}
+
+		Ok(ret)
+	}
+}
@@ -0,0 +1,80 @@
+use async_trait::async_trait;
+use eyre::Result;
+
+use crate::{
+	chain::{
+		tron::{
+			client::{Transaction, TransactionInfo},
+			modules::TronModuleTrait,
+			TronTopic,
+		},
+		ChainTrait, ModuleId, ModuleTrait, Tron, WarehouseData, U256,
+	},
+	models::{Amount, PrimaryId},
+	BlockHeight,
+};
+
+pub struct TronTokenBalance {
+	network_id: PrimaryId,
+}
+
+impl ModuleTrait for TronTokenBalance {
+	fn new(network_id: PrimaryId) -> Self {
+		Self { network_id }
+	}
+
+	fn get_id(&self) -> ModuleId {
+		ModuleId::TronTokenBalance
+	}
+}
+
+#[async_trait]
+impl TronModuleTrait for TronTokenBalance {
+	async fn run(
+		&self,
+		tron: &Tron,
+		block_height: BlockHeight,
+		block_time: u32,
+		tx: Transaction,
+		tx_info: TransactionInfo,
+	) -> Result<WarehouseData> {
+		let mut ret = WarehouseData::new();
+
+		for (i, log) in tx_info.log.into_iter().enumerate() {
+			// process trc-20 `transfer` event
+			match Tron::get_topic(&log)? {
+				TronTopic::TokenTransfer(from, to, amount) if amount > U256::zero() => {
+					let asset_address = tron.format_address(&log.address);
+
+					ret.amounts.insert(Amount::new(
+						self.get_id(),
+						self.network_id,
+						block_height,
+						&tx.tx_id,
+						i as u64,
+						&from,
+						Some(asset_address.clone()),
+						U256::zero(),
+						amount,
+						block_time,
+					));
+					ret.amounts.insert(Amount::new(
+						self.get_id(),
+						self.network_id,
+						block_height,
+						&tx.tx_id,
+						i as u64,
+						&to,
+						Some(asset_address),
+						amount,
+						U256::zero(),
+						block_time,
+					));
+				}
+				_ => {}
+			}
+		}
+
+		Ok(ret)
+	}
+}
@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use eyre::Result;
+
+use crate::{
+	chain::{
+		tron::{
+			client::{Transaction, TransactionInfo},
+			modules::TronModuleTrait,
+			TronTopic,
+		},
+		ChainTrait, ModuleId, ModuleTrait, Tron, WarehouseData, U256,
+	},
+	models::{PrimaryId, Transfer},
+	BlockHeight,
+};
+
+pub struct TronTokenTransfer {
+	network_id: PrimaryId,
+}
+
+impl ModuleTrait for TronTokenTransfer {
+	fn new(network_id: PrimaryId) -> Self {
+		Self { network_id }
+	}
+
+	fn get_id(&self) -> ModuleId {
+		ModuleId::TronTokenTransfer
+	}
+}
+
+#[async_trait]
+impl TronModuleTrait for TronTokenTransfer {
+	async fn run(
+		&self,
+		tron: &Tron,
+		block_height: BlockHeight,
+		block_time: u32,
+		tx: Transaction,
+		tx_info: TransactionInfo,
+	) -> Result<WarehouseData> {
+		let mut ret = WarehouseData::new();
+
+		for (i, log) in tx_info.log.into_iter().enumerate() {
+			// process trc-20 `transfer` event
+			match Tron::get_topic(&log)? {
+				TronTopic::TokenTransfer(from, to, amount) if amount > U256::zero() => {
+					ret.transfers.insert(Transfer::new(
+						self.get_id(),
+						self.network_id,
+						block_height,
+						&tx.tx_id,
+						i as u64,
+						&from,
+						&to,
+						Some(tron.format_address(&log.address)),
+						amount,
+						amount,
+						block_time,
+					));
+				}
+				_ => {}
+			}
+		}
+
+		Ok(ret)
+	}
+}
@@ -0,0 +1,65 @@
+use async_trait::async_trait;
+use eyre::Result;
+
+use crate::{
+	chain::{
+		tron::{
+			client::{Transaction, TransactionInfo},
+			modules::TronModuleTrait,
+		},
+		ModuleId, ModuleTrait, Tron, WarehouseData,
+	},
+	models::{PrimaryId, Transfer},
+	BlockHeight,
+};
+
+pub struct TronTransfer {
+	network_id: PrimaryId,
+}
+
+impl ModuleTrait for TronTransfer {
+	fn new(network_id: PrimaryId) -> Self {
+		Self { network_id }
+	}
+
+	fn get_id(&self) -> ModuleId {
+		ModuleId::TronTransfer
+	}
+}
+
+#[async_trait]
+impl TronModuleTrait for TronTransfer {
+	async fn run(
+		&self,
+		_tron: &Tron,
+		block_height: BlockHeight,
+		block_time: u32,
+		tx: Transaction,
+		_tx_info: TransactionInfo,
+	) -> Result<WarehouseData> {
+		let mut ret = WarehouseData::new();
+
+		if let Some((from, to, amount)) = Tron::get_transfer(&tx)? {
+			// skip if no asset transfer or sending to self
+			if amount.is_zero() || from == to {
+				return Ok(ret);
+			}
+
+			ret.transfers.insert(Transfer::new(
+				self.get_id(),
+				self.network_id,
+				block_height,
+				&tx.tx_id,
+				0,
+				&from,
+				&to,
+				None,
+				amount,
+				amount,
+				block_time,
+			));
+		}
+
+		Ok(ret)
+	}
+}
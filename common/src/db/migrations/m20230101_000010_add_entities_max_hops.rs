@@ -0,0 +1,34 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		manager
+			.alter_table(
+				Table::alter()
+					.table(Entities::Table)
+					.add_column(ColumnDef::new(Entities::MaxHops).big_integer().null())
+					.to_owned(),
+			)
+			.await
+	}
+
+	async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		manager
+			.alter_table(
+				Table::alter().table(Entities::Table).drop_column(Entities::MaxHops).to_owned(),
+			)
+			.await
+	}
+}
+
+#[derive(Iden)]
+enum Entities {
+	#[iden = "entities"]
+	Table,
+	MaxHops,
+}
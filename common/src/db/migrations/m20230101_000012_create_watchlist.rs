@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		manager
+			.create_table(
+				Table::create()
+					.table(WatchedAddresses::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(WatchedAddresses::WatchlistId)
+							.big_integer()
+							.not_null()
+							.auto_increment()
+							.primary_key(),
+					)
+					.col(ColumnDef::new(WatchedAddresses::NetworkId).big_integer().not_null())
+					.col(ColumnDef::new(WatchedAddresses::Network).string().not_null())
+					.col(ColumnDef::new(WatchedAddresses::Id).unique_key().string().not_null())
+					.col(ColumnDef::new(WatchedAddresses::Address).string().not_null())
+					.col(ColumnDef::new(WatchedAddresses::Description).string().not_null())
+					.col(ColumnDef::new(WatchedAddresses::UpdatedAt).date_time().null())
+					.col(
+						ColumnDef::new(WatchedAddresses::CreatedAt)
+							.date_time()
+							.not_null()
+							.extra("DEFAULT CURRENT_TIMESTAMP".to_owned()),
+					)
+					.foreign_key(
+						&mut sea_query::ForeignKey::create()
+							.name("fk_watchlist_network_id")
+							.from(WatchedAddresses::Table, WatchedAddresses::NetworkId)
+							.to(Alias::new("networks"), Alias::new("network_id"))
+							.on_delete(ForeignKeyAction::Cascade)
+							.to_owned(),
+					)
+					.foreign_key(
+						&mut sea_query::ForeignKey::create()
+							.name("fk_watchlist_network")
+							.from(WatchedAddresses::Table, WatchedAddresses::Network)
+							.to(Alias::new("networks"), Alias::new("id"))
+							.on_update(ForeignKeyAction::Cascade)
+							.to_owned(),
+					)
+					.to_owned(),
+			)
+			.await?;
+
+		manager
+			.create_index(
+				Index::create()
+					.if_not_exists()
+					.name("ux_watchlist_network_id_address")
+					.table(WatchedAddresses::Table)
+					.unique()
+					.col(WatchedAddresses::NetworkId)
+					.col(WatchedAddresses::Address)
+					.to_owned(),
+			)
+			.await?;
+
+		// webhooks can be limited to links into watched addresses
+		manager
+			.alter_table(
+				Table::alter()
+					.table(Webhooks::Table)
+					.add_column(ColumnDef::new(Webhooks::Watchlist).boolean().null())
+					.to_owned(),
+			)
+			.await
+	}
+
+	async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		manager
+			.alter_table(
+				Table::alter().table(Webhooks::Table).drop_column(Webhooks::Watchlist).to_owned(),
+			)
+			.await?;
+
+		manager.drop_table(Table::drop().table(WatchedAddresses::Table).to_owned()).await
+	}
+}
+
+#[derive(Iden)]
+enum WatchedAddresses {
+	#[iden = "watchlist"]
+	Table,
+	WatchlistId,
+	NetworkId,
+	Network,
+	Id,
+	Address,
+	Description,
+	UpdatedAt,
+	CreatedAt,
+}
+
+#[derive(Iden)]
+enum Webhooks {
+	#[iden = "webhooks"]
+	Table,
+	Watchlist,
+}
@@ -8,6 +8,11 @@ mod m20230101_000005_create_entities;
 mod m20230101_000006_create_addresses;
 mod m20230101_000007_create_tags;
 mod m20230101_000008_create_entity_tags;
+mod m20230101_000009_add_networks_confirmations;
+mod m20230101_000010_add_entities_max_hops;
+mod m20230101_000011_create_webhooks;
+mod m20230101_000012_create_watchlist;
+mod m20230101_000013_add_tags_severity;
 
 pub struct Migrator;
 
@@ -22,6 +27,11 @@ impl MigratorTrait for Migrator {
 			Box::new(m20230101_000006_create_addresses::Migration),
 			Box::new(m20230101_000007_create_tags::Migration),
 			Box::new(m20230101_000008_create_entity_tags::Migration),
+			Box::new(m20230101_000009_add_networks_confirmations::Migration),
+			Box::new(m20230101_000010_add_entities_max_hops::Migration),
+			Box::new(m20230101_000011_create_webhooks::Migration),
+			Box::new(m20230101_000012_create_watchlist::Migration),
+			Box::new(m20230101_000013_add_tags_severity::Migration),
 		]
 	}
 }
@@ -12,7 +12,7 @@ pub enum AppError<'a> {
 	ServerStartup { url: String, error: String },
 
 	#[display(
-		fmt = "Barreleye requires Clickhouse to run. Provide the connection URL with \"--warehouse=<URL>\". Could not connect to warehouse @ `{url}`"
+		fmt = "Barreleye requires a warehouse to run. Provide the connection URL with \"--warehouse=<URL>\" (use \"file://\" for an embedded one). Could not connect to warehouse @ `{url}`"
 	)]
 	WarehouseConnection { url: String },
 
@@ -0,0 +1,26 @@
+use derive_more::Display;
+use serde::Serialize;
+
+pub const CHANNEL_CAPACITY: usize = 1_000;
+
+// real-time updates published by the indexer (only reach a server running in the same process)
+#[derive(Display, Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Event {
+	#[display(fmt = "upstream")]
+	#[serde(rename_all = "camelCase")]
+	Upstream {
+		network: String,
+		address: String,
+		entity: String,
+		from_address: String,
+		hops: usize,
+		block_height: u64,
+	},
+	#[display(fmt = "progress")]
+	#[serde(rename_all = "camelCase")]
+	Progress { network: String, confirmed_block_height: u64, sync: f64 },
+	#[display(fmt = "networks")]
+	#[serde(rename_all = "camelCase")]
+	Networks { networks: Vec<String> },
+}
@@ -21,15 +21,19 @@ use std::{
 		Arc,
 	},
 };
-use tokio::{sync::RwLock, time::Duration};
+use tokio::{
+	sync::{broadcast, RwLock},
+	time::Duration,
+};
 
 use crate::{
-	chain::{Bitcoin, BoxedChain, Evm},
+	chain::{Bitcoin, BoxedChain, Evm, Solana, Tron},
 	models::{Config, ConfigKey, Network, PrimaryId},
 };
 pub use cache::Cache;
 pub use db::Db;
 pub use errors::AppError;
+pub use events::Event;
 pub use progress::{Progress, ReadyType as ProgressReadyType, Step as ProgressStep};
 pub use settings::Settings;
 pub use warehouse::Warehouse;
@@ -38,9 +42,12 @@ pub mod cache;
 pub mod chain;
 pub mod db;
 pub mod errors;
+pub mod events;
 pub mod models;
 pub mod progress;
+pub mod risk;
 pub mod settings;
+pub mod taint;
 pub mod utils;
 pub mod warehouse;
 
@@ -69,6 +76,7 @@ pub struct App {
 	is_ready: Arc<AtomicBool>,
 	is_primary: Arc<AtomicBool>,
 	connected_at: Arc<RwLock<Option<NaiveDateTime>>>,
+	events: broadcast::Sender<Event>,
 }
 
 impl App {
@@ -88,6 +96,7 @@ impl App {
 			is_ready: Arc::new(AtomicBool::new(false)),
 			is_primary: Arc::new(AtomicBool::new(false)),
 			connected_at: Arc::new(RwLock::new(None)),
+			events: broadcast::channel(events::CHANNEL_CAPACITY).0,
 		};
 
 		app.networks = Arc::new(RwLock::new(app.get_networks().await?));
@@ -115,6 +124,8 @@ impl App {
 			let boxed_chain: BoxedChain = match n.blockchain {
 				Blockchain::Bitcoin => Box::new(Bitcoin::new(c, n)),
 				Blockchain::Evm => Box::new(Evm::new(c, n)),
+				Blockchain::Tron => Box::new(Tron::new(c, n)),
+				Blockchain::Solana => Box::new(Solana::new(c, n)),
 			};
 
 			ret.insert(network_id, Arc::new(boxed_chain));
@@ -164,6 +175,8 @@ impl App {
 					let mut boxed_chain: BoxedChain = match n.blockchain {
 						Blockchain::Bitcoin => Box::new(Bitcoin::new(c, n.clone())),
 						Blockchain::Evm => Box::new(Evm::new(c, n.clone())),
+						Blockchain::Tron => Box::new(Tron::new(c, n.clone())),
+						Blockchain::Solana => Box::new(Solana::new(c, n.clone())),
 					};
 
 					async move {
@@ -223,8 +236,8 @@ impl App {
 		} else {
 			warnings.extend(
 				networks
-					.iter()
-					.filter_map(|(_, chain)| {
+					.values()
+					.filter_map(|chain| {
 						if self.settings.is_indexer && chain.get_network().rps == 0 {
 							Some(format!(
 								"{} rpc requests are not rate-limited",
@@ -266,6 +279,19 @@ impl App {
 		Ok(())
 	}
 
+	pub fn publish(&self, event: Event) {
+		// nobody might be listening, which is fine
+		let _ = self.events.send(event);
+	}
+
+	pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+		self.events.subscribe()
+	}
+
+	pub fn has_subscribers(&self) -> bool {
+		self.events.receiver_count() > 0
+	}
+
 	pub async fn format_address(&self, address: &str) -> Result<String> {
 		for (_, chain) in self.networks.read().await.iter() {
 			let formatted_address = chain.format_address(address);
@@ -300,6 +326,10 @@ pub enum IdPrefix {
 	Address,
 	#[display(fmt = "tag")]
 	Tag,
+	#[display(fmt = "whk")]
+	Webhook,
+	#[display(fmt = "wch")]
+	Watchlist,
 }
 
 #[derive(
@@ -340,6 +370,10 @@ pub enum Blockchain {
 	Bitcoin = 1,
 	#[serde(rename = "evm")]
 	Evm = 2,
+	#[serde(rename = "tron")]
+	Tron = 3,
+	#[serde(rename = "solana")]
+	Solana = 4,
 }
 
 pub fn quit(app_error: AppError) -> ! {
@@ -1,17 +1,25 @@
 use clickhouse::Row;
 use eyre::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
 
 use crate::{
 	chain::{u256, ModuleId, U256},
 	models::{PrimaryId, PrimaryIds},
 	warehouse::Warehouse,
+	BlockHeight,
 };
 
 pub static TABLE: &str = "amounts";
 
+// namespace for amount uuids (which are v5, derived from the amount itself)
+static UUID_NAMESPACE: Uuid = Uuid::from_u128(0x9b4e2d71_0c3a_4f86_a2d5_6e1f7c380b94);
+
 #[derive(PartialEq, Eq, Hash, Debug, Clone, Row, Serialize, Deserialize)]
 pub struct Model {
+	#[serde(with = "clickhouse::serde::uuid")]
+	pub uuid: Uuid,
 	pub module_id: u16,
 	pub network_id: u64,
 	pub block_height: u64,
@@ -33,6 +41,7 @@ impl Model {
 		network_id: PrimaryId,
 		block_height: u64,
 		tx_hash: &str,
+		position: u64,
 		address: &str,
 		asset_address: Option<String>,
 		amount_in: U256,
@@ -40,6 +49,7 @@ impl Model {
 		created_at: u32,
 	) -> Self {
 		Self {
+			uuid: Uuid::nil(),
 			module_id: module_id as u16,
 			network_id: network_id as u64,
 			block_height,
@@ -50,61 +60,76 @@ impl Model {
 			amount_out,
 			created_at,
 		}
+		.with_uuid(position)
 	}
 
-	pub async fn create_many(warehouse: &Warehouse, models: Vec<Self>) -> Result<()> {
-		let mut insert = warehouse.get().insert(TABLE)?;
-		for model in models.into_iter() {
-			insert.write(&model).await?;
-		}
+	// same idea as transfer uuids: re-indexing a block produces the exact same amounts, so
+	// they can be written more than once without being counted twice. `position` tells apart
+	// otherwise identical amounts within a tx (eg two equal token transfers)
+	fn with_uuid(self, position: u64) -> Self {
+		let name = format!(
+			"{}:{}:{}:{}:{}:{}:{}:{}:{}",
+			self.module_id,
+			self.network_id,
+			self.block_height,
+			self.tx_hash,
+			position,
+			self.address,
+			self.asset_address,
+			self.amount_in,
+			self.amount_out,
+		);
 
-		Ok(insert.end().await?)
+		Self { uuid: Uuid::new_v5(&UUID_NAMESPACE, name.as_bytes()), ..self }
+	}
+
+	pub async fn create_many<I>(warehouse: &Warehouse, models: I) -> Result<()>
+	where
+		I: IntoIterator<Item = Self>,
+		I::IntoIter: Send + 'static,
+	{
+		warehouse.get().create_amounts(Box::new(models.into_iter())).await
 	}
 
 	pub async fn get_all_network_ids_by_addresses(
 		warehouse: &Warehouse,
 		mut addresses: Vec<String>,
 	) -> Result<PrimaryIds> {
-		#[derive(PartialEq, Eq, Hash, Debug, Clone, Row, Serialize, Deserialize)]
-		struct Data {
-			network_id: u64,
-		}
+		addresses.sort_unstable();
+		addresses.dedup();
+
+		Ok(warehouse.get().get_amount_network_ids_by_addresses(addresses).await?.into())
+	}
 
+	pub async fn get_all_received_by_addresses(
+		warehouse: &Warehouse,
+		mut addresses: Vec<String>,
+		(since, until): (Option<u32>, Option<u32>),
+	) -> Result<HashMap<(PrimaryId, String), U256>> {
 		addresses.sort_unstable();
 		addresses.dedup();
 
-		Ok(warehouse
+		warehouse
 			.get()
-			.query(&format!(
-				r#"
-					SELECT DISTINCT network_id
-					FROM {TABLE}
-					WHERE address IN ?
-                "#
-			))
-			.bind(addresses)
-			.fetch_all::<Data>()
-			.await?
-			.into_iter()
-			.map(|d| d.network_id as PrimaryId)
-			.collect::<Vec<PrimaryId>>()
-			.into())
+			.get_amounts_received_by_addresses(
+				addresses,
+				(since.unwrap_or(0), until.unwrap_or(u32::MAX)),
+			)
+			.await
 	}
 
 	pub async fn delete_all_by_network_id(
 		warehouse: &Warehouse,
 		network_ids: PrimaryIds,
 	) -> Result<()> {
-		Ok(warehouse
-			.get()
-			.query(&format!(
-				r#"
-					SET allow_experimental_lightweight_delete = true;
-					DELETE FROM {TABLE} WHERE network_id IN ?
-                "#
-			))
-			.bind(network_ids.into_iter().collect::<Vec<PrimaryId>>())
-			.execute()
-			.await?)
+		warehouse.get().delete_all_by_network_ids(TABLE, network_ids.into_iter().collect()).await
+	}
+
+	pub async fn delete_all_after_block_height(
+		warehouse: &Warehouse,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+	) -> Result<()> {
+		warehouse.get().delete_all_after_block_height(TABLE, network_id, block_height).await
 	}
 }
@@ -26,49 +26,24 @@ impl Model {
 		warehouse: &Warehouse,
 		mut addresses: Vec<String>,
 	) -> Result<Vec<Model>> {
-		// @TODO until I256 is implemented, doing this hacky "group by" statement
-		// ideally: "SELECT ?fields FROM {TABLE} WHERE address IN ?"
-
 		addresses.sort_unstable();
 		addresses.dedup();
 
-		Ok(warehouse
-			.get()
-			.query(&format!(
-				r#"
-					SELECT *
-					FROM (
-	                    SELECT
-	                        network_id,
-	                        address,
-	                        asset_address,
-	                        SUM(balance) as balance
-	                    FROM {TABLE}
-	                    WHERE address IN ?
-	                    GROUP BY (network_id, address, asset_address)
-					)
-					WHERE balance >= 0
-                "#
-			))
-			.bind(addresses)
-			.fetch_all::<Model>()
-			.await?)
+		warehouse.get().get_balances_by_addresses(addresses).await
+	}
+
+	pub async fn get_random_sample(
+		warehouse: &Warehouse,
+		network_id: PrimaryId,
+		limit: u64,
+	) -> Result<Vec<Model>> {
+		warehouse.get().get_balances_random_sample(network_id, limit).await
 	}
 
 	pub async fn delete_all_by_network_id(
 		warehouse: &Warehouse,
 		network_ids: PrimaryIds,
 	) -> Result<()> {
-		Ok(warehouse
-			.get()
-			.query(&format!(
-				r#"
-					SET allow_experimental_lightweight_delete = true;
-					DELETE FROM {TABLE} WHERE network_id IN ?
-                "#
-			))
-			.bind(network_ids.into_iter().collect::<Vec<PrimaryId>>())
-			.execute()
-			.await?)
+		warehouse.get().delete_all_by_network_ids(TABLE, network_ids.into_iter().collect()).await
 	}
 }
@@ -0,0 +1,100 @@
+use clickhouse::Row;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	models::{PrimaryId, PrimaryIds},
+	warehouse::Warehouse,
+	BlockHeight,
+};
+
+pub static TABLE: &str = "clusters";
+
+// addresses assumed to be controlled by the same owner. `cluster_id` is the smallest address that
+// ever joined the cluster, and rows are replaced (by `block_height`) whenever clusters merge
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Row, Serialize, Deserialize)]
+pub struct Model {
+	pub network_id: u64,
+	pub address: String,
+	pub cluster_id: String,
+	pub block_height: u64,
+}
+
+pub use Model as Cluster;
+
+impl Model {
+	pub fn new(
+		network_id: PrimaryId,
+		address: &str,
+		cluster_id: &str,
+		block_height: BlockHeight,
+	) -> Self {
+		Self {
+			network_id: network_id as u64,
+			address: address.to_string(),
+			cluster_id: cluster_id.to_string(),
+			block_height,
+		}
+	}
+
+	pub async fn create_many<I>(warehouse: &Warehouse, models: I) -> Result<()>
+	where
+		I: IntoIterator<Item = Self>,
+		I::IntoIter: Send + 'static,
+	{
+		warehouse.get().create_clusters(Box::new(models.into_iter())).await
+	}
+
+	pub async fn get_all_by_addresses(
+		warehouse: &Warehouse,
+		mut addresses: Vec<String>,
+	) -> Result<Vec<Self>> {
+		addresses.sort_unstable();
+		addresses.dedup();
+
+		warehouse.get().get_clusters_by_addresses(addresses).await
+	}
+
+	pub async fn get_all_by_cluster_ids(
+		warehouse: &Warehouse,
+		network_id: PrimaryId,
+		mut cluster_ids: Vec<String>,
+	) -> Result<Vec<Self>> {
+		cluster_ids.sort_unstable();
+		cluster_ids.dedup();
+
+		warehouse.get().get_clusters_by_cluster_ids(network_id, cluster_ids).await
+	}
+
+	pub async fn get_all_after_block_height(
+		warehouse: &Warehouse,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+	) -> Result<Vec<Self>> {
+		warehouse.get().get_clusters_after_block_height(network_id, block_height).await
+	}
+
+	// all members of the cluster `address` belongs to (including itself)
+	pub async fn get_all_by_member(
+		warehouse: &Warehouse,
+		network_id: PrimaryId,
+		address: &str,
+	) -> Result<Vec<Self>> {
+		warehouse.get().get_clusters_by_member(network_id, address).await
+	}
+
+	pub async fn delete_all_by_network_id(
+		warehouse: &Warehouse,
+		network_ids: PrimaryIds,
+	) -> Result<()> {
+		warehouse.get().delete_all_by_network_ids(TABLE, network_ids.into_iter().collect()).await
+	}
+
+	pub async fn delete_all_after_block_height(
+		warehouse: &Warehouse,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+	) -> Result<()> {
+		warehouse.get().delete_all_after_block_height(TABLE, network_id, block_height).await
+	}
+}
@@ -15,6 +15,8 @@ pub enum ConfigKey {
 	Primary,
 	#[display(fmt = "indexer_tail_sync_n{_0}")]
 	IndexerTailSync(PrimaryId),
+	#[display(fmt = "indexer_tail_hashes_n{_0}")]
+	IndexerTailHashes(PrimaryId),
 	#[display(fmt = "indexer_chunk_sync_n{_0}_b{_1}")]
 	IndexerChunkSync(PrimaryId, BlockHeight),
 	#[display(fmt = "indexer_module_sync_n{_0}_m{_1}")]
@@ -23,6 +25,10 @@ pub enum ConfigKey {
 	IndexerModuleSynced(PrimaryId, u16),
 	#[display(fmt = "indexer_upstream_sync_n{_0}_a{_1}")]
 	IndexerUpstreamSync(PrimaryId, PrimaryId),
+	#[display(fmt = "indexer_downstream_sync_n{_0}_a{_1}")]
+	IndexerDownstreamSync(PrimaryId, PrimaryId),
+	#[display(fmt = "indexer_cluster_sync_n{_0}")]
+	IndexerClusterSync(PrimaryId),
 	#[display(fmt = "indexer_n{_0}_progress")]
 	IndexerProgress(PrimaryId),
 	#[display(fmt = "block_height_n{_0}")]
@@ -43,6 +49,7 @@ impl From<String> for ConfigKey {
 		match template.to_string().as_str() {
 			"primary" => Self::Primary,
 			"indexer_tail_sync_n{}" if n.len() == 1 => Self::IndexerTailSync(n[0]),
+			"indexer_tail_hashes_n{}" if n.len() == 1 => Self::IndexerTailHashes(n[0]),
 			"indexer_chunk_sync_n{}_b{}" if n.len() == 2 => {
 				Self::IndexerChunkSync(n[0], n[1] as BlockHeight)
 			}
@@ -55,6 +62,10 @@ impl From<String> for ConfigKey {
 			"indexer_upstream_sync_n{}_a{}" if n.len() == 2 => {
 				Self::IndexerUpstreamSync(n[0], n[1])
 			}
+			"indexer_downstream_sync_n{}_a{}" if n.len() == 2 => {
+				Self::IndexerDownstreamSync(n[0], n[1])
+			}
+			"indexer_cluster_sync_n{}" if n.len() == 1 => Self::IndexerClusterSync(n[0]),
 			"indexer_n{}_progress" if n.len() == 1 => Self::IndexerProgress(n[0]),
 			"block_height_n{}" if n.len() == 1 => Self::BlockHeight(n[0]),
 			"networks_updated" => Self::NetworksUpdated,
@@ -73,10 +84,13 @@ mod tests {
 		let config_keys = HashMap::from([
 			(ConfigKey::Primary, "primary"),
 			(ConfigKey::IndexerTailSync(123), "indexer_tail_sync_n123"),
+			(ConfigKey::IndexerTailHashes(123), "indexer_tail_hashes_n123"),
 			(ConfigKey::IndexerChunkSync(123, 456), "indexer_chunk_sync_n123_b456"),
 			(ConfigKey::IndexerModuleSync(123, 456), "indexer_module_sync_n123_m456"),
 			(ConfigKey::IndexerModuleSynced(123, 456), "indexer_module_synced_n123_m456"),
 			(ConfigKey::IndexerUpstreamSync(123, 456), "indexer_upstream_sync_n123_a456"),
+			(ConfigKey::IndexerDownstreamSync(123, 456), "indexer_downstream_sync_n123_a456"),
+			(ConfigKey::IndexerClusterSync(123), "indexer_cluster_sync_n123"),
 			(ConfigKey::IndexerProgress(123), "indexer_n123_progress"),
 			(ConfigKey::BlockHeight(123), "block_height_n123"),
 			(ConfigKey::NetworksUpdated, "networks_updated"),
@@ -0,0 +1,115 @@
+use clickhouse::Row;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+	models::{LinkUuid, PrimaryId, PrimaryIds},
+	warehouse::Warehouse,
+	BlockHeight,
+};
+
+pub static TABLE: &str = "downstream_links";
+
+// the reverse of `Link`: funds from `from_address` reached the entity address in `to_address`.
+// `transfer_uuids` are in chronological order, and `block_height` is that of the last transfer
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Row, Serialize, Deserialize)]
+pub struct Model {
+	pub network_id: u64,
+	pub block_height: u64,
+	pub from_address: String,
+	pub to_address: String,
+	pub transfer_uuids: Vec<LinkUuid>,
+	pub created_at: u32,
+}
+
+pub use Model as DownstreamLink;
+
+impl Model {
+	pub fn new(
+		network_id: PrimaryId,
+		block_height: u64,
+		from_address: &str,
+		to_address: &str,
+		transfer_uuids: Vec<LinkUuid>,
+		created_at: u32,
+	) -> Self {
+		Self {
+			network_id: network_id as u64,
+			block_height,
+			from_address: from_address.to_string(),
+			to_address: to_address.to_string(),
+			transfer_uuids,
+			created_at,
+		}
+	}
+
+	pub async fn create_many<I>(warehouse: &Warehouse, models: I) -> Result<()>
+	where
+		I: IntoIterator<Item = Self>,
+		I::IntoIter: Send + 'static,
+	{
+		warehouse.get().create_downstream_links(Box::new(models.into_iter())).await
+	}
+
+	pub async fn get_all_by_addresses(
+		warehouse: &Warehouse,
+		mut addresses: Vec<String>,
+	) -> Result<Vec<Self>> {
+		addresses.sort_unstable();
+		addresses.dedup();
+
+		warehouse.get().get_downstream_links_by_addresses(addresses).await
+	}
+
+	pub async fn get_all_disinct_by_addresses(
+		warehouse: &Warehouse,
+		mut addresses: Vec<String>,
+	) -> Result<Vec<Self>> {
+		addresses.sort_unstable();
+		addresses.dedup();
+
+		warehouse.get().get_distinct_downstream_links_by_addresses(addresses).await
+	}
+
+	pub async fn delete_all_by_targets(
+		warehouse: &Warehouse,
+		targets: HashMap<PrimaryId, HashSet<String>>, // network_id -> addresses
+	) -> Result<()> {
+		if !targets.is_empty() {
+			warehouse.get().delete_downstream_links_by_targets(targets).await?;
+		}
+
+		Ok(())
+	}
+
+	pub async fn delete_all_by_network_id(
+		warehouse: &Warehouse,
+		network_ids: PrimaryIds,
+	) -> Result<()> {
+		warehouse.get().delete_all_by_network_ids(TABLE, network_ids.into_iter().collect()).await
+	}
+
+	pub async fn delete_all_after_block_height(
+		warehouse: &Warehouse,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+	) -> Result<()> {
+		warehouse.get().delete_all_after_block_height(TABLE, network_id, block_height).await
+	}
+
+	pub async fn delete_all_by_newly_added_addresses(
+		warehouse: &Warehouse,
+		targets: HashMap<PrimaryId, HashSet<String>>, // network_id -> addresses
+	) -> Result<()> {
+		// same idea as `Link::delete_all_by_newly_added_addresses`, except chains run the other
+		// way: a chain is broken when any transfer but the last one went into a newly added
+		// entity address (the last one is fine, that's the entity the chain points to)
+
+		if !targets.is_empty() {
+			warehouse.get().delete_downstream_links_by_newly_added_addresses(targets).await?;
+		}
+
+		Ok(())
+	}
+}
@@ -24,6 +24,8 @@ pub struct Model {
 	pub name: Option<String>,
 	pub description: String,
 	pub url: String,
+	#[sea_orm(nullable)]
+	pub max_hops: Option<i64>,
 	#[serde(skip_serializing)]
 	pub is_deleted: bool,
 	#[sea_orm(nullable)]
@@ -57,6 +59,7 @@ pub struct JoinedModel {
 	pub name: Option<String>,
 	pub description: String,
 	pub url: String,
+	pub max_hops: Option<i64>,
 	pub is_deleted: bool,
 	pub updated_at: Option<DateTime>,
 	pub created_at: DateTime,
@@ -71,6 +74,7 @@ impl From<JoinedModel> for Model {
 			name: m.name,
 			description: m.description,
 			url: m.url,
+			max_hops: m.max_hops,
 			is_deleted: m.is_deleted,
 			updated_at: m.updated_at,
 			created_at: m.created_at,
@@ -138,12 +142,18 @@ impl SoftDeleteModel for Model {
 }
 
 impl Model {
-	pub fn new_model(name: Option<String>, description: &str, url: &str) -> ActiveModel {
+	pub fn new_model(
+		name: Option<String>,
+		description: &str,
+		url: &str,
+		max_hops: Option<i64>,
+	) -> ActiveModel {
 		ActiveModel {
 			id: Set(utils::new_unique_id(IdPrefix::Entity)),
 			name: Set(name),
 			description: Set(description.to_string()),
 			url: Set(url.to_string()),
+			max_hops: Set(max_hops),
 			is_deleted: Set(false),
 			..Default::default()
 		}
@@ -5,7 +5,8 @@ use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
 use crate::{
-	models::{transfer::TABLE as TRANSFERS_TABLE, PrimaryId, PrimaryIds},
+	chain::U256,
+	models::{PrimaryId, PrimaryIds},
 	warehouse::Warehouse,
 	BlockHeight,
 };
@@ -28,6 +29,16 @@ pub struct Model {
 
 pub use Model as Link;
 
+// optional constraints for reading links back out
+#[derive(Default, Debug, Clone)]
+pub struct Filters {
+	pub min_hops: Option<u64>,
+	pub max_hops: Option<u64>,
+	pub since: Option<u32>,
+	pub until: Option<u32>,
+	pub min_amount: Option<(String, U256)>, // every transfer has to move this much of the asset
+}
+
 impl Model {
 	pub fn new(
 		network_id: PrimaryId,
@@ -47,87 +58,42 @@ impl Model {
 		}
 	}
 
-	pub async fn create_many(warehouse: &Warehouse, models: Vec<Self>) -> Result<()> {
-		let mut insert = warehouse.get().insert(TABLE)?;
-		for model in models.into_iter() {
-			insert.write(&model).await?;
-		}
-
-		Ok(insert.end().await?)
+	pub async fn create_many<I>(warehouse: &Warehouse, models: I) -> Result<()>
+	where
+		I: IntoIterator<Item = Self>,
+		I::IntoIter: Send + 'static,
+	{
+		warehouse.get().create_links(Box::new(models.into_iter())).await
 	}
 
 	pub async fn get_all_by_addresses(
 		warehouse: &Warehouse,
 		mut addresses: Vec<String>,
+		filters: &Filters,
 	) -> Result<Vec<Self>> {
 		addresses.sort_unstable();
 		addresses.dedup();
 
-		Ok(warehouse
-			.get()
-			.query(&format!(
-				r#"
-					SELECT *
-					FROM {TABLE}
-					WHERE to_address IN ?
-                "#
-			))
-			.bind(addresses)
-			.fetch_all::<Model>()
-			.await?)
+		warehouse.get().get_links_by_addresses(addresses, filters).await
 	}
 
 	pub async fn get_all_disinct_by_addresses(
 		warehouse: &Warehouse,
 		mut addresses: Vec<String>,
+		filters: &Filters,
 	) -> Result<Vec<Self>> {
 		addresses.sort_unstable();
 		addresses.dedup();
 
-		Ok(warehouse
-			.get()
-			.query(&format!(
-				r#"
-					SELECT DISTINCT ON (network_id, from_address) *
-					FROM {TABLE}
-					WHERE to_address IN ?
-					ORDER BY LENGTH(transfer_uuids) ASC
-                "#
-			))
-			.bind(addresses)
-			.fetch_all::<Model>()
-			.await?)
+		warehouse.get().get_distinct_links_by_addresses(addresses, filters).await
 	}
 
 	pub async fn get_all_to_seed_blocks(
 		warehouse: &Warehouse,
 		network_id: PrimaryId,
-		(block_height_min, block_height_max): (BlockHeight, BlockHeight),
+		block_range: (BlockHeight, BlockHeight),
 	) -> Result<Vec<Self>> {
-		Ok(warehouse
-			.get()
-			.query(&format!(
-				r#"
-					SELECT *
-					FROM {TABLE}
-					WHERE network_id = ? AND to_address IN (
-					    SELECT from_address
-					    FROM {TRANSFERS_TABLE}
-					    WHERE
-							network_id = ? AND
-							length(from_address) > 0 AND
-							length(to_address) > 0 AND
-							block_height >= ? AND
-							block_height <= ?
-					)
-                "#
-			))
-			.bind(network_id)
-			.bind(network_id)
-			.bind(block_height_min)
-			.bind(block_height_max)
-			.fetch_all::<Model>()
-			.await?)
+		warehouse.get().get_links_to_seed_blocks(network_id, block_range).await
 	}
 
 	pub async fn delete_all_by_sources(
@@ -135,17 +101,7 @@ impl Model {
 		sources: HashMap<PrimaryId, HashSet<String>>, // network_id -> addresses
 	) -> Result<()> {
 		if !sources.is_empty() {
-			warehouse
-				.get()
-				.query(&format!(
-					r#"
-						SET allow_experimental_lightweight_delete = true;
-						DELETE FROM {TABLE} WHERE {}
-					"#,
-					Self::get_network_id_address_tuples(sources, "from_address"),
-				))
-				.execute()
-				.await?;
+			warehouse.get().delete_links_by_sources(sources).await?;
 		}
 
 		Ok(())
@@ -155,17 +111,15 @@ impl Model {
 		warehouse: &Warehouse,
 		network_ids: PrimaryIds,
 	) -> Result<()> {
-		Ok(warehouse
-			.get()
-			.query(&format!(
-				r#"
-					SET allow_experimental_lightweight_delete = true;
-					DELETE FROM {TABLE} WHERE network_id IN ?
-                "#
-			))
-			.bind(network_ids.into_iter().collect::<Vec<PrimaryId>>())
-			.execute()
-			.await?)
+		warehouse.get().delete_all_by_network_ids(TABLE, network_ids.into_iter().collect()).await
+	}
+
+	pub async fn delete_all_after_block_height(
+		warehouse: &Warehouse,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+	) -> Result<()> {
+		warehouse.get().delete_all_after_block_height(TABLE, network_id, block_height).await
 	}
 
 	pub async fn delete_all_by_newly_added_addresses(
@@ -186,52 +140,14 @@ impl Model {
 		// 1. find all links where `to_address` is in (targets)
 		// 2. gather the last elements of those `transfer_uuids` into an array
 		// 3. delete all link records that contain those uuids in the middle of `transfer_uuids`
-		//    meaning not first, because it's ok if target is in the `from_address`
-		//    but also not last, because it's ok if target is in the `to_address`
-		//    (in the middle = labeled entity is in the middle of the links chain)
+		//    meaning not first, because it's ok if target is in the `from_address` but also not
+		//    last, because it's ok if target is in the `to_address` (in the middle = labeled entity
+		//    is in the middle of the links chain)
 
 		if !targets.is_empty() {
-			warehouse
-				.get()
-				.query(&format!(
-					r#"
-						SET allow_experimental_lightweight_delete = true;
-						DELETE FROM {TABLE}
-						WHERE
-							length(transfer_uuids) > 2 AND
-							hasAny(
-								arraySlice(transfer_uuids, 2, -1),
-								(
-									SELECT DISTINCT groupArray(transfer_uuids[-1])
-									FROM {TABLE}
-									WHERE length(transfer_uuids) > 0 AND {}
-								)
-							)
-					"#,
-					Self::get_network_id_address_tuples(targets, "to_address"),
-				))
-				.execute()
-				.await?;
+			warehouse.get().delete_links_by_newly_added_addresses(targets).await?;
 		}
 
 		Ok(())
 	}
-
-	fn get_network_id_address_tuples(
-		map: HashMap<PrimaryId, HashSet<String>>,
-		field: &str,
-	) -> String {
-		map.into_iter()
-			.map(|(network_id, addresses)| {
-				let escaped_addresses = addresses
-					.into_iter()
-					.map(|a| format!("'{}'", a.replace('\\', "\\\\").replace('\'', "\\'")))
-					.collect::<Vec<String>>()
-					.join(",");
-
-				format!("(network_id = {network_id} AND {field} IN ({escaped_addresses}))")
-			})
-			.collect::<Vec<String>>()
-			.join(" OR ")
-	}
 }
@@ -14,29 +14,39 @@ pub use address::{Address, AddressActiveModel, Column as AddressColumn};
 pub use amount::Amount;
 pub use api_key::{ApiKey, ApiKeyActiveModel, Column as ApiKeyColumn};
 pub use balance::Balance;
+pub use cluster::Cluster;
+pub use downstream_link::DownstreamLink;
 pub use entity::{
 	Column as EntityColumn, JoinedEntity, LabeledEntity as Entity,
 	LabeledEntityActiveModel as EntityActiveModel, SanitizedEntity,
 };
 pub use entity_tag::{Column as EntityTagColumn, EntityTag};
-pub use link::{Link, LinkUuid};
+pub use link::{Filters as LinkFilters, Link, LinkUuid};
 pub use network::{Column as NetworkColumn, Network, NetworkActiveModel, SanitizedNetwork};
+pub use reconciliation::{Reconciliation, Stats as ReconciliationStats};
 pub use relation::{Reason as RelationReason, Relation};
 pub use tag::{Column as TagColumn, JoinedTag, SanitizedTag, Tag, TagActiveModel};
 pub use transfer::Transfer;
+pub use watchlist::{Column as WatchedAddressColumn, WatchedAddress, WatchedAddressActiveModel};
+pub use webhook::{Column as WebhookColumn, Webhook, WebhookActiveModel};
 
 pub mod address;
 pub mod amount;
 pub mod api_key;
 pub mod balance;
+pub mod cluster;
 pub mod config;
+pub mod downstream_link;
 pub mod entity;
 pub mod entity_tag;
 pub mod link;
 pub mod network;
+pub mod reconciliation;
 pub mod relation;
 pub mod tag;
 pub mod transfer;
+pub mod watchlist;
+pub mod webhook;
 
 pub type PrimaryId = i64;
 
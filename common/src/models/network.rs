@@ -27,6 +27,7 @@ pub struct Model {
 	pub block_time_ms: i64,
 	pub rpc_endpoints: Json,
 	pub rps: i32,
+	pub confirmations: i64,
 	#[serde(skip_serializing)]
 	pub is_deleted: bool,
 	#[sea_orm(nullable)]
@@ -86,6 +87,7 @@ impl Model {
 		block_time_ms: i64,
 		rpc_endpoints: Vec<String>,
 		rps: i32,
+		confirmations: i64,
 	) -> ActiveModel {
 		ActiveModel {
 			id: Set(utils::new_unique_id(IdPrefix::Network)),
@@ -97,6 +99,7 @@ impl Model {
 			rpc_endpoints: Set(json!(rpc_endpoints)),
 			is_deleted: Set(false),
 			rps: Set(rps),
+			confirmations: Set(confirmations),
 			..Default::default()
 		}
 	}
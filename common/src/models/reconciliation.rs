@@ -0,0 +1,84 @@
+use clickhouse::Row;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	chain::{u256, ModuleId, U256},
+	models::{PrimaryId, PrimaryIds},
+	warehouse::Warehouse,
+	BlockHeight,
+};
+
+pub static TABLE: &str = "reconciliations";
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Row, Serialize, Deserialize)]
+pub struct Model {
+	pub network_id: u64,
+	pub module_id: u16,
+	pub block_height: u64,
+	pub address: String,
+	pub asset_address: String,
+	#[serde(with = "u256")]
+	pub indexed_balance: U256,
+	#[serde(with = "u256")]
+	pub live_balance: U256,
+	pub created_at: u32,
+}
+
+pub use Model as Reconciliation;
+
+#[derive(Debug, Clone, Row, Deserialize)]
+pub struct Stats {
+	pub network_id: u64,
+	pub module_id: u16,
+	pub checked: u64,
+	pub drifted: u64,
+	pub checked_at: u32,
+}
+
+impl Model {
+	pub fn new(
+		network_id: PrimaryId,
+		module_id: ModuleId,
+		block_height: BlockHeight,
+		address: &str,
+		asset_address: &str,
+		indexed_balance: U256,
+		live_balance: U256,
+		created_at: u32,
+	) -> Self {
+		Self {
+			network_id: network_id as u64,
+			module_id: module_id as u16,
+			block_height,
+			address: address.to_string(),
+			asset_address: asset_address.to_string(),
+			indexed_balance,
+			live_balance,
+			created_at,
+		}
+	}
+
+	pub fn is_drifting(&self) -> bool {
+		self.indexed_balance != self.live_balance
+	}
+
+	pub async fn create_many<I>(warehouse: &Warehouse, models: I) -> Result<()>
+	where
+		I: IntoIterator<Item = Self>,
+		I::IntoIter: Send + 'static,
+	{
+		warehouse.get().create_reconciliations(Box::new(models.into_iter())).await
+	}
+
+	pub async fn get_stats(warehouse: &Warehouse, since: u32) -> Result<Vec<Stats>> {
+		warehouse.get().get_reconciliation_stats(since).await
+	}
+
+	pub async fn delete_all_by_network_id(
+		warehouse: &Warehouse,
+		network_ids: PrimaryIds,
+	) -> Result<()> {
+		warehouse.get().delete_all_by_network_ids(TABLE, network_ids.into_iter().collect()).await
+	}
+}
@@ -1,5 +1,6 @@
 use clickhouse::Row;
-use eyre::Result;
+use derive_more::Display;
+use eyre::{eyre, Report, Result};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -8,14 +9,30 @@ use crate::{
 	models::{PrimaryId, PrimaryIds},
 	utils,
 	warehouse::Warehouse,
+	BlockHeight,
 };
 
 pub static TABLE: &str = "experimental_relations";
 
 #[repr(u16)]
+#[derive(Display, Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Reason {
 	WholeBalanceTransfer = 1,
 	NoChangeInUtxo = 2,
+	CommonInputOwnership = 3,
+}
+
+impl TryFrom<u16> for Reason {
+	type Error = Report;
+
+	fn try_from(v: u16) -> Result<Self> {
+		match v {
+			1 => Ok(Self::WholeBalanceTransfer),
+			2 => Ok(Self::NoChangeInUtxo),
+			3 => Ok(Self::CommonInputOwnership),
+			_ => Err(eyre!("unknown relation reason: {v}")),
+		}
+	}
 }
 
 #[derive(PartialEq, Eq, Hash, Debug, Clone, Row, Serialize, Deserialize)]
@@ -58,29 +75,62 @@ impl Model {
 		}
 	}
 
-	pub async fn create_many(warehouse: &Warehouse, models: Vec<Self>) -> Result<()> {
-		let mut insert = warehouse.get().insert(TABLE)?;
-		for model in models.into_iter() {
-			insert.write(&model).await?;
-		}
+	pub async fn create_many<I>(warehouse: &Warehouse, models: I) -> Result<()>
+	where
+		I: IntoIterator<Item = Self>,
+		I::IntoIter: Send + 'static,
+	{
+		warehouse.get().create_relations(Box::new(models.into_iter())).await
+	}
+
+	pub async fn get_all_by_block_range(
+		warehouse: &Warehouse,
+		network_id: PrimaryId,
+		reason: Reason,
+		block_range: (BlockHeight, BlockHeight),
+	) -> Result<Vec<Self>> {
+		warehouse.get().get_relations_by_block_range(network_id, reason, block_range).await
+	}
+
+	// relations in either direction that touch any of `addresses`, up to `max_block_height`
+	pub async fn get_all_by_addresses(
+		warehouse: &Warehouse,
+		network_id: PrimaryId,
+		reason: Reason,
+		mut addresses: Vec<String>,
+		max_block_height: BlockHeight,
+	) -> Result<Vec<Self>> {
+		addresses.sort_unstable();
+		addresses.dedup();
 
-		Ok(insert.end().await?)
+		warehouse
+			.get()
+			.get_relations_by_addresses(network_id, reason, addresses, max_block_height)
+			.await
+	}
+
+	// relations in either direction, most recent first
+	pub async fn get_all_by_address_paginated(
+		warehouse: &Warehouse,
+		address: &str,
+		offset: u64,
+		limit: u64,
+	) -> Result<Vec<Self>> {
+		warehouse.get().get_relations_by_address_paginated(address, offset, limit).await
 	}
 
 	pub async fn delete_all_by_network_id(
 		warehouse: &Warehouse,
 		network_ids: PrimaryIds,
 	) -> Result<()> {
-		Ok(warehouse
-			.get()
-			.query(&format!(
-				r#"
-					SET allow_experimental_lightweight_delete = true;
-					DELETE FROM {TABLE} WHERE network_id IN ?
-                "#
-			))
-			.bind(network_ids.into_iter().collect::<Vec<PrimaryId>>())
-			.execute()
-			.await?)
+		warehouse.get().delete_all_by_network_ids(TABLE, network_ids.into_iter().collect()).await
+	}
+
+	pub async fn delete_all_after_block_height(
+		warehouse: &Warehouse,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+	) -> Result<()> {
+		warehouse.get().delete_all_after_block_height(TABLE, network_id, block_height).await
 	}
 }
@@ -20,6 +20,7 @@ pub struct Model {
 	pub tag_id: PrimaryId,
 	pub id: String,
 	pub name: String,
+	pub severity: i32, // 0 (harmless) to 100 (most severe), used for risk scoring
 	#[sea_orm(nullable)]
 	#[serde(skip_serializing)]
 	pub updated_at: Option<DateTime>,
@@ -46,6 +47,7 @@ pub struct JoinedModel {
 	pub tag_id: PrimaryId,
 	pub id: String,
 	pub name: String,
+	pub severity: i32,
 	pub updated_at: Option<DateTime>,
 	pub created_at: DateTime,
 	pub entity_id: PrimaryId,
@@ -68,6 +70,7 @@ impl From<JoinedModel> for Model {
 			tag_id: m.tag_id,
 			id: m.id,
 			name: m.name,
+			severity: m.severity,
 			updated_at: m.updated_at,
 			created_at: m.created_at,
 			entities: None,
@@ -80,11 +83,12 @@ impl From<JoinedModel> for Model {
 pub struct SanitizedTag {
 	pub id: String,
 	pub name: String,
+	pub severity: i32,
 }
 
 impl From<Model> for SanitizedTag {
 	fn from(m: Model) -> SanitizedTag {
-		SanitizedTag { id: m.id, name: m.name }
+		SanitizedTag { id: m.id, name: m.name, severity: m.severity }
 	}
 }
 
@@ -109,10 +113,11 @@ impl BasicModel for Model {
 }
 
 impl Model {
-	pub fn new_model(name: &str) -> ActiveModel {
+	pub fn new_model(name: &str, severity: i32) -> ActiveModel {
 		ActiveModel {
 			id: Set(utils::new_unique_id(IdPrefix::Tag)),
 			name: Set(name.to_string()),
+			severity: Set(severity),
 			..Default::default()
 		}
 	}
@@ -6,13 +6,15 @@ use uuid::Uuid;
 use crate::{
 	chain::{u256, ModuleId, U256},
 	models::{PrimaryId, PrimaryIds},
-	utils,
 	warehouse::Warehouse,
 	BlockHeight,
 };
 
 pub static TABLE: &str = "transfers";
 
+// namespace for transfer uuids (which are v5, derived from the transfer itself)
+static UUID_NAMESPACE: Uuid = Uuid::from_u128(0x3f1c9a52_7e4b_4d0a_9c61_5b2e8d07a4f3);
+
 #[derive(PartialEq, Eq, Hash, Debug, Clone, Row, Serialize, Deserialize)]
 pub struct Model {
 	#[serde(with = "clickhouse::serde::uuid")]
@@ -24,6 +26,7 @@ pub struct Model {
 	pub from_address: String,
 	pub to_address: String,
 	pub asset_address: String,
+	pub token_id: String,
 	#[serde(with = "u256")]
 	pub relative_amount: U256,
 	#[serde(with = "u256")]
@@ -34,11 +37,13 @@ pub struct Model {
 pub use Model as Transfer;
 
 impl Model {
+	#[allow(clippy::too_many_arguments)]
 	pub fn new(
 		module_id: ModuleId,
 		network_id: PrimaryId,
 		block_height: u64,
 		tx_hash: &str,
+		position: u64,
 		from_address: &str,
 		to_address: &str,
 		asset_address: Option<String>,
@@ -47,7 +52,7 @@ impl Model {
 		created_at: u32,
 	) -> Self {
 		Self {
-			uuid: utils::new_uuid(),
+			uuid: Uuid::nil(),
 			module_id: module_id as u16,
 			network_id: network_id as u64,
 			block_height,
@@ -55,19 +60,46 @@ impl Model {
 			from_address: from_address.to_string(),
 			to_address: to_address.to_string(),
 			asset_address: asset_address.unwrap_or_default(),
+			token_id: String::new(),
 			relative_amount,
 			batch_amount,
 			created_at,
 		}
+		.with_uuid(position)
 	}
 
-	pub async fn create_many(warehouse: &Warehouse, models: Vec<Self>) -> Result<()> {
-		let mut insert = warehouse.get().insert(TABLE)?;
-		for model in models.into_iter() {
-			insert.write(&model).await?;
-		}
+	// nft transfers also tell tokens apart by id, so it goes into their uuid as well
+	pub fn with_token_id(self, token_id: String) -> Self {
+		Self { uuid: Uuid::new_v5(&self.uuid, token_id.as_bytes()), token_id, ..self }
+	}
+
+	// the uuid is derived from the transfer itself plus its `position` in the tx (log, trace
+	// or instruction index, which isn't stored), so re-indexing a block produces the exact same
+	// transfers (and links pointing at them), and no two events in one tx share a uuid
+	fn with_uuid(self, position: u64) -> Self {
+		let name = format!(
+			"{}:{}:{}:{}:{}:{}:{}:{}:{}:{}",
+			self.module_id,
+			self.network_id,
+			self.block_height,
+			self.tx_hash,
+			position,
+			self.from_address,
+			self.to_address,
+			self.asset_address,
+			self.relative_amount,
+			self.batch_amount,
+		);
 
-		Ok(insert.end().await?)
+		Self { uuid: Uuid::new_v5(&UUID_NAMESPACE, name.as_bytes()), ..self }
+	}
+
+	pub async fn create_many<I>(warehouse: &Warehouse, models: I) -> Result<()>
+	where
+		I: IntoIterator<Item = Self>,
+		I::IntoIter: Send + 'static,
+	{
+		warehouse.get().create_transfers(Box::new(models.into_iter())).await
 	}
 
 	pub async fn get_first_by_source(
@@ -75,51 +107,48 @@ impl Model {
 		network_id: PrimaryId,
 		address: &str,
 	) -> Result<Option<Self>> {
-		let results = warehouse
-			.get()
-			.query(&format!(
-				r#"
-					SELECT *
-					FROM {TABLE}
-					WHERE network_id = ? AND from_address = ?
-					ORDER BY created_at ASC
-					LIMIT 1
-                "#
-			))
-			.bind(network_id)
-			.bind(address.to_string())
-			.fetch_all::<Model>()
-			.await?;
-
-		Ok(match results.len() {
-			0 => None,
-			_ => Some(results[0].clone()),
-		})
+		warehouse.get().get_first_transfer_by_source(network_id, address).await
+	}
+
+	pub async fn get_first_by_target(
+		warehouse: &Warehouse,
+		network_id: PrimaryId,
+		address: &str,
+	) -> Result<Option<Self>> {
+		warehouse.get().get_first_transfer_by_target(network_id, address).await
+	}
+
+	pub async fn get_all_by_targets(
+		warehouse: &Warehouse,
+		network_id: PrimaryId,
+		mut addresses: Vec<String>,
+		block_range: (BlockHeight, BlockHeight),
+	) -> Result<Vec<Self>> {
+		addresses.sort_unstable();
+		addresses.dedup();
+
+		warehouse.get().get_transfers_by_targets(network_id, addresses, block_range).await
+	}
+
+	// transfers in & out of `addresses`, up to `max_block_height`
+	pub async fn get_all_by_addresses(
+		warehouse: &Warehouse,
+		network_id: PrimaryId,
+		mut addresses: Vec<String>,
+		max_block_height: BlockHeight,
+	) -> Result<Vec<Self>> {
+		addresses.sort_unstable();
+		addresses.dedup();
+
+		warehouse.get().get_transfers_by_addresses(network_id, addresses, max_block_height).await
 	}
 
 	pub async fn get_all_by_block_range(
 		warehouse: &Warehouse,
 		network_id: PrimaryId,
-		(block_height_min, block_height_max): (BlockHeight, BlockHeight),
+		block_range: (BlockHeight, BlockHeight),
 	) -> Result<Vec<Self>> {
-		Ok(warehouse
-			.get()
-			.query(&format!(
-				r#"
-					SELECT *
-					FROM {TABLE}
-					WHERE
-						network_id = ? AND
-						block_height >= ? AND
-						block_height <= ?
-					ORDER BY block_height ASC
-                "#
-			))
-			.bind(network_id)
-			.bind(block_height_min)
-			.bind(block_height_max)
-			.fetch_all::<Model>()
-			.await?)
+		warehouse.get().get_transfers_by_block_range(network_id, block_range).await
 	}
 
 	pub async fn get_all_by_uuids(
@@ -129,34 +158,21 @@ impl Model {
 		uuids.sort_unstable();
 		uuids.dedup();
 
-		Ok(warehouse
-			.get()
-			.query(&format!(
-				r#"
-					SELECT *
-					FROM {TABLE}
-					WHERE uuid IN ?
-                "#
-			))
-			.bind(uuids)
-			.fetch_all::<Model>()
-			.await?)
+		warehouse.get().get_transfers_by_uuids(uuids).await
 	}
 
 	pub async fn delete_all_by_network_id(
 		warehouse: &Warehouse,
 		network_ids: PrimaryIds,
 	) -> Result<()> {
-		Ok(warehouse
-			.get()
-			.query(&format!(
-				r#"
-					SET allow_experimental_lightweight_delete = true;
-					DELETE FROM {TABLE} WHERE network_id IN ?
-                "#
-			))
-			.bind(network_ids.into_iter().collect::<Vec<PrimaryId>>())
-			.execute()
-			.await?)
+		warehouse.get().delete_all_by_network_ids(TABLE, network_ids.into_iter().collect()).await
+	}
+
+	pub async fn delete_all_after_block_height(
+		warehouse: &Warehouse,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+	) -> Result<()> {
+		warehouse.get().delete_all_after_block_height(TABLE, network_id, block_height).await
 	}
 }
@@ -0,0 +1,121 @@
+use eyre::Result;
+use sea_orm::{
+	entity::{prelude::*, *},
+	ConnectionTrait,
+};
+use sea_orm_migration::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	models::{BasicModel, PrimaryId, PrimaryIds},
+	utils, IdPrefix,
+};
+
+// customer addresses being monitored (kept apart from the labeled `Address` records)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, DeriveEntityModel)]
+#[sea_orm(table_name = "watchlist")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+	#[sea_orm(primary_key)]
+	#[serde(skip_serializing, skip_deserializing)]
+	pub watchlist_id: PrimaryId,
+	#[serde(skip_serializing)]
+	pub network_id: PrimaryId,
+	pub network: String,
+	pub id: String,
+	pub address: String,
+	pub description: String,
+	#[sea_orm(nullable)]
+	#[serde(skip_serializing)]
+	pub updated_at: Option<DateTime>,
+	pub created_at: DateTime,
+}
+
+pub use ActiveModel as WatchedAddressActiveModel;
+pub use Model as WatchedAddress;
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl BasicModel for Model {
+	type ActiveModel = ActiveModel;
+}
+
+impl Model {
+	pub fn new_model(
+		network_id: PrimaryId,
+		network: &str,
+		address: &str,
+		description: &str,
+	) -> ActiveModel {
+		ActiveModel {
+			network_id: Set(network_id),
+			network: Set(network.to_string()),
+			id: Set(utils::new_unique_id(IdPrefix::Watchlist)),
+			address: Set(address.to_string()),
+			description: Set(description.to_string()),
+			..Default::default()
+		}
+	}
+
+	pub async fn create_many<C>(c: &C, data: Vec<ActiveModel>) -> Result<PrimaryId>
+	where
+		C: ConnectionTrait,
+	{
+		let insert_result = Entity::insert_many(data)
+			.on_conflict(
+				OnConflict::columns([Column::NetworkId, Column::Address]).do_nothing().to_owned(),
+			)
+			.exec(c)
+			.await?;
+
+		Ok(insert_result.last_insert_id)
+	}
+
+	pub async fn get_all_by_addresses<C>(c: &C, mut addresses: Vec<String>) -> Result<Vec<Self>>
+	where
+		C: ConnectionTrait,
+	{
+		addresses.sort_unstable();
+		addresses.dedup();
+
+		Ok(Entity::find().filter(Column::Address.is_in(addresses)).all(c).await?)
+	}
+
+	pub async fn get_all_by_network_ids<C>(c: &C, network_ids: PrimaryIds) -> Result<Vec<Self>>
+	where
+		C: ConnectionTrait,
+	{
+		Ok(Entity::find().filter(Column::NetworkId.is_in(network_ids)).all(c).await?)
+	}
+
+	pub async fn get_all_by_network_id_and_addresses<C>(
+		c: &C,
+		network_id: PrimaryId,
+		mut addresses: Vec<String>,
+	) -> Result<Vec<Self>>
+	where
+		C: ConnectionTrait,
+	{
+		addresses.sort_unstable();
+		addresses.dedup();
+
+		Ok(Entity::find()
+			.filter(Column::NetworkId.eq(network_id))
+			.filter(Column::Address.is_in(addresses))
+			.all(c)
+			.await?)
+	}
+
+	pub async fn delete_all_by_network_ids<C>(c: &C, network_ids: PrimaryIds) -> Result<u64>
+	where
+		C: ConnectionTrait,
+	{
+		let res =
+			Entity::delete_many().filter(Column::NetworkId.is_in(network_ids)).exec(c).await?;
+
+		Ok(res.rows_affected)
+	}
+}
@@ -0,0 +1,98 @@
+use eyre::Result;
+use sea_orm::{
+	entity::{prelude::*, *},
+	ConnectionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	models::{BasicModel, PrimaryId},
+	utils, IdPrefix,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, DeriveEntityModel)]
+#[sea_orm(table_name = "webhooks")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+	#[sea_orm(primary_key)]
+	#[serde(skip_serializing, skip_deserializing)]
+	pub webhook_id: PrimaryId,
+	pub id: String,
+	pub url: String,
+	#[serde(skip_serializing)]
+	pub secret: String,
+	#[sea_orm(nullable)]
+	pub address: Option<String>,
+	#[sea_orm(nullable)]
+	pub entity: Option<String>,
+	#[sea_orm(nullable)]
+	pub tag: Option<String>,
+	#[sea_orm(nullable)]
+	pub min_hops: Option<i64>,
+	#[sea_orm(nullable)]
+	pub watchlist: Option<bool>,
+	pub is_active: bool,
+	#[sea_orm(nullable)]
+	#[serde(skip_serializing)]
+	pub updated_at: Option<DateTime>,
+	pub created_at: DateTime,
+}
+
+pub use ActiveModel as WebhookActiveModel;
+pub use Model as Webhook;
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl BasicModel for Model {
+	type ActiveModel = ActiveModel;
+}
+
+impl Model {
+	pub fn new_model(
+		url: &str,
+		address: Option<String>,
+		entity: Option<String>,
+		tag: Option<String>,
+		min_hops: Option<i64>,
+		watchlist: Option<bool>,
+	) -> ActiveModel {
+		ActiveModel {
+			id: Set(utils::new_unique_id(IdPrefix::Webhook)),
+			url: Set(url.to_string()),
+			secret: Set(utils::new_uuid().simple().to_string()),
+			address: Set(address),
+			entity: Set(entity),
+			tag: Set(tag),
+			min_hops: Set(min_hops),
+			watchlist: Set(watchlist),
+			is_active: Set(true),
+			..Default::default()
+		}
+	}
+
+	pub async fn get_all_active<C>(c: &C) -> Result<Vec<Self>>
+	where
+		C: ConnectionTrait,
+	{
+		Ok(Entity::find().filter(Column::IsActive.eq(true)).all(c).await?)
+	}
+
+	// whether a link from `entity` (tagged with `tags`) into `address` should be delivered
+	pub fn is_match(
+		&self,
+		address: &str,
+		is_watched: bool,
+		entity: &str,
+		tags: &[String],
+		hops: usize,
+	) -> bool {
+		self.address.as_ref().is_none_or(|a| a == address) &&
+			self.entity.as_ref().is_none_or(|e| e == entity) &&
+			self.tag.as_ref().is_none_or(|t| tags.contains(t)) &&
+			self.min_hops.is_none_or(|min_hops| hops as i64 >= min_hops) &&
+			self.watchlist.is_none_or(|watchlist| !watchlist || is_watched)
+	}
+}
@@ -0,0 +1,69 @@
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+pub const MAX_SEVERITY: u8 = 100;
+
+// What an onboarding flow should do with an address, given its score and configured thresholds
+#[derive(Display, Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Decision {
+	#[serde(rename = "accept")]
+	#[display(fmt = "accept")]
+	Accept,
+	#[serde(rename = "review")]
+	#[display(fmt = "review")]
+	Review,
+	#[serde(rename = "reject")]
+	#[display(fmt = "reject")]
+	Reject,
+}
+
+impl Decision {
+	pub fn new(score: f64, review_threshold: f64, reject_threshold: f64) -> Self {
+		if score >= reject_threshold {
+			Self::Reject
+		} else if score >= review_threshold {
+			Self::Review
+		} else {
+			Self::Accept
+		}
+	}
+}
+
+// risk (0-100) of an address being labeled with a tag of `severity`
+pub fn get_label_score(severity: u8) -> f64 {
+	severity.min(MAX_SEVERITY) as f64
+}
+
+// risk (0-100) of having received `percentage` of funds from an entity `hops` transfers away;
+// every extra hop multiplies it by `hop_decay`
+pub fn get_exposure_score(severity: u8, percentage: f64, hops: usize, hop_decay: f64) -> f64 {
+	let decay = hop_decay.clamp(0.0, 1.0).powi(hops.saturating_sub(1) as i32);
+	get_label_score(severity) * (percentage.clamp(0.0, 100.0) / 100.0) * decay
+}
+
+// combines independent factors, so the total grows with each one but never exceeds 100
+pub fn get_score(factors: &[f64]) -> f64 {
+	let safe = factors.iter().fold(1.0, |acc, score| acc * (1.0 - score.clamp(0.0, 100.0) / 100.0));
+	((1.0 - safe) * 10_000.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_score() {
+		assert_eq!(get_exposure_score(80, 50.0, 1, 0.5), 40.0);
+		assert_eq!(get_exposure_score(80, 50.0, 3, 0.5), 10.0);
+		assert_eq!(get_exposure_score(80, 50.0, 0, 0.5), 40.0);
+
+		assert_eq!(get_score(&[]), 0.0);
+		assert_eq!(get_score(&[40.0]), 40.0);
+		assert_eq!(get_score(&[50.0, 50.0]), 75.0);
+		assert_eq!(get_score(&[100.0, 10.0]), 100.0);
+
+		assert_eq!(Decision::new(10.0, 25.0, 75.0), Decision::Accept);
+		assert_eq!(Decision::new(25.0, 25.0, 75.0), Decision::Review);
+		assert_eq!(Decision::new(90.0, 25.0, 75.0), Decision::Reject);
+	}
+}
@@ -0,0 +1,239 @@
+use derive_more::Display;
+use ethers::types::U512;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::{chain::U256, models::Transfer};
+
+// How value from a source is assumed to propagate along a path of transfers:
+// - `Poison`: anything that touched the source is fully tainted
+// - `Haircut`: taint is diluted by the other funds moving in the same transaction
+// - `Fifo`: every address spends its funds in the order they arrived, so an outgoing transfer is
+//   only tainted by the part of it that drains the tainted incoming one (see `History`)
+#[derive(Display, Default, Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum TaintModel {
+	#[serde(rename = "poison")]
+	#[display(fmt = "poison")]
+	Poison,
+	#[serde(rename = "haircut")]
+	#[display(fmt = "haircut")]
+	#[default]
+	Haircut,
+	#[serde(rename = "fifo")]
+	#[display(fmt = "fifo")]
+	Fifo,
+}
+
+impl TaintModel {
+	// tainted amount that arrived at the end of `path` (transfers in chronological order);
+	// `history` is only used by fifo
+	pub fn get_tainted_amount(&self, path: &[&Transfer], history: &History) -> U256 {
+		let mut tainted = U256::zero();
+
+		for (i, transfer) in path.iter().enumerate() {
+			tainted = if i == 0 || *self == Self::Poison {
+				transfer.relative_amount
+			} else if path[i - 1].asset_address != transfer.asset_address {
+				// amounts are not comparable across assets, so carry over the tainted share
+				mul_div(transfer.relative_amount, tainted, path[i - 1].relative_amount)
+			} else {
+				match self {
+					Self::Haircut => mul_div(
+						transfer.relative_amount,
+						tainted.min(transfer.batch_amount),
+						transfer.batch_amount,
+					),
+					_ => {
+						// where the incoming & outgoing transfers sit in the address' queue
+						let prev = path[i - 1];
+						let inflow_start = history.get_inflows_before(prev);
+						let outflow_start = history.get_outflows_before(transfer);
+
+						let overlap = inflow_start
+							.saturating_add(prev.relative_amount)
+							.min(outflow_start.saturating_add(transfer.relative_amount))
+							.saturating_sub(inflow_start.max(outflow_start));
+
+						mul_div(overlap, tainted, prev.relative_amount)
+					}
+				}
+			};
+
+			if tainted.is_zero() {
+				break;
+			}
+		}
+
+		tainted
+	}
+}
+
+// transfers in & out of the addresses along paths, per `(network_id, address, asset_address)`.
+// transfers within the same block have no known order, so they're ordered by tx hash (and
+// incoming ones are not held back by outgoing ones). addresses without any history behave as if
+// the tainted funds were the only ones they ever had
+#[derive(Default)]
+pub struct History {
+	inflows: HashMap<(u64, String, String), Vec<Transfer>>,
+	outflows: HashMap<(u64, String, String), Vec<Transfer>>,
+}
+
+impl History {
+	pub fn new(transfers: Vec<Transfer>) -> Self {
+		let mut ret = Self::default();
+
+		for transfer in transfers.into_iter() {
+			let (network_id, asset_address) = (transfer.network_id, transfer.asset_address.clone());
+			ret.inflows
+				.entry((network_id, transfer.to_address.clone(), asset_address.clone()))
+				.or_default()
+				.push(transfer.clone());
+			ret.outflows
+				.entry((network_id, transfer.from_address.clone(), asset_address))
+				.or_default()
+				.push(transfer);
+		}
+
+		for transfers in ret.inflows.values_mut().chain(ret.outflows.values_mut()) {
+			transfers.sort_by(|a, b| get_position(a).cmp(&get_position(b)));
+			transfers.dedup_by_key(|t| t.uuid);
+		}
+
+		ret
+	}
+
+	// total that arrived at the recipient of `transfer` before it did
+	fn get_inflows_before(&self, transfer: &Transfer) -> U256 {
+		let key =
+			(transfer.network_id, transfer.to_address.clone(), transfer.asset_address.clone());
+		get_total_before(self.inflows.get(&key), transfer)
+	}
+
+	// total that left the sender of `transfer` before it did
+	fn get_outflows_before(&self, transfer: &Transfer) -> U256 {
+		let key =
+			(transfer.network_id, transfer.from_address.clone(), transfer.asset_address.clone());
+		get_total_before(self.outflows.get(&key), transfer)
+	}
+}
+
+fn get_position(transfer: &Transfer) -> (u64, &str, Uuid) {
+	(transfer.block_height, &transfer.tx_hash, transfer.uuid)
+}
+
+fn get_total_before(transfers: Option<&Vec<Transfer>>, transfer: &Transfer) -> U256 {
+	transfers
+		.into_iter()
+		.flatten()
+		.take_while(|t| get_position(t) < get_position(transfer))
+		.fold(U256::zero(), |total, t| total.saturating_add(t.relative_amount))
+}
+
+// share of `total` that is tainted, as a percentage with two decimals
+pub fn get_percentage(tainted: U256, total: U256) -> f64 {
+	if total.is_zero() {
+		return 0.0;
+	}
+
+	let basis_points = mul_div(tainted.min(total), U256::from(10_000), total);
+	basis_points.as_u64() as f64 / 100.0
+}
+
+fn mul_div(a: U256, b: U256, c: U256) -> U256 {
+	if c.is_zero() {
+		return U256::zero();
+	}
+
+	U256::try_from(a.full_mul(b) / U512::from(c)).unwrap_or(U256::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::chain::ModuleId;
+
+	fn transfer(asset_address: &str, relative_amount: u64, batch_amount: u64) -> Transfer {
+		Transfer::new(
+			ModuleId::EvmTransfer,
+			1,
+			1,
+			"",
+			0,
+			"",
+			"",
+			Some(asset_address.to_string()),
+			U256::from(relative_amount),
+			U256::from(batch_amount),
+			0,
+		)
+	}
+
+	#[test]
+	fn test_get_tainted_amount() {
+		// 100 leaves the source, 60 of it is mixed into a 300 batch, 150 of that batch moves on
+		let path = [transfer("", 100, 100), transfer("", 60, 300), transfer("", 150, 200)];
+		let path = path.iter().collect::<Vec<&Transfer>>();
+
+		let history = History::default();
+
+		assert_eq!(TaintModel::Poison.get_tainted_amount(&path, &history), U256::from(150));
+		assert_eq!(TaintModel::Haircut.get_tainted_amount(&path, &history), U256::from(15));
+		assert_eq!(TaintModel::Fifo.get_tainted_amount(&path, &history), U256::from(60));
+
+		// switching assets keeps the tainted share
+		let path = [transfer("", 100, 100), transfer("", 50, 200), transfer("0xa", 1_000, 1_000)];
+		let path = path.iter().collect::<Vec<&Transfer>>();
+
+		assert_eq!(TaintModel::Haircut.get_tainted_amount(&path, &history), U256::from(500));
+		assert_eq!(TaintModel::Fifo.get_tainted_amount(&path, &history), U256::from(1_000));
+	}
+
+	#[test]
+	fn test_get_tainted_amount_fifo() {
+		let transfer = |block_height, from_address, to_address, amount: u64| {
+			let tx_hash = format!("0x{block_height}{from_address}{to_address}");
+			Transfer::new(
+				ModuleId::EvmTransfer,
+				1,
+				block_height,
+				&tx_hash,
+				0,
+				from_address,
+				to_address,
+				None,
+				U256::from(amount),
+				U256::from(amount),
+				0,
+			)
+		};
+
+		// "a" holds 20 (50 in, 30 out) before 100 tainted arrive, so 40 of the 60 it sends to
+		// "b" are tainted. "b" already holds 200, so only 50 of the 250 it sends on are from
+		// that tainted 60, making them 40/60 tainted
+		let path =
+			[transfer(2, "s", "a", 100), transfer(3, "a", "b", 60), transfer(4, "b", "c", 250)];
+		let mut history =
+			vec![transfer(1, "x", "a", 50), transfer(1, "a", "y", 30), transfer(1, "z", "b", 200)];
+		history.extend(path.clone());
+		let history = History::new(history);
+		let path = path.iter().collect::<Vec<&Transfer>>();
+
+		assert_eq!(TaintModel::Fifo.get_tainted_amount(&path[..2], &history), U256::from(40));
+		assert_eq!(TaintModel::Fifo.get_tainted_amount(&path, &history), U256::from(33));
+
+		// outgoing transfers that drain older funds first don't carry any taint
+		let path = [transfer(2, "s", "a", 100), transfer(3, "a", "b", 20)];
+		let history = History::new(vec![transfer(1, "x", "a", 50), path[0].clone()]);
+		let path = path.iter().collect::<Vec<&Transfer>>();
+
+		assert_eq!(TaintModel::Fifo.get_tainted_amount(&path, &history), U256::zero());
+	}
+
+	#[test]
+	fn test_get_percentage() {
+		assert_eq!(get_percentage(U256::from(1), U256::from(3)), 33.33);
+		assert_eq!(get_percentage(U256::from(5), U256::from(3)), 100.0);
+		assert_eq!(get_percentage(U256::from(5), U256::zero()), 0.0);
+	}
+}
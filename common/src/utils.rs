@@ -102,6 +102,7 @@ pub fn has_pathname(url: &str) -> bool {
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow)]
 mod tests {
 	use super::*;
 	use std::collections::HashMap;
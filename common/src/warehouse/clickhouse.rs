@@ -0,0 +1,1010 @@
+use async_trait::async_trait;
+use clickhouse::{query::Query, Client as ClickhouseClient, Row};
+use eyre::{Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::{
+	chain::{u256, U256},
+	models::{
+		amount, balance, cluster, downstream_link, link, reconciliation, relation, transfer,
+		Amount, Balance, Cluster, DownstreamLink, Link, LinkFilters, PrimaryId, Reconciliation,
+		ReconciliationStats, Relation, RelationReason, Transfer,
+	},
+	utils,
+	warehouse::{migrations, Rows, WarehouseTrait},
+	BlockHeight,
+};
+
+// how many transfers are checked against the warehouse at a time before being inserted
+const EXISTING_TRANSFERS_CHUNK: usize = 10_000;
+
+pub struct Clickhouse {
+	url_without_database: String,
+	client: ClickhouseClient,
+}
+
+impl Clickhouse {
+	pub async fn new(url: &str) -> Result<Self> {
+		let (url_without_database, db_name) = utils::without_pathname(url);
+
+		// create db if doesn't exist + check that connection is good
+		ClickhouseClient::default()
+			.with_url(url_without_database.clone())
+			.query(&format!("CREATE DATABASE IF NOT EXISTS {db_name};"))
+			.execute()
+			.await
+			.wrap_err(url_without_database.clone())?;
+
+		Ok(Self {
+			url_without_database: url_without_database.clone(),
+			client: ClickhouseClient::default()
+				.with_url(url_without_database)
+				.with_database(db_name),
+		})
+	}
+
+	// uuids of `transfers` that are already in the warehouse; narrowed down by the sorting key
+	// so it doesn't turn into a full scan
+	async fn get_existing_transfer_uuids(&self, transfers: &[Transfer]) -> Result<HashSet<Uuid>> {
+		#[derive(Row, Deserialize)]
+		struct Existing {
+			#[serde(with = "clickhouse::serde::uuid")]
+			uuid: Uuid,
+		}
+
+		let module_ids = transfers.iter().map(|t| t.module_id).collect::<HashSet<u16>>();
+		let network_ids = transfers.iter().map(|t| t.network_id).collect::<HashSet<u64>>();
+		let block_height_min = transfers.iter().map(|t| t.block_height).min().unwrap_or_default();
+		let block_height_max = transfers.iter().map(|t| t.block_height).max().unwrap_or_default();
+
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT uuid
+					FROM {}
+					WHERE
+						module_id IN ? AND
+						network_id IN ? AND
+						block_height >= ? AND
+						block_height <= ? AND
+						uuid IN ?
+				"#,
+				transfer::TABLE
+			))
+			.bind(module_ids.into_iter().collect::<Vec<u16>>())
+			.bind(network_ids.into_iter().collect::<Vec<u64>>())
+			.bind(block_height_min)
+			.bind(block_height_max)
+			.bind(transfers.iter().map(|t| t.uuid).collect::<Vec<Uuid>>())
+			.fetch_all::<Existing>()
+			.await?
+			.into_iter()
+			.map(|e| e.uuid)
+			.collect())
+	}
+
+	// rows are streamed into a single insert as they come, which flushes them out in chunks
+	async fn insert<T>(&self, table: &str, models: Rows<T>) -> Result<()>
+	where
+		T: Row + Serialize,
+	{
+		let mut insert = self.client.insert(table)?;
+		for model in models {
+			insert.write(&model).await?;
+		}
+
+		Ok(insert.end().await?)
+	}
+}
+
+#[async_trait]
+impl WarehouseTrait for Clickhouse {
+	async fn create_migrations_table(&self) -> Result<()> {
+		self.client
+			.query(&format!(
+				r#"
+					CREATE TABLE IF NOT EXISTS {}
+					(
+						version String,
+						applied_at DateTime
+					)
+					ENGINE = ReplacingMergeTree
+					ORDER BY version
+				"#,
+				migrations::TABLE
+			))
+			.execute()
+			.await
+			.wrap_err(self.url_without_database.clone())
+	}
+
+	async fn get_applied_migrations(&self) -> Result<Vec<String>> {
+		let exists = self
+			.client
+			.query(&format!("EXISTS TABLE {}", migrations::TABLE))
+			.fetch_one::<u8>()
+			.await?;
+		if exists == 0 {
+			return Ok(vec![]);
+		}
+
+		Ok(self
+			.client
+			.query(&format!("SELECT version FROM {} FINAL ORDER BY version", migrations::TABLE))
+			.fetch_all::<String>()
+			.await?)
+	}
+
+	// ddl can't be rolled back here, so a failed step leaves the migration unrecorded (steps
+	// are expected to be safe to re-run)
+	async fn apply_migration(&self, version: &str, statements: Vec<String>) -> Result<()> {
+		for statement in statements.iter() {
+			self.client.query(statement).execute().await.wrap_err(version.to_string())?;
+		}
+
+		Ok(self
+			.client
+			.query(&format!(
+				"INSERT INTO {} (version, applied_at) VALUES (?, now())",
+				migrations::TABLE
+			))
+			.bind(version)
+			.execute()
+			.await?)
+	}
+
+	async fn revert_migration(&self, version: &str, statements: Vec<String>) -> Result<()> {
+		for statement in statements.iter() {
+			self.client.query(statement).execute().await.wrap_err(version.to_string())?;
+		}
+
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SET allow_experimental_lightweight_delete = true;
+					DELETE FROM {} WHERE version = ?
+				"#,
+				migrations::TABLE
+			))
+			.bind(version)
+			.execute()
+			.await?)
+	}
+
+	async fn delete_all_by_network_ids(
+		&self,
+		table: &str,
+		network_ids: Vec<PrimaryId>,
+	) -> Result<()> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SET allow_experimental_lightweight_delete = true;
+					DELETE FROM {table} WHERE network_id IN ?
+                "#
+			))
+			.bind(network_ids)
+			.execute()
+			.await?)
+	}
+
+	async fn delete_all_after_block_height(
+		&self,
+		table: &str,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+	) -> Result<()> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SET allow_experimental_lightweight_delete = true;
+					DELETE FROM {table} WHERE network_id = ? AND block_height > ?
+                "#
+			))
+			.bind(network_id)
+			.bind(block_height)
+			.execute()
+			.await?)
+	}
+
+	async fn create_transfers(&self, mut models: Rows<Transfer>) -> Result<()> {
+		// transfer uuids are derived from their contents, so whatever is already stored (eg
+		// blocks re-indexed after a restart) is skipped here instead of lingering until a merge
+		let mut insert = self.client.insert(transfer::TABLE)?;
+
+		loop {
+			let chunk = models.by_ref().take(EXISTING_TRANSFERS_CHUNK).collect::<Vec<Transfer>>();
+			if chunk.is_empty() {
+				break;
+			}
+
+			let existing = self.get_existing_transfer_uuids(&chunk).await?;
+			for model in chunk.into_iter().filter(|m| !existing.contains(&m.uuid)) {
+				insert.write(&model).await?;
+			}
+		}
+
+		Ok(insert.end().await?)
+	}
+
+	async fn get_first_transfer_by_source(
+		&self,
+		network_id: PrimaryId,
+		address: &str,
+	) -> Result<Option<Transfer>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE network_id = ? AND from_address = ?
+					ORDER BY created_at ASC
+					LIMIT 1
+                "#,
+				transfer::TABLE
+			))
+			.bind(network_id)
+			.bind(address)
+			.fetch_all::<Transfer>()
+			.await?
+			.into_iter()
+			.next())
+	}
+
+	async fn get_first_transfer_by_target(
+		&self,
+		network_id: PrimaryId,
+		address: &str,
+	) -> Result<Option<Transfer>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE network_id = ? AND to_address = ?
+					ORDER BY created_at ASC
+					LIMIT 1
+                "#,
+				transfer::TABLE
+			))
+			.bind(network_id)
+			.bind(address)
+			.fetch_all::<Transfer>()
+			.await?
+			.into_iter()
+			.next())
+	}
+
+	async fn get_transfers_by_targets(
+		&self,
+		network_id: PrimaryId,
+		addresses: Vec<String>,
+		(block_height_min, block_height_max): (BlockHeight, BlockHeight),
+	) -> Result<Vec<Transfer>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE
+						network_id = ? AND
+						to_address IN ? AND
+						length(from_address) > 0 AND
+						block_height >= ? AND
+						block_height <= ?
+					ORDER BY block_height DESC
+                "#,
+				transfer::TABLE
+			))
+			.bind(network_id)
+			.bind(addresses)
+			.bind(block_height_min)
+			.bind(block_height_max)
+			.fetch_all::<Transfer>()
+			.await?)
+	}
+
+	async fn get_transfers_by_addresses(
+		&self,
+		network_id: PrimaryId,
+		addresses: Vec<String>,
+		max_block_height: BlockHeight,
+	) -> Result<Vec<Transfer>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE
+						network_id = ? AND
+						(from_address IN ? OR to_address IN ?) AND
+						block_height <= ?
+                "#,
+				transfer::TABLE
+			))
+			.bind(network_id)
+			.bind(addresses.clone())
+			.bind(addresses)
+			.bind(max_block_height)
+			.fetch_all::<Transfer>()
+			.await?)
+	}
+
+	async fn get_transfers_by_block_range(
+		&self,
+		network_id: PrimaryId,
+		(block_height_min, block_height_max): (BlockHeight, BlockHeight),
+	) -> Result<Vec<Transfer>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE
+						network_id = ? AND
+						block_height >= ? AND
+						block_height <= ?
+					ORDER BY block_height ASC
+                "#,
+				transfer::TABLE
+			))
+			.bind(network_id)
+			.bind(block_height_min)
+			.bind(block_height_max)
+			.fetch_all::<Transfer>()
+			.await?)
+	}
+
+	async fn get_transfers_by_uuids(&self, uuids: Vec<Uuid>) -> Result<Vec<Transfer>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE uuid IN ?
+                "#,
+				transfer::TABLE
+			))
+			.bind(uuids)
+			.fetch_all::<Transfer>()
+			.await?)
+	}
+
+	// `amounts` is replacing on `uuid` and `balances` only reads the merged rows, so amounts
+	// that are already stored (eg blocks re-indexed after a restart) can safely go in again
+	async fn create_amounts(&self, models: Rows<Amount>) -> Result<()> {
+		self.insert(amount::TABLE, models).await
+	}
+
+	async fn get_amount_network_ids_by_addresses(
+		&self,
+		addresses: Vec<String>,
+	) -> Result<Vec<PrimaryId>> {
+		#[derive(Row, Deserialize)]
+		struct Data {
+			network_id: u64,
+		}
+
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT DISTINCT network_id
+					FROM {}
+					WHERE address IN ?
+                "#,
+				amount::TABLE
+			))
+			.bind(addresses)
+			.fetch_all::<Data>()
+			.await?
+			.into_iter()
+			.map(|d| d.network_id as PrimaryId)
+			.collect())
+	}
+
+	async fn get_amounts_received_by_addresses(
+		&self,
+		addresses: Vec<String>,
+		(since, until): (u32, u32),
+	) -> Result<HashMap<(PrimaryId, String), U256>> {
+		#[derive(Row, Deserialize)]
+		struct Data {
+			network_id: u64,
+			asset_address: String,
+			#[serde(with = "u256")]
+			amount_in: U256,
+		}
+
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT network_id, asset_address, SUM(amount_in) as amount_in
+					FROM {} FINAL
+					WHERE
+						address IN ? AND
+						created_at >= toDateTime(?) AND
+						created_at <= toDateTime(?)
+					GROUP BY (network_id, asset_address)
+                "#,
+				amount::TABLE
+			))
+			.bind(addresses)
+			.bind(since)
+			.bind(until)
+			.fetch_all::<Data>()
+			.await?
+			.into_iter()
+			.map(|d| ((d.network_id as PrimaryId, d.asset_address), d.amount_in))
+			.collect())
+	}
+
+	async fn get_balances_by_addresses(&self, addresses: Vec<String>) -> Result<Vec<Balance>> {
+		// @TODO until I256 is implemented, doing this hacky "group by" statement
+		// ideally: "SELECT ?fields FROM {TABLE} WHERE address IN ?"
+
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM (
+	                    SELECT
+	                        network_id,
+	                        address,
+	                        asset_address,
+	                        SUM(balance) as balance
+	                    FROM {}
+	                    WHERE address IN ?
+	                    GROUP BY (network_id, address, asset_address)
+					)
+					WHERE balance >= 0
+                "#,
+				balance::TABLE
+			))
+			.bind(addresses)
+			.fetch_all::<Balance>()
+			.await?)
+	}
+
+	async fn get_balances_random_sample(
+		&self,
+		network_id: PrimaryId,
+		limit: u64,
+	) -> Result<Vec<Balance>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT
+						network_id,
+						address,
+						asset_address,
+						SUM(balance) as balance
+					FROM {}
+					WHERE
+						network_id = ? AND
+						(address, asset_address) IN (
+							SELECT address, asset_address
+							FROM {}
+							WHERE network_id = ?
+							ORDER BY rand()
+							LIMIT ?
+						)
+					GROUP BY (network_id, address, asset_address)
+                "#,
+				balance::TABLE,
+				balance::TABLE
+			))
+			.bind(network_id)
+			.bind(network_id)
+			.bind(limit)
+			.fetch_all::<Balance>()
+			.await?)
+	}
+
+	async fn create_links(&self, models: Rows<Link>) -> Result<()> {
+		self.insert(link::TABLE, models).await
+	}
+
+	async fn get_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+		filters: &LinkFilters,
+	) -> Result<Vec<Link>> {
+		let query = self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE to_address IN ?{}
+                "#,
+				link::TABLE,
+				get_link_conditions(filters)
+			))
+			.bind(&addresses);
+
+		Ok(bind_link_filters(query, filters, &addresses).fetch_all::<Link>().await?)
+	}
+
+	async fn get_distinct_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+		filters: &LinkFilters,
+	) -> Result<Vec<Link>> {
+		let query = self
+			.client
+			.query(&format!(
+				r#"
+					SELECT DISTINCT ON (network_id, from_address) *
+					FROM {}
+					WHERE to_address IN ?{}
+					ORDER BY LENGTH(transfer_uuids) ASC
+                "#,
+				link::TABLE,
+				get_link_conditions(filters)
+			))
+			.bind(&addresses);
+
+		Ok(bind_link_filters(query, filters, &addresses).fetch_all::<Link>().await?)
+	}
+
+	async fn get_links_to_seed_blocks(
+		&self,
+		network_id: PrimaryId,
+		(block_height_min, block_height_max): (BlockHeight, BlockHeight),
+	) -> Result<Vec<Link>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE network_id = ? AND to_address IN (
+					    SELECT from_address
+					    FROM {}
+					    WHERE
+							network_id = ? AND
+							length(from_address) > 0 AND
+							length(to_address) > 0 AND
+							block_height >= ? AND
+							block_height <= ?
+					)
+                "#,
+				link::TABLE,
+				transfer::TABLE
+			))
+			.bind(network_id)
+			.bind(network_id)
+			.bind(block_height_min)
+			.bind(block_height_max)
+			.fetch_all::<Link>()
+			.await?)
+	}
+
+	async fn delete_links_by_sources(
+		&self,
+		sources: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SET allow_experimental_lightweight_delete = true;
+					DELETE FROM {} WHERE {}
+				"#,
+				link::TABLE,
+				get_network_id_address_tuples(sources, "from_address"),
+			))
+			.execute()
+			.await?)
+	}
+
+	async fn delete_links_by_newly_added_addresses(
+		&self,
+		targets: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()> {
+		// `arraySlice(transfer_uuids, 2, -1)` leaves out the first and the last transfers
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SET allow_experimental_lightweight_delete = true;
+					DELETE FROM {}
+					WHERE
+						length(transfer_uuids) > 2 AND
+						hasAny(
+							arraySlice(transfer_uuids, 2, -1),
+							(
+								SELECT DISTINCT groupArray(transfer_uuids[-1])
+								FROM {}
+								WHERE length(transfer_uuids) > 0 AND {}
+							)
+						)
+				"#,
+				link::TABLE,
+				link::TABLE,
+				get_network_id_address_tuples(targets, "to_address"),
+			))
+			.execute()
+			.await?)
+	}
+
+	async fn create_downstream_links(&self, models: Rows<DownstreamLink>) -> Result<()> {
+		self.insert(downstream_link::TABLE, models).await
+	}
+
+	async fn get_downstream_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+	) -> Result<Vec<DownstreamLink>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE from_address IN ?
+                "#,
+				downstream_link::TABLE
+			))
+			.bind(addresses)
+			.fetch_all::<DownstreamLink>()
+			.await?)
+	}
+
+	async fn get_distinct_downstream_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+	) -> Result<Vec<DownstreamLink>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT DISTINCT ON (network_id, to_address) *
+					FROM {}
+					WHERE from_address IN ?
+					ORDER BY LENGTH(transfer_uuids) ASC
+                "#,
+				downstream_link::TABLE
+			))
+			.bind(addresses)
+			.fetch_all::<DownstreamLink>()
+			.await?)
+	}
+
+	async fn delete_downstream_links_by_targets(
+		&self,
+		targets: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SET allow_experimental_lightweight_delete = true;
+					DELETE FROM {} WHERE {}
+				"#,
+				downstream_link::TABLE,
+				get_network_id_address_tuples(targets, "to_address"),
+			))
+			.execute()
+			.await?)
+	}
+
+	async fn delete_downstream_links_by_newly_added_addresses(
+		&self,
+		targets: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SET allow_experimental_lightweight_delete = true;
+					DELETE FROM {}
+					WHERE
+						length(transfer_uuids) > 1 AND
+						hasAny(
+							arrayPopBack(transfer_uuids),
+							(
+								SELECT groupArray(uuid)
+								FROM {}
+								WHERE {}
+							)
+						)
+				"#,
+				downstream_link::TABLE,
+				transfer::TABLE,
+				get_network_id_address_tuples(targets, "to_address"),
+			))
+			.execute()
+			.await?)
+	}
+
+	async fn create_relations(&self, models: Rows<Relation>) -> Result<()> {
+		self.insert(relation::TABLE, models).await
+	}
+
+	async fn get_relations_by_block_range(
+		&self,
+		network_id: PrimaryId,
+		reason: RelationReason,
+		(block_height_min, block_height_max): (BlockHeight, BlockHeight),
+	) -> Result<Vec<Relation>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE
+						network_id = ? AND
+						reason = ? AND
+						block_height >= ? AND
+						block_height <= ?
+                "#,
+				relation::TABLE
+			))
+			.bind(network_id)
+			.bind(reason as u16)
+			.bind(block_height_min)
+			.bind(block_height_max)
+			.fetch_all::<Relation>()
+			.await?)
+	}
+
+	async fn get_relations_by_addresses(
+		&self,
+		network_id: PrimaryId,
+		reason: RelationReason,
+		addresses: Vec<String>,
+		max_block_height: BlockHeight,
+	) -> Result<Vec<Relation>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE
+						network_id = ? AND
+						reason = ? AND
+						(from_address IN ? OR to_address IN ?) AND
+						block_height <= ?
+                "#,
+				relation::TABLE
+			))
+			.bind(network_id)
+			.bind(reason as u16)
+			.bind(addresses.clone())
+			.bind(addresses)
+			.bind(max_block_height)
+			.fetch_all::<Relation>()
+			.await?)
+	}
+
+	async fn get_relations_by_address_paginated(
+		&self,
+		address: &str,
+		offset: u64,
+		limit: u64,
+	) -> Result<Vec<Relation>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {} FINAL
+					WHERE from_address = ? OR to_address = ?
+					ORDER BY block_height DESC, tx_hash, from_address, to_address
+					LIMIT ? OFFSET ?
+                "#,
+				relation::TABLE
+			))
+			.bind(address)
+			.bind(address)
+			.bind(limit)
+			.bind(offset)
+			.fetch_all::<Relation>()
+			.await?)
+	}
+
+	async fn create_clusters(&self, models: Rows<Cluster>) -> Result<()> {
+		self.insert(cluster::TABLE, models).await
+	}
+
+	async fn get_clusters_by_addresses(&self, addresses: Vec<String>) -> Result<Vec<Cluster>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {} FINAL
+					WHERE address IN ?
+                "#,
+				cluster::TABLE
+			))
+			.bind(addresses)
+			.fetch_all::<Cluster>()
+			.await?)
+	}
+
+	async fn get_clusters_by_cluster_ids(
+		&self,
+		network_id: PrimaryId,
+		cluster_ids: Vec<String>,
+	) -> Result<Vec<Cluster>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {} FINAL
+					WHERE network_id = ? AND cluster_id IN ?
+                "#,
+				cluster::TABLE
+			))
+			.bind(network_id)
+			.bind(cluster_ids)
+			.fetch_all::<Cluster>()
+			.await?)
+	}
+
+	async fn get_clusters_after_block_height(
+		&self,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+	) -> Result<Vec<Cluster>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {} FINAL
+					WHERE network_id = ? AND block_height > ?
+                "#,
+				cluster::TABLE
+			))
+			.bind(network_id)
+			.bind(block_height)
+			.fetch_all::<Cluster>()
+			.await?)
+	}
+
+	async fn get_clusters_by_member(
+		&self,
+		network_id: PrimaryId,
+		address: &str,
+	) -> Result<Vec<Cluster>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT *
+					FROM {} FINAL
+					WHERE network_id = ? AND cluster_id IN (
+						SELECT cluster_id
+						FROM {} FINAL
+						WHERE network_id = ? AND address = ?
+					)
+                "#,
+				cluster::TABLE,
+				cluster::TABLE
+			))
+			.bind(network_id)
+			.bind(network_id)
+			.bind(address)
+			.fetch_all::<Cluster>()
+			.await?)
+	}
+
+	async fn create_reconciliations(&self, models: Rows<Reconciliation>) -> Result<()> {
+		self.insert(reconciliation::TABLE, models).await
+	}
+
+	async fn get_reconciliation_stats(&self, since: u32) -> Result<Vec<ReconciliationStats>> {
+		Ok(self
+			.client
+			.query(&format!(
+				r#"
+					SELECT
+						network_id,
+						module_id,
+						count() AS checked,
+						countIf(indexed_balance != live_balance) AS drifted,
+						toUInt32(max(created_at)) AS checked_at
+					FROM {}
+					WHERE created_at >= toDateTime(?)
+					GROUP BY (network_id, module_id)
+					ORDER BY (network_id, module_id)
+                "#,
+				reconciliation::TABLE
+			))
+			.bind(since)
+			.fetch_all::<ReconciliationStats>()
+			.await?)
+	}
+}
+
+fn get_link_conditions(filters: &LinkFilters) -> String {
+	let mut ret = String::new();
+
+	if filters.min_hops.is_some() {
+		ret.push_str(" AND length(transfer_uuids) >= ?");
+	}
+	if filters.max_hops.is_some() {
+		ret.push_str(" AND length(transfer_uuids) <= ?");
+	}
+	if filters.since.is_some() {
+		ret.push_str(" AND created_at >= toDateTime(?)");
+	}
+	if filters.until.is_some() {
+		ret.push_str(" AND created_at <= toDateTime(?)");
+	}
+	if filters.min_amount.is_some() {
+		ret.push_str(&format!(
+			r#"
+				AND NOT hasAny(transfer_uuids, (
+					SELECT groupArray(uuid)
+					FROM {}
+					WHERE
+						uuid IN (SELECT arrayJoin(transfer_uuids) FROM {} WHERE to_address IN ?) AND
+						(asset_address != ? OR relative_amount < toUInt256(?))
+				))
+			"#,
+			transfer::TABLE,
+			link::TABLE
+		));
+	}
+
+	ret
+}
+
+// `addresses` are the same ones links are being fetched for
+fn bind_link_filters(mut query: Query, filters: &LinkFilters, addresses: &[String]) -> Query {
+	if let Some(min_hops) = filters.min_hops {
+		query = query.bind(min_hops);
+	}
+	if let Some(max_hops) = filters.max_hops {
+		query = query.bind(max_hops);
+	}
+	if let Some(since) = filters.since {
+		query = query.bind(since);
+	}
+	if let Some(until) = filters.until {
+		query = query.bind(until);
+	}
+	if let Some((asset_address, min_amount)) = &filters.min_amount {
+		query = query.bind(addresses).bind(asset_address).bind(min_amount.to_string());
+	}
+
+	query
+}
+
+fn get_network_id_address_tuples(map: HashMap<PrimaryId, HashSet<String>>, field: &str) -> String {
+	map.into_iter()
+		.map(|(network_id, addresses)| {
+			let escaped_addresses = addresses
+				.into_iter()
+				.map(|a| format!("'{}'", a.replace('\\', "\\\\").replace('\'', "\\'")))
+				.collect::<Vec<String>>()
+				.join(",");
+
+			format!("(network_id = {network_id} AND {field} IN ({escaped_addresses}))")
+		})
+		.collect::<Vec<String>>()
+		.join(" OR ")
+}
@@ -0,0 +1,142 @@
+use crate::{
+	models::transfer::TABLE,
+	warehouse::{migrations::MigrationTrait, Driver},
+};
+
+pub struct Migration;
+
+// tables are created with `IF NOT EXISTS`, so warehouses set up before migrations were
+// versioned adopt them as-is. sql drivers have no replacing engine, so unique constraints +
+// `ON CONFLICT` take care of duplicate rows instead
+impl MigrationTrait for Migration {
+	fn name(&self) -> &str {
+		module_path!().rsplit("::").next().unwrap_or_default()
+	}
+
+	fn up(&self, driver: &Driver) -> Vec<String> {
+		match driver {
+			Driver::Clickhouse => vec![format!(
+				r#"
+					CREATE TABLE IF NOT EXISTS {TABLE}
+					(
+						uuid UUID,
+						module_id UInt16,
+						network_id UInt64,
+						block_height UInt64,
+						tx_hash String,
+						from_address String,
+						to_address String,
+						asset_address String,
+						token_id String,
+						relative_amount UInt256,
+						batch_amount UInt256,
+						created_at DateTime
+					)
+					ENGINE = ReplacingMergeTree
+					ORDER BY (
+						module_id,
+						network_id,
+						block_height,
+						tx_hash,
+						from_address,
+						to_address,
+						asset_address,
+						relative_amount,
+						batch_amount,
+						token_id
+					)
+					PARTITION BY toYYYYMM(created_at)
+				"#
+			)],
+			// u64/u16 map onto signed columns, u256 onto `NUMERIC(78)` and `created_at` stays a
+			// unix timestamp
+			Driver::PostgreSQL => vec![
+				format!(
+					r#"
+						CREATE TABLE IF NOT EXISTS {TABLE}
+						(
+							uuid UUID NOT NULL,
+							module_id INTEGER NOT NULL,
+							network_id BIGINT NOT NULL,
+							block_height BIGINT NOT NULL,
+							tx_hash TEXT NOT NULL,
+							from_address TEXT NOT NULL,
+							to_address TEXT NOT NULL,
+							asset_address TEXT NOT NULL,
+							token_id TEXT NOT NULL DEFAULT '',
+							relative_amount NUMERIC(78) NOT NULL,
+							batch_amount NUMERIC(78) NOT NULL,
+							created_at BIGINT NOT NULL,
+							UNIQUE (
+								module_id,
+								network_id,
+								block_height,
+								tx_hash,
+								from_address,
+								to_address,
+								asset_address,
+								relative_amount,
+								batch_amount,
+								token_id
+							)
+						)
+					"#
+				),
+				format!("CREATE INDEX IF NOT EXISTS {TABLE}_uuid ON {TABLE} (uuid)"),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_from_address ON {TABLE} (network_id, \
+					 from_address)"
+				),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_to_address ON {TABLE} (network_id, to_address)"
+				),
+			],
+			// u256 values are zero-padded text, so they still compare as numbers
+			Driver::SQLite => vec![
+				format!(
+					r#"
+						CREATE TABLE IF NOT EXISTS {TABLE}
+						(
+							uuid TEXT NOT NULL,
+							module_id INTEGER NOT NULL,
+							network_id INTEGER NOT NULL,
+							block_height INTEGER NOT NULL,
+							tx_hash TEXT NOT NULL,
+							from_address TEXT NOT NULL,
+							to_address TEXT NOT NULL,
+							asset_address TEXT NOT NULL,
+							token_id TEXT NOT NULL DEFAULT '',
+							relative_amount TEXT NOT NULL,
+							batch_amount TEXT NOT NULL,
+							created_at INTEGER NOT NULL,
+							UNIQUE (
+								module_id,
+								network_id,
+								block_height,
+								tx_hash,
+								from_address,
+								to_address,
+								asset_address,
+								relative_amount,
+								batch_amount,
+								token_id
+							)
+						)
+					"#
+				),
+				format!("CREATE INDEX IF NOT EXISTS {TABLE}_uuid ON {TABLE} (uuid)"),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_from_address ON {TABLE} (network_id, \
+					 from_address)"
+				),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_to_address ON {TABLE} (network_id, to_address)"
+				),
+			],
+		}
+	}
+
+	fn down(&self, _driver: &Driver) -> Vec<String> {
+		vec![format!("DROP TABLE IF EXISTS {TABLE}")]
+	}
+}
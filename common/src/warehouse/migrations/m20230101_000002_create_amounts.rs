@@ -0,0 +1,96 @@
+use crate::{
+	models::amount::TABLE,
+	warehouse::{migrations::MigrationTrait, Driver},
+};
+
+pub struct Migration;
+
+impl MigrationTrait for Migration {
+	fn name(&self) -> &str {
+		module_path!().rsplit("::").next().unwrap_or_default()
+	}
+
+	fn up(&self, driver: &Driver) -> Vec<String> {
+		match driver {
+			Driver::Clickhouse => vec![format!(
+				r#"
+					CREATE TABLE IF NOT EXISTS {TABLE}
+					(
+						module_id UInt16,
+						network_id UInt64,
+						block_height UInt64,
+						tx_hash String,
+						address String,
+						asset_address String,
+						amount_in UInt256,
+						amount_out UInt256,
+						created_at DateTime
+					)
+					ENGINE = ReplacingMergeTree
+					ORDER BY (
+						network_id,
+						block_height,
+						tx_hash,
+						address,
+						asset_address
+					)
+					PARTITION BY toYYYYMM(created_at)
+				"#
+			)],
+			Driver::PostgreSQL => vec![
+				format!(
+					r#"
+						CREATE TABLE IF NOT EXISTS {TABLE}
+						(
+							module_id INTEGER NOT NULL,
+							network_id BIGINT NOT NULL,
+							block_height BIGINT NOT NULL,
+							tx_hash TEXT NOT NULL,
+							address TEXT NOT NULL,
+							asset_address TEXT NOT NULL,
+							amount_in NUMERIC(78) NOT NULL,
+							amount_out NUMERIC(78) NOT NULL,
+							created_at BIGINT NOT NULL
+						)
+					"#
+				),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_address ON {TABLE} (address, network_id)"
+				),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_block_height ON {TABLE} (network_id, \
+					 block_height)"
+				),
+			],
+			Driver::SQLite => vec![
+				format!(
+					r#"
+						CREATE TABLE IF NOT EXISTS {TABLE}
+						(
+							module_id INTEGER NOT NULL,
+							network_id INTEGER NOT NULL,
+							block_height INTEGER NOT NULL,
+							tx_hash TEXT NOT NULL,
+							address TEXT NOT NULL,
+							asset_address TEXT NOT NULL,
+							amount_in TEXT NOT NULL,
+							amount_out TEXT NOT NULL,
+							created_at INTEGER NOT NULL
+						)
+					"#
+				),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_address ON {TABLE} (address, network_id)"
+				),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_block_height ON {TABLE} (network_id, \
+					 block_height)"
+				),
+			],
+		}
+	}
+
+	fn down(&self, _driver: &Driver) -> Vec<String> {
+		vec![format!("DROP TABLE IF EXISTS {TABLE}")]
+	}
+}
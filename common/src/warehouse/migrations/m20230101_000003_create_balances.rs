@@ -0,0 +1,58 @@
+use crate::{
+	models::{amount, balance::TABLE},
+	warehouse::{migrations::MigrationTrait, Driver},
+};
+
+pub struct Migration;
+
+impl MigrationTrait for Migration {
+	fn name(&self) -> &str {
+		module_path!().rsplit("::").next().unwrap_or_default()
+	}
+
+	fn up(&self, driver: &Driver) -> Vec<String> {
+		match driver {
+			Driver::Clickhouse => vec![format!(
+				r#"
+					CREATE MATERIALIZED VIEW IF NOT EXISTS {TABLE}
+					ENGINE = SummingMergeTree
+					PARTITION BY network_id
+					ORDER BY (network_id, address, asset_address)
+					POPULATE AS
+					SELECT
+						network_id,
+						address,
+						asset_address,
+						(amount_in - amount_out) as balance
+					FROM {}
+					GROUP BY (network_id, address, asset_address, amount_in, amount_out)
+				"#,
+				amount::TABLE
+			)],
+			// computed on read instead of being materialized, so orphaned amounts don't have to be
+			// offset
+			Driver::PostgreSQL => vec![format!(
+				r#"
+					CREATE OR REPLACE VIEW {TABLE} AS
+					SELECT
+						network_id,
+						address,
+						asset_address,
+						(amount_in - amount_out) AS balance
+					FROM {}
+				"#,
+				amount::TABLE
+			)],
+			// amounts are text here, so balances are summed up by the driver instead
+			Driver::SQLite => vec![],
+		}
+	}
+
+	fn down(&self, driver: &Driver) -> Vec<String> {
+		match driver {
+			Driver::Clickhouse => vec![format!("DROP TABLE IF EXISTS {TABLE}")],
+			Driver::PostgreSQL => vec![format!("DROP VIEW IF EXISTS {TABLE}")],
+			Driver::SQLite => vec![],
+		}
+	}
+}
@@ -0,0 +1,99 @@
+use crate::{
+	models::downstream_link::TABLE,
+	warehouse::{migrations::MigrationTrait, Driver},
+};
+
+pub struct Migration;
+
+impl MigrationTrait for Migration {
+	fn name(&self) -> &str {
+		module_path!().rsplit("::").next().unwrap_or_default()
+	}
+
+	fn up(&self, driver: &Driver) -> Vec<String> {
+		match driver {
+			Driver::Clickhouse => vec![format!(
+				r#"
+					CREATE TABLE IF NOT EXISTS {TABLE}
+					(
+						network_id UInt64,
+						block_height UInt64,
+						from_address String,
+						to_address String,
+						transfer_uuids Array(UUID),
+						created_at DateTime
+					)
+					ENGINE = ReplacingMergeTree
+					ORDER BY (
+						network_id,
+						block_height,
+						from_address,
+						to_address,
+						transfer_uuids
+					)
+					PARTITION BY toYYYYMM(created_at)
+				"#
+			)],
+			Driver::PostgreSQL => vec![
+				format!(
+					r#"
+						CREATE TABLE IF NOT EXISTS {TABLE}
+						(
+							network_id BIGINT NOT NULL,
+							block_height BIGINT NOT NULL,
+							from_address TEXT NOT NULL,
+							to_address TEXT NOT NULL,
+							transfer_uuids UUID[] NOT NULL,
+							created_at BIGINT NOT NULL,
+							UNIQUE (
+								network_id,
+								block_height,
+								from_address,
+								to_address,
+								transfer_uuids
+							)
+						)
+					"#
+				),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_from_address ON {TABLE} (from_address)"
+				),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_to_address ON {TABLE} (network_id, to_address)"
+				),
+			],
+			Driver::SQLite => vec![
+				format!(
+					r#"
+						CREATE TABLE IF NOT EXISTS {TABLE}
+						(
+							network_id INTEGER NOT NULL,
+							block_height INTEGER NOT NULL,
+							from_address TEXT NOT NULL,
+							to_address TEXT NOT NULL,
+							transfer_uuids TEXT NOT NULL,
+							created_at INTEGER NOT NULL,
+							UNIQUE (
+								network_id,
+								block_height,
+								from_address,
+								to_address,
+								transfer_uuids
+							)
+						)
+					"#
+				),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_from_address ON {TABLE} (from_address)"
+				),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_to_address ON {TABLE} (network_id, to_address)"
+				),
+			],
+		}
+	}
+
+	fn down(&self, _driver: &Driver) -> Vec<String> {
+		vec![format!("DROP TABLE IF EXISTS {TABLE}")]
+	}
+}
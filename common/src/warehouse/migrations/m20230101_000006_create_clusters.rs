@@ -0,0 +1,74 @@
+use crate::{
+	models::cluster::TABLE,
+	warehouse::{migrations::MigrationTrait, Driver},
+};
+
+pub struct Migration;
+
+impl MigrationTrait for Migration {
+	fn name(&self) -> &str {
+		module_path!().rsplit("::").next().unwrap_or_default()
+	}
+
+	fn up(&self, driver: &Driver) -> Vec<String> {
+		match driver {
+			Driver::Clickhouse => vec![format!(
+				r#"
+					CREATE TABLE IF NOT EXISTS {TABLE}
+					(
+						network_id UInt64,
+						address String,
+						cluster_id String,
+						block_height UInt64
+					)
+					ENGINE = ReplacingMergeTree(block_height)
+					ORDER BY (
+						network_id,
+						address
+					)
+					PARTITION BY network_id
+				"#
+			)],
+			Driver::PostgreSQL => vec![
+				format!(
+					r#"
+						CREATE TABLE IF NOT EXISTS {TABLE}
+						(
+							network_id BIGINT NOT NULL,
+							address TEXT NOT NULL,
+							cluster_id TEXT NOT NULL,
+							block_height BIGINT NOT NULL,
+							PRIMARY KEY (network_id, address)
+						)
+					"#
+				),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_cluster_id ON {TABLE} (network_id, cluster_id)"
+				),
+				format!("CREATE INDEX IF NOT EXISTS {TABLE}_address ON {TABLE} (address)"),
+			],
+			Driver::SQLite => vec![
+				format!(
+					r#"
+						CREATE TABLE IF NOT EXISTS {TABLE}
+						(
+							network_id INTEGER NOT NULL,
+							address TEXT NOT NULL,
+							cluster_id TEXT NOT NULL,
+							block_height INTEGER NOT NULL,
+							PRIMARY KEY (network_id, address)
+						)
+					"#
+				),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_cluster_id ON {TABLE} (network_id, cluster_id)"
+				),
+				format!("CREATE INDEX IF NOT EXISTS {TABLE}_address ON {TABLE} (address)"),
+			],
+		}
+	}
+
+	fn down(&self, _driver: &Driver) -> Vec<String> {
+		vec![format!("DROP TABLE IF EXISTS {TABLE}")]
+	}
+}
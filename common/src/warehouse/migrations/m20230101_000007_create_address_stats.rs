@@ -0,0 +1,62 @@
+use crate::{
+	models::transfer,
+	warehouse::{migrations::MigrationTrait, Driver},
+};
+
+pub struct Migration;
+
+static TABLE: &str = "experimental_address_stats";
+
+// only materialized by clickhouse; nothing reads from it yet
+impl MigrationTrait for Migration {
+	fn name(&self) -> &str {
+		module_path!().rsplit("::").next().unwrap_or_default()
+	}
+
+	fn up(&self, driver: &Driver) -> Vec<String> {
+		match driver {
+			Driver::Clickhouse => vec![format!(
+				r#"
+					CREATE MATERIALIZED VIEW IF NOT EXISTS {TABLE}
+					ENGINE = SummingMergeTree
+					PARTITION BY network_id
+					ORDER BY (address, network_id)
+					POPULATE AS
+					SELECT
+						a.address,
+						a.network_id,
+						a.in,
+						b.out
+					FROM
+					(
+						SELECT
+							to_address AS address,
+							network_id,
+							COUNT(from_address) AS in
+						FROM {}
+						GROUP BY (network_id, to_address)
+					) AS a
+					LEFT JOIN
+					(
+						SELECT
+							from_address AS address,
+							network_id,
+							COUNT(to_address) AS out
+						FROM {}
+						GROUP BY (network_id, from_address)
+					) AS b ON (a.address = b.address) AND (a.network_id = b.network_id)
+				"#,
+				transfer::TABLE,
+				transfer::TABLE
+			)],
+			Driver::PostgreSQL | Driver::SQLite => vec![],
+		}
+	}
+
+	fn down(&self, driver: &Driver) -> Vec<String> {
+		match driver {
+			Driver::Clickhouse => vec![format!("DROP TABLE IF EXISTS {TABLE}")],
+			Driver::PostgreSQL | Driver::SQLite => vec![],
+		}
+	}
+}
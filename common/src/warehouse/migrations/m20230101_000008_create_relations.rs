@@ -0,0 +1,103 @@
+use crate::{
+	models::relation::TABLE,
+	warehouse::{migrations::MigrationTrait, Driver},
+};
+
+pub struct Migration;
+
+impl MigrationTrait for Migration {
+	fn name(&self) -> &str {
+		module_path!().rsplit("::").next().unwrap_or_default()
+	}
+
+	fn up(&self, driver: &Driver) -> Vec<String> {
+		match driver {
+			Driver::Clickhouse => vec![format!(
+				r#"
+					CREATE TABLE IF NOT EXISTS {TABLE}
+					(
+						uuid UUID,
+						module_id UInt16,
+						network_id UInt64,
+						block_height UInt64,
+						tx_hash String,
+						from_address String,
+						to_address String,
+						reason UInt16,
+						created_at DateTime
+					)
+					ENGINE = ReplacingMergeTree
+					ORDER BY (
+						module_id,
+						network_id,
+						from_address,
+						to_address
+					)
+					PARTITION BY toYYYYMM(created_at)
+				"#
+			)],
+			Driver::PostgreSQL => vec![
+				format!(
+					r#"
+						CREATE TABLE IF NOT EXISTS {TABLE}
+						(
+							uuid UUID NOT NULL,
+							module_id INTEGER NOT NULL,
+							network_id BIGINT NOT NULL,
+							block_height BIGINT NOT NULL,
+							tx_hash TEXT NOT NULL,
+							from_address TEXT NOT NULL,
+							to_address TEXT NOT NULL,
+							reason INTEGER NOT NULL,
+							created_at BIGINT NOT NULL,
+							UNIQUE (
+								module_id,
+								network_id,
+								from_address,
+								to_address
+							)
+						)
+					"#
+				),
+				format!("CREATE INDEX IF NOT EXISTS {TABLE}_to_address ON {TABLE} (to_address)"),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_block_height ON {TABLE} (network_id, \
+					 block_height)"
+				),
+			],
+			Driver::SQLite => vec![
+				format!(
+					r#"
+						CREATE TABLE IF NOT EXISTS {TABLE}
+						(
+							uuid TEXT NOT NULL,
+							module_id INTEGER NOT NULL,
+							network_id INTEGER NOT NULL,
+							block_height INTEGER NOT NULL,
+							tx_hash TEXT NOT NULL,
+							from_address TEXT NOT NULL,
+							to_address TEXT NOT NULL,
+							reason INTEGER NOT NULL,
+							created_at INTEGER NOT NULL,
+							UNIQUE (
+								module_id,
+								network_id,
+								from_address,
+								to_address
+							)
+						)
+					"#
+				),
+				format!("CREATE INDEX IF NOT EXISTS {TABLE}_to_address ON {TABLE} (to_address)"),
+				format!(
+					"CREATE INDEX IF NOT EXISTS {TABLE}_block_height ON {TABLE} (network_id, \
+					 block_height)"
+				),
+			],
+		}
+	}
+
+	fn down(&self, _driver: &Driver) -> Vec<String> {
+		vec![format!("DROP TABLE IF EXISTS {TABLE}")]
+	}
+}
@@ -0,0 +1,83 @@
+use crate::{
+	models::reconciliation::TABLE,
+	warehouse::{migrations::MigrationTrait, Driver},
+};
+
+pub struct Migration;
+
+// clickhouse expires old rows with a ttl, the sql drivers prune them on insert
+impl MigrationTrait for Migration {
+	fn name(&self) -> &str {
+		module_path!().rsplit("::").next().unwrap_or_default()
+	}
+
+	fn up(&self, driver: &Driver) -> Vec<String> {
+		match driver {
+			Driver::Clickhouse => vec![format!(
+				r#"
+					CREATE TABLE IF NOT EXISTS {TABLE}
+					(
+						network_id UInt64,
+						module_id UInt16,
+						block_height UInt64,
+						address String,
+						asset_address String,
+						indexed_balance UInt256,
+						live_balance UInt256,
+						created_at DateTime
+					)
+					ENGINE = ReplacingMergeTree
+					ORDER BY (
+						network_id,
+						module_id,
+						address,
+						asset_address,
+						block_height
+					)
+					PARTITION BY toYYYYMM(created_at)
+					TTL created_at + INTERVAL 30 DAY
+				"#
+			)],
+			Driver::PostgreSQL => vec![
+				format!(
+					r#"
+						CREATE TABLE IF NOT EXISTS {TABLE}
+						(
+							network_id BIGINT NOT NULL,
+							module_id INTEGER NOT NULL,
+							block_height BIGINT NOT NULL,
+							address TEXT NOT NULL,
+							asset_address TEXT NOT NULL,
+							indexed_balance NUMERIC(78) NOT NULL,
+							live_balance NUMERIC(78) NOT NULL,
+							created_at BIGINT NOT NULL
+						)
+					"#
+				),
+				format!("CREATE INDEX IF NOT EXISTS {TABLE}_created_at ON {TABLE} (created_at)"),
+			],
+			Driver::SQLite => vec![
+				format!(
+					r#"
+						CREATE TABLE IF NOT EXISTS {TABLE}
+						(
+							network_id INTEGER NOT NULL,
+							module_id INTEGER NOT NULL,
+							block_height INTEGER NOT NULL,
+							address TEXT NOT NULL,
+							asset_address TEXT NOT NULL,
+							indexed_balance TEXT NOT NULL,
+							live_balance TEXT NOT NULL,
+							created_at INTEGER NOT NULL
+						)
+					"#
+				),
+				format!("CREATE INDEX IF NOT EXISTS {TABLE}_created_at ON {TABLE} (created_at)"),
+			],
+		}
+	}
+
+	fn down(&self, _driver: &Driver) -> Vec<String> {
+		vec![format!("DROP TABLE IF EXISTS {TABLE}")]
+	}
+}
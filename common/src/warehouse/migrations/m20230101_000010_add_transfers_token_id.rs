@@ -0,0 +1,65 @@
+use crate::{
+	models::transfer::TABLE,
+	warehouse::{migrations::MigrationTrait, Driver},
+};
+
+pub struct Migration;
+
+// clickhouse transfers tables created before nft transfers existed (and adopted as-is by the
+// first migration) have no `token_id`. a sorting key can only be extended by columns added in
+// the same statement, and re-stating an unchanged key is a no-op, so this is safe either way.
+// sql drivers always had the column
+impl MigrationTrait for Migration {
+	fn name(&self) -> &str {
+		module_path!().rsplit("::").next().unwrap_or_default()
+	}
+
+	fn up(&self, driver: &Driver) -> Vec<String> {
+		match driver {
+			Driver::Clickhouse => vec![format!(
+				r#"
+					ALTER TABLE {TABLE}
+					ADD COLUMN IF NOT EXISTS token_id String AFTER asset_address,
+					MODIFY ORDER BY (
+						module_id,
+						network_id,
+						block_height,
+						tx_hash,
+						from_address,
+						to_address,
+						asset_address,
+						relative_amount,
+						batch_amount,
+						token_id
+					)
+				"#
+			)],
+			Driver::PostgreSQL | Driver::SQLite => vec![],
+		}
+	}
+
+	fn down(&self, driver: &Driver) -> Vec<String> {
+		match driver {
+			Driver::Clickhouse => vec![
+				format!(
+					r#"
+						ALTER TABLE {TABLE}
+						MODIFY ORDER BY (
+							module_id,
+							network_id,
+							block_height,
+							tx_hash,
+							from_address,
+							to_address,
+							asset_address,
+							relative_amount,
+							batch_amount
+						)
+					"#
+				),
+				format!("ALTER TABLE {TABLE} DROP COLUMN IF EXISTS token_id"),
+			],
+			Driver::PostgreSQL | Driver::SQLite => vec![],
+		}
+	}
+}
@@ -0,0 +1,64 @@
+use crate::{
+	models::amount::TABLE,
+	warehouse::{migrations::MigrationTrait, Driver},
+};
+
+pub struct Migration;
+
+static COLUMNS: &[&str] = &[
+	"module_id",
+	"network_id",
+	"block_height",
+	"tx_hash",
+	"address",
+	"asset_address",
+	"amount_in",
+	"amount_out",
+];
+
+// re-indexed blocks used to insert their amounts again (counting them twice in balances), so
+// duplicates are dropped before the key goes in. clickhouse is taken care of by the next
+// migration
+impl MigrationTrait for Migration {
+	fn name(&self) -> &str {
+		module_path!().rsplit("::").next().unwrap_or_default()
+	}
+
+	fn up(&self, driver: &Driver) -> Vec<String> {
+		let columns = COLUMNS.join(", ");
+
+		match driver {
+			Driver::Clickhouse => vec![],
+			Driver::PostgreSQL => vec![
+				format!(
+					r#"
+						DELETE FROM {TABLE} a
+						USING {TABLE} b
+						WHERE a.ctid > b.ctid AND ({}) = ({})
+					"#,
+					COLUMNS.iter().map(|c| format!("a.{c}")).collect::<Vec<String>>().join(", "),
+					COLUMNS.iter().map(|c| format!("b.{c}")).collect::<Vec<String>>().join(", "),
+				),
+				format!("CREATE UNIQUE INDEX IF NOT EXISTS {TABLE}_unique ON {TABLE} ({columns})"),
+			],
+			Driver::SQLite => vec![
+				format!(
+					r#"
+						DELETE FROM {TABLE}
+						WHERE rowid NOT IN (SELECT MIN(rowid) FROM {TABLE} GROUP BY {columns})
+					"#
+				),
+				format!("CREATE UNIQUE INDEX IF NOT EXISTS {TABLE}_unique ON {TABLE} ({columns})"),
+			],
+		}
+	}
+
+	fn down(&self, driver: &Driver) -> Vec<String> {
+		match driver {
+			Driver::Clickhouse => vec![],
+			Driver::PostgreSQL | Driver::SQLite => {
+				vec![format!("DROP INDEX IF EXISTS {TABLE}_unique")]
+			}
+		}
+	}
+}
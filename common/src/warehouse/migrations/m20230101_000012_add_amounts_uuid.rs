@@ -0,0 +1,103 @@
+use crate::{
+	models::{amount::TABLE, balance},
+	warehouse::{
+		migrations::{
+			m20230101_000003_create_balances, m20230101_000011_add_amounts_unique_key,
+			MigrationTrait,
+		},
+		Driver,
+	},
+};
+
+pub struct Migration;
+
+// amounts are told apart by a deterministic uuid, so writing them again is a no-op while equal
+// amounts within one tx are still kept apart (the previous key merged them). rows stored before
+// this have no uuid, they're already unique by the previous key.
+//
+// clickhouse replaces rows on the new sorting key, and `balances` turns into a plain view over
+// the merged amounts: a materialized view counts every insert, including the duplicates that
+// were never cleaned out of it
+impl MigrationTrait for Migration {
+	fn name(&self) -> &str {
+		module_path!().rsplit("::").next().unwrap_or_default()
+	}
+
+	fn up(&self, driver: &Driver) -> Vec<String> {
+		match driver {
+			Driver::Clickhouse => vec![
+				format!(
+					r#"
+						ALTER TABLE {TABLE}
+						ADD COLUMN IF NOT EXISTS uuid UUID FIRST,
+						MODIFY ORDER BY (
+							network_id,
+							block_height,
+							tx_hash,
+							address,
+							asset_address,
+							uuid
+						)
+					"#
+				),
+				format!("OPTIMIZE TABLE {TABLE} FINAL"),
+				format!("DROP TABLE IF EXISTS {}", balance::TABLE),
+				format!(
+					r#"
+						CREATE VIEW IF NOT EXISTS {}
+						AS SELECT
+							network_id,
+							address,
+							asset_address,
+							(amount_in - amount_out) as balance
+						FROM {TABLE} FINAL
+					"#,
+					balance::TABLE
+				),
+			],
+			Driver::PostgreSQL => vec![
+				format!("ALTER TABLE {TABLE} ADD COLUMN IF NOT EXISTS uuid UUID"),
+				format!("DROP INDEX IF EXISTS {TABLE}_unique"),
+				format!("CREATE UNIQUE INDEX IF NOT EXISTS {TABLE}_uuid ON {TABLE} (uuid)"),
+			],
+			Driver::SQLite => vec![
+				format!("ALTER TABLE {TABLE} ADD COLUMN uuid TEXT"),
+				format!("DROP INDEX IF EXISTS {TABLE}_unique"),
+				format!("CREATE UNIQUE INDEX IF NOT EXISTS {TABLE}_uuid ON {TABLE} (uuid)"),
+			],
+		}
+	}
+
+	fn down(&self, driver: &Driver) -> Vec<String> {
+		match driver {
+			Driver::Clickhouse => [
+				vec![
+					format!("DROP VIEW IF EXISTS {}", balance::TABLE),
+					format!(
+						r#"
+							ALTER TABLE {TABLE}
+							MODIFY ORDER BY (
+								network_id,
+								block_height,
+								tx_hash,
+								address,
+								asset_address
+							)
+						"#
+					),
+					format!("ALTER TABLE {TABLE} DROP COLUMN IF EXISTS uuid"),
+				],
+				m20230101_000003_create_balances::Migration.up(driver),
+			]
+			.concat(),
+			Driver::PostgreSQL | Driver::SQLite => [
+				vec![
+					format!("DROP INDEX IF EXISTS {TABLE}_uuid"),
+					format!("ALTER TABLE {TABLE} DROP COLUMN uuid"),
+				],
+				m20230101_000011_add_amounts_unique_key::Migration.up(driver),
+			]
+			.concat(),
+		}
+	}
+}
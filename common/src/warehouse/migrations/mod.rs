@@ -0,0 +1,74 @@
+use clap::ValueEnum;
+
+use crate::warehouse::Driver;
+
+mod m20230101_000001_create_transfers;
+mod m20230101_000002_create_amounts;
+mod m20230101_000003_create_balances;
+mod m20230101_000004_create_links;
+mod m20230101_000005_create_downstream_links;
+mod m20230101_000006_create_clusters;
+mod m20230101_000007_create_address_stats;
+mod m20230101_000008_create_relations;
+mod m20230101_000009_create_reconciliations;
+mod m20230101_000010_add_transfers_token_id;
+mod m20230101_000011_add_amounts_unique_key;
+mod m20230101_000012_add_amounts_uuid;
+
+pub static TABLE: &str = "schema_migrations";
+
+// what to do with warehouse migrations on startup
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum Mode {
+	/// Apply pending migrations, then start as usual
+	#[default]
+	Run,
+	/// Print pending migrations without applying them, then exit
+	DryRun,
+	/// Revert the most recently applied migration, then exit
+	Rollback,
+}
+
+// a single schema change; statements are plain sql in the dialect of `driver` (and may be
+// empty when a driver has nothing to do for it)
+pub trait MigrationTrait: Send + Sync {
+	fn name(&self) -> &str;
+	fn up(&self, driver: &Driver) -> Vec<String>;
+	fn down(&self, driver: &Driver) -> Vec<String>;
+}
+
+pub struct Migrator;
+
+impl Migrator {
+	// applied in this order & reverted in reverse, so only ever append to this list
+	pub fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+		vec![
+			Box::new(m20230101_000001_create_transfers::Migration),
+			Box::new(m20230101_000002_create_amounts::Migration),
+			Box::new(m20230101_000003_create_balances::Migration),
+			Box::new(m20230101_000004_create_links::Migration),
+			Box::new(m20230101_000005_create_downstream_links::Migration),
+			Box::new(m20230101_000006_create_clusters::Migration),
+			Box::new(m20230101_000007_create_address_stats::Migration),
+			Box::new(m20230101_000008_create_relations::Migration),
+			Box::new(m20230101_000009_create_reconciliations::Migration),
+			Box::new(m20230101_000010_add_transfers_token_id::Migration),
+			Box::new(m20230101_000011_add_amounts_unique_key::Migration),
+			Box::new(m20230101_000012_add_amounts_uuid::Migration),
+		]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_migrations_are_ordered() {
+		let names =
+			Migrator::migrations().iter().map(|m| m.name().to_string()).collect::<Vec<String>>();
+
+		assert_eq!(names[0], "m20230101_000001_create_transfers");
+		assert!(names.windows(2).all(|w| w[0] < w[1]));
+	}
+}
@@ -1,247 +1,284 @@
-use clickhouse::Client as ClickhouseClient;
-use eyre::{Result, WrapErr};
+use async_trait::async_trait;
+use eyre::Result;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{
+	collections::{HashMap, HashSet},
+	str::FromStr,
+	sync::Arc,
+};
+use uuid::Uuid;
 
-use crate::{utils, Settings};
+use crate::{
+	chain::U256,
+	models::{
+		Amount, Balance, Cluster, DownstreamLink, Link, LinkFilters, PrimaryId, Reconciliation,
+		ReconciliationStats, Relation, RelationReason, Transfer,
+	},
+	warehouse::{
+		clickhouse::Clickhouse, migrations::Migrator, postgres::PostgreSQL, sqlite::SQLite,
+	},
+	BlockHeight, Settings,
+};
+
+pub use migrations::Mode as MigrationMode;
+
+mod clickhouse;
+mod migrations;
+mod postgres;
+mod sqlite;
 
 #[derive(Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
 pub enum Driver {
 	#[default]
 	#[serde(rename = "clickhouse")]
 	Clickhouse,
+	#[serde(rename = "postgres")]
+	PostgreSQL,
+	#[serde(rename = "sqlite")]
+	SQLite,
 }
 
-pub struct Clickhouse {
-	client: ClickhouseClient,
+impl FromStr for Driver {
+	type Err = ();
+
+	fn from_str(d: &str) -> Result<Self, Self::Err> {
+		match d {
+			"http" | "https" => Ok(Self::Clickhouse),
+			"postgres" | "postgresql" => Ok(Self::PostgreSQL),
+			"file" => Ok(Self::SQLite),
+			_ => Err(()),
+		}
+	}
+}
+
+// models handed to drivers for inserting; consumed lazily, so drivers can write them out as they
+// go instead of collecting them all first
+pub type Rows<T> = Box<dyn Iterator<Item = T> + Send>;
+
+// every query models run against the warehouse; drivers translate them into their own dialect.
+// `addresses`, `uuids` and `cluster_ids` arguments are expected to be deduped already
+#[async_trait]
+pub trait WarehouseTrait: Send + Sync {
+	// schema migrations (see `Migrator`); `version` is the migration's name
+	async fn create_migrations_table(&self) -> Result<()>;
+	// none if the migrations table hasn't been created yet (checking doesn't create it)
+	async fn get_applied_migrations(&self) -> Result<Vec<String>>;
+	async fn apply_migration(&self, version: &str, statements: Vec<String>) -> Result<()>;
+	async fn revert_migration(&self, version: &str, statements: Vec<String>) -> Result<()>;
+
+	// shared by every table that has these columns
+	async fn delete_all_by_network_ids(
+		&self,
+		table: &str,
+		network_ids: Vec<PrimaryId>,
+	) -> Result<()>;
+	async fn delete_all_after_block_height(
+		&self,
+		table: &str,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+	) -> Result<()>;
+
+	// transfers
+	async fn create_transfers(&self, models: Rows<Transfer>) -> Result<()>;
+	async fn get_first_transfer_by_source(
+		&self,
+		network_id: PrimaryId,
+		address: &str,
+	) -> Result<Option<Transfer>>;
+	async fn get_first_transfer_by_target(
+		&self,
+		network_id: PrimaryId,
+		address: &str,
+	) -> Result<Option<Transfer>>;
+	async fn get_transfers_by_targets(
+		&self,
+		network_id: PrimaryId,
+		addresses: Vec<String>,
+		block_range: (BlockHeight, BlockHeight),
+	) -> Result<Vec<Transfer>>;
+	async fn get_transfers_by_addresses(
+		&self,
+		network_id: PrimaryId,
+		addresses: Vec<String>,
+		max_block_height: BlockHeight,
+	) -> Result<Vec<Transfer>>;
+	async fn get_transfers_by_block_range(
+		&self,
+		network_id: PrimaryId,
+		block_range: (BlockHeight, BlockHeight),
+	) -> Result<Vec<Transfer>>;
+	async fn get_transfers_by_uuids(&self, uuids: Vec<Uuid>) -> Result<Vec<Transfer>>;
+
+	// amounts & balances
+	async fn create_amounts(&self, models: Rows<Amount>) -> Result<()>;
+	async fn get_amount_network_ids_by_addresses(
+		&self,
+		addresses: Vec<String>,
+	) -> Result<Vec<PrimaryId>>;
+	async fn get_amounts_received_by_addresses(
+		&self,
+		addresses: Vec<String>,
+		range: (u32, u32),
+	) -> Result<HashMap<(PrimaryId, String), U256>>;
+	async fn get_balances_by_addresses(&self, addresses: Vec<String>) -> Result<Vec<Balance>>;
+	async fn get_balances_random_sample(
+		&self,
+		network_id: PrimaryId,
+		limit: u64,
+	) -> Result<Vec<Balance>>;
+
+	// links
+	async fn create_links(&self, models: Rows<Link>) -> Result<()>;
+	async fn get_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+		filters: &LinkFilters,
+	) -> Result<Vec<Link>>;
+	async fn get_distinct_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+		filters: &LinkFilters,
+	) -> Result<Vec<Link>>;
+	async fn get_links_to_seed_blocks(
+		&self,
+		network_id: PrimaryId,
+		block_range: (BlockHeight, BlockHeight),
+	) -> Result<Vec<Link>>;
+	async fn delete_links_by_sources(
+		&self,
+		sources: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()>;
+	async fn delete_links_by_newly_added_addresses(
+		&self,
+		targets: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()>;
+
+	// downstream links
+	async fn create_downstream_links(&self, models: Rows<DownstreamLink>) -> Result<()>;
+	async fn get_downstream_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+	) -> Result<Vec<DownstreamLink>>;
+	async fn get_distinct_downstream_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+	) -> Result<Vec<DownstreamLink>>;
+	async fn delete_downstream_links_by_targets(
+		&self,
+		targets: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()>;
+	async fn delete_downstream_links_by_newly_added_addresses(
+		&self,
+		targets: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()>;
+
+	// relations
+	async fn create_relations(&self, models: Rows<Relation>) -> Result<()>;
+	async fn get_relations_by_block_range(
+		&self,
+		network_id: PrimaryId,
+		reason: RelationReason,
+		block_range: (BlockHeight, BlockHeight),
+	) -> Result<Vec<Relation>>;
+	async fn get_relations_by_addresses(
+		&self,
+		network_id: PrimaryId,
+		reason: RelationReason,
+		addresses: Vec<String>,
+		max_block_height: BlockHeight,
+	) -> Result<Vec<Relation>>;
+	async fn get_relations_by_address_paginated(
+		&self,
+		address: &str,
+		offset: u64,
+		limit: u64,
+	) -> Result<Vec<Relation>>;
+
+	// clusters
+	async fn create_clusters(&self, models: Rows<Cluster>) -> Result<()>;
+	async fn get_clusters_by_addresses(&self, addresses: Vec<String>) -> Result<Vec<Cluster>>;
+	async fn get_clusters_by_cluster_ids(
+		&self,
+		network_id: PrimaryId,
+		cluster_ids: Vec<String>,
+	) -> Result<Vec<Cluster>>;
+	async fn get_clusters_after_block_height(
+		&self,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+	) -> Result<Vec<Cluster>>;
+	async fn get_clusters_by_member(
+		&self,
+		network_id: PrimaryId,
+		address: &str,
+	) -> Result<Vec<Cluster>>;
+
+	// reconciliations
+	async fn create_reconciliations(&self, models: Rows<Reconciliation>) -> Result<()>;
+	async fn get_reconciliation_stats(&self, since: u32) -> Result<Vec<ReconciliationStats>>;
 }
 
 pub struct Warehouse {
-	url_without_database: String,
-	db_name: String,
-	clickhouse: Clickhouse,
+	settings: Arc<Settings>,
+	warehouse: Box<dyn WarehouseTrait>,
 }
 
 impl Warehouse {
 	pub async fn new(settings: Arc<Settings>) -> Result<Self> {
-		let (url_without_database, db_name) = utils::without_pathname(&settings.warehouse);
-
-		// create db if doesn't exist + check that connection is good
-		ClickhouseClient::default()
-			.with_url(url_without_database.clone())
-			.query(&format!("CREATE DATABASE IF NOT EXISTS {db_name};"))
-			.execute()
-			.await
-			.wrap_err(url_without_database.clone())?;
-
 		Ok(Self {
-			url_without_database: url_without_database.clone(),
-			db_name: db_name.clone(),
-			clickhouse: Clickhouse {
-				client: ClickhouseClient::default()
-					.with_url(url_without_database)
-					.with_database(db_name),
+			settings: settings.clone(),
+			warehouse: match settings.warehouse_driver {
+				Driver::Clickhouse => Box::new(Clickhouse::new(&settings.warehouse).await?),
+				Driver::PostgreSQL => Box::new(PostgreSQL::new(&settings.warehouse).await?),
+				Driver::SQLite => Box::new(SQLite::new(&settings.warehouse).await?),
 			},
 		})
 	}
 
 	pub async fn run_migrations(&self) -> Result<()> {
-		self.clickhouse
-			.client
-			.query(&format!(
-				r#"
-					CREATE TABLE IF NOT EXISTS {}.transfers
-					(
-						uuid UUID,
-						module_id UInt16,
-						network_id UInt64,
-						block_height UInt64,
-						tx_hash String,
-						from_address String,
-						to_address String,
-						asset_address String,
-						relative_amount UInt256,
-						batch_amount UInt256,
-						created_at DateTime
-					)
-					ENGINE = ReplacingMergeTree
-					ORDER BY (
-						module_id,
-						network_id,
-						block_height,
-						tx_hash,
-						from_address,
-						to_address,
-						asset_address,
-						relative_amount,
-						batch_amount
-					)
-					PARTITION BY toYYYYMM(created_at);
-				"#,
-				self.db_name
-			))
-			.execute()
-			.await
-			.wrap_err(self.url_without_database.clone())?;
-
-		self.clickhouse
-			.client
-			.query(&format!(
-				r#"
-					CREATE TABLE IF NOT EXISTS {}.amounts
-					(
-						module_id UInt16,
-						network_id UInt64,
-						block_height UInt64,
-						tx_hash String,
-						address String,
-						asset_address String,
-						amount_in UInt256,
-						amount_out UInt256,
-						created_at DateTime
-					)
-					ENGINE = ReplacingMergeTree
-					ORDER BY (
-						network_id,
-						block_height,
-						tx_hash,
-						address,
-						asset_address
-					)
-					PARTITION BY toYYYYMM(created_at);
-				"#,
-				self.db_name
-			))
-			.execute()
-			.await
-			.wrap_err(self.url_without_database.clone())?;
-
-		self.clickhouse
-			.client
-			.query(&format!(
-				r#"
-					CREATE MATERIALIZED VIEW IF NOT EXISTS {}.balances
-					ENGINE = SummingMergeTree
-					PARTITION BY network_id
-					ORDER BY (network_id, address, asset_address)
-					POPULATE AS
-					SELECT
-						network_id,
-					    address,
-					    asset_address,
-					    (amount_in - amount_out) as balance
-					FROM {}.amounts
-					GROUP BY (network_id, address, asset_address, amount_in, amount_out)
-				"#,
-				self.db_name, self.db_name,
-			))
-			.execute()
-			.await
-			.wrap_err(self.url_without_database.clone())?;
-
-		self.clickhouse
-			.client
-			.query(&format!(
-				r#"
-					CREATE TABLE IF NOT EXISTS {}.links
-					(
-						network_id UInt64,
-						block_height UInt64,
-						from_address String,
-						to_address String,
-						transfer_uuids Array(UUID),
-						created_at DateTime
-					)
-					ENGINE = ReplacingMergeTree
-					ORDER BY (
-						network_id,
-						block_height,
-						from_address,
-						to_address,
-						transfer_uuids
-					)
-					PARTITION BY toYYYYMM(created_at);
-				"#,
-				self.db_name
-			))
-			.execute()
-			.await
-			.wrap_err(self.url_without_database.clone())?;
-
-		self.clickhouse
-			.client
-			.query(&format!(
-				r#"
-					CREATE MATERIALIZED VIEW IF NOT EXISTS {}.experimental_address_stats
-					ENGINE = SummingMergeTree
-					PARTITION BY network_id
-					ORDER BY (address, network_id)
-					POPULATE AS
-					SELECT
-					    a.address,
-					    a.network_id,
-					    a.in,
-					    b.out
-					FROM
-					(
-					    SELECT
-					        to_address AS address,
-					        network_id,
-					        COUNT(from_address) AS in
-					    FROM {}.transfers
-					    GROUP BY (network_id, to_address)
-					) AS a
-					LEFT JOIN
-					(
-					    SELECT
-					        from_address AS address,
-					        network_id,
-					        COUNT(to_address) AS out
-					    FROM {}.transfers
-					    GROUP BY (network_id, from_address)
-					) AS b ON (a.address = b.address) AND (a.network_id = b.network_id)
-				"#,
-				self.db_name, self.db_name, self.db_name
-			))
-			.execute()
-			.await
-			.wrap_err(self.url_without_database.clone())?;
-
-		self.clickhouse
-			.client
-			.query(&format!(
-				r#"
-					CREATE TABLE IF NOT EXISTS {}.experimental_relations
-					(
-						uuid UUID,
-						module_id UInt16,
-						network_id UInt64,
-						block_height UInt64,
-						tx_hash String,
-						from_address String,
-						to_address String,
-						reason UInt16,
-						created_at DateTime
-					)
-					ENGINE = ReplacingMergeTree
-					ORDER BY (
-						module_id,
-						network_id,
-						from_address,
-						to_address
-					)
-					PARTITION BY toYYYYMM(created_at);
-				"#,
-				self.db_name
-			))
-			.execute()
-			.await
-			.wrap_err(self.url_without_database.clone())?;
+		self.warehouse.create_migrations_table().await?;
+		for (name, statements) in self.get_pending_migrations().await?.into_iter() {
+			self.warehouse.apply_migration(&name, statements).await?;
+		}
 
 		Ok(())
 	}
 
-	pub fn get(&self) -> &ClickhouseClient {
-		&self.clickhouse.client
+	// names & up-statements of migrations that haven't been applied yet, in order
+	pub async fn get_pending_migrations(&self) -> Result<Vec<(String, Vec<String>)>> {
+		let applied = self.warehouse.get_applied_migrations().await?;
+
+		Ok(Migrator::migrations()
+			.iter()
+			.filter(|m| !applied.iter().any(|name| name == m.name()))
+			.map(|m| (m.name().to_string(), m.up(&self.settings.warehouse_driver)))
+			.collect())
+	}
+
+	// reverts the most recently applied migration, returning its name
+	pub async fn rollback_migration(&self) -> Result<Option<String>> {
+		self.warehouse.create_migrations_table().await?;
+		let applied = self.warehouse.get_applied_migrations().await?;
+
+		let migration = Migrator::migrations()
+			.into_iter()
+			.rev()
+			.find(|m| applied.iter().any(|name| name == m.name()));
+
+		Ok(match migration {
+			Some(m) => {
+				self.warehouse
+					.revert_migration(m.name(), m.down(&self.settings.warehouse_driver))
+					.await?;
+
+				Some(m.name().to_string())
+			}
+			None => None,
+		})
+	}
+
+	pub fn get(&self) -> &dyn WarehouseTrait {
+		self.warehouse.as_ref()
 	}
 }
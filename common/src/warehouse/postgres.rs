@@ -0,0 +1,1321 @@
+use async_trait::async_trait;
+use eyre::{Result, WrapErr};
+use log::LevelFilter;
+use sea_orm::{
+	prelude::BigDecimal, ConnectOptions, ConnectionTrait, Database, DatabaseConnection, DbBackend,
+	QueryResult, Statement, TransactionTrait, Value,
+};
+use std::{
+	collections::{HashMap, HashSet},
+	str::FromStr,
+};
+use uuid::Uuid;
+
+use crate::{
+	chain::U256,
+	models::{
+		amount, balance, cluster, downstream_link, link, reconciliation, relation, transfer,
+		Amount, Balance, Cluster, DownstreamLink, Link, LinkFilters, LinkUuid, PrimaryId,
+		Reconciliation, ReconciliationStats, Relation, RelationReason, Transfer,
+	},
+	utils,
+	warehouse::{migrations, Rows, WarehouseTrait},
+	BlockHeight,
+};
+
+// postgres caps the number of bind parameters per statement
+const MAX_PARAMS: usize = 65_535;
+
+// how long reconciliation results are kept around for (clickhouse does this with a ttl)
+const RECONCILIATIONS_TTL: i64 = 30 * 24 * 60 * 60;
+
+static TRANSFER_COLUMNS: &str = r#"
+	uuid, module_id, network_id, block_height, tx_hash, from_address, to_address, asset_address,
+	token_id, relative_amount::text AS relative_amount, batch_amount::text AS batch_amount,
+	created_at
+"#;
+
+static LINK_COLUMNS: &[&str] =
+	&["network_id", "block_height", "from_address", "to_address", "transfer_uuids", "created_at"];
+
+pub struct PostgreSQL {
+	url_without_database: String,
+	db: DatabaseConnection,
+}
+
+impl PostgreSQL {
+	pub async fn new(url: &str) -> Result<Self> {
+		let with_options = |url: String| -> ConnectOptions {
+			let mut opt = ConnectOptions::new(url);
+			opt.sqlx_logging(false).sqlx_logging_level(LevelFilter::Warn);
+			opt
+		};
+
+		let (url_without_database, db_name) = utils::without_pathname(url);
+
+		// create db if doesn't exist + check that connection is good
+		let conn = Database::connect(with_options(url_without_database.clone()))
+			.await
+			.wrap_err(url_without_database.clone())?;
+
+		let result = conn
+			.execute(Statement::from_sql_and_values(
+				DbBackend::Postgres,
+				"SELECT datname FROM pg_catalog.pg_database WHERE datname = $1",
+				[db_name.clone().into()],
+			))
+			.await
+			.wrap_err(url_without_database.clone())?;
+
+		if result.rows_affected() == 0 {
+			conn.execute(Statement::from_string(
+				DbBackend::Postgres,
+				format!(r#"CREATE DATABASE "{db_name}";"#),
+			))
+			.await
+			.wrap_err(url_without_database.clone())?;
+		}
+
+		Ok(Self {
+			url_without_database: url_without_database.clone(),
+			db: Database::connect(with_options(url.to_string()))
+				.await
+				.wrap_err(url_without_database)?,
+		})
+	}
+
+	async fn execute(&self, sql: &str, values: Vec<Value>) -> Result<()> {
+		self.db.execute(Statement::from_sql_and_values(DbBackend::Postgres, sql, values)).await?;
+
+		Ok(())
+	}
+
+	async fn query(&self, sql: &str, values: Vec<Value>) -> Result<Vec<QueryResult>> {
+		Ok(self
+			.db
+			.query_all(Statement::from_sql_and_values(DbBackend::Postgres, sql, values))
+			.await?)
+	}
+
+	// multi-row insert, pulling rows a statement's worth at a time (so they're never all in
+	// memory at once) and staying under the bind parameter limit
+	async fn insert(
+		&self,
+		table: &str,
+		columns: &[&str],
+		mut rows: impl Iterator<Item = Result<Vec<Value>>> + Send,
+		on_conflict: &str,
+	) -> Result<()> {
+		loop {
+			let chunk =
+				rows.by_ref().take(MAX_PARAMS / columns.len()).collect::<Result<Vec<_>>>()?;
+			if chunk.is_empty() {
+				break;
+			}
+
+			let mut values = vec![];
+			let mut tuples = vec![];
+			for row in chunk {
+				let mut placeholders = vec![];
+				for value in row.into_iter() {
+					values.push(value);
+					placeholders.push(format!("${}", values.len()));
+				}
+
+				tuples.push(format!("({})", placeholders.join(", ")));
+			}
+
+			self.execute(
+				&format!(
+					"INSERT INTO {table} ({}) VALUES {} {on_conflict}",
+					columns.join(", "),
+					tuples.join(", ")
+				),
+				values,
+			)
+			.await?;
+		}
+
+		Ok(())
+	}
+
+	// `(network_id = $1 AND field = ANY($2)) OR ...`, with values appended to `values`
+	fn get_network_id_address_conditions(
+		map: HashMap<PrimaryId, HashSet<String>>,
+		field: &str,
+		values: &mut Vec<Value>,
+	) -> String {
+		map.into_iter()
+			.map(|(network_id, addresses)| {
+				values.push(network_id.into());
+				values.push(addresses.into_iter().collect::<Vec<String>>().into());
+
+				format!("(network_id = ${} AND {field} = ANY(${}))", values.len() - 1, values.len())
+			})
+			.collect::<Vec<String>>()
+			.join(" OR ")
+	}
+}
+
+#[async_trait]
+impl WarehouseTrait for PostgreSQL {
+	async fn create_migrations_table(&self) -> Result<()> {
+		self.execute(
+			&format!(
+				r#"
+					CREATE TABLE IF NOT EXISTS {}
+					(
+						version TEXT NOT NULL PRIMARY KEY,
+						applied_at BIGINT NOT NULL
+					)
+				"#,
+				migrations::TABLE
+			),
+			vec![],
+		)
+		.await
+		.wrap_err(self.url_without_database.clone())
+	}
+
+	async fn get_applied_migrations(&self) -> Result<Vec<String>> {
+		let exists = self
+			.query(
+				r#"
+					SELECT table_name
+					FROM information_schema.tables
+					WHERE table_schema = current_schema() AND table_name = $1
+				"#,
+				vec![migrations::TABLE.into()],
+			)
+			.await?;
+		if exists.is_empty() {
+			return Ok(vec![]);
+		}
+
+		self.query(&format!("SELECT version FROM {} ORDER BY version", migrations::TABLE), vec![])
+			.await?
+			.iter()
+			.map(|r| Ok(r.try_get("", "version")?))
+			.collect()
+	}
+
+	// steps and their bookkeeping run in one transaction, so a failed migration leaves no trace
+	async fn apply_migration(&self, version: &str, statements: Vec<String>) -> Result<()> {
+		let txn = self.db.begin().await?;
+		for statement in statements.into_iter() {
+			txn.execute(Statement::from_string(DbBackend::Postgres, statement))
+				.await
+				.wrap_err(version.to_string())?;
+		}
+
+		txn.execute(Statement::from_sql_and_values(
+			DbBackend::Postgres,
+			&format!("INSERT INTO {} (version, applied_at) VALUES ($1, $2)", migrations::TABLE),
+			[version.into(), utils::now().timestamp().into()],
+		))
+		.await?;
+
+		Ok(txn.commit().await?)
+	}
+
+	async fn revert_migration(&self, version: &str, statements: Vec<String>) -> Result<()> {
+		let txn = self.db.begin().await?;
+		for statement in statements.into_iter() {
+			txn.execute(Statement::from_string(DbBackend::Postgres, statement))
+				.await
+				.wrap_err(version.to_string())?;
+		}
+
+		txn.execute(Statement::from_sql_and_values(
+			DbBackend::Postgres,
+			&format!("DELETE FROM {} WHERE version = $1", migrations::TABLE),
+			[version.into()],
+		))
+		.await?;
+
+		Ok(txn.commit().await?)
+	}
+
+	async fn delete_all_by_network_ids(
+		&self,
+		table: &str,
+		network_ids: Vec<PrimaryId>,
+	) -> Result<()> {
+		// balances are a view over amounts, which get deleted on their own
+		if table == balance::TABLE {
+			return Ok(());
+		}
+
+		self.execute(
+			&format!("DELETE FROM {table} WHERE network_id = ANY($1)"),
+			vec![network_ids.into()],
+		)
+		.await
+	}
+
+	async fn delete_all_after_block_height(
+		&self,
+		table: &str,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+	) -> Result<()> {
+		self.execute(
+			&format!("DELETE FROM {table} WHERE network_id = $1 AND block_height > $2"),
+			vec![network_id.into(), (block_height as i64).into()],
+		)
+		.await
+	}
+
+	async fn create_transfers(&self, models: Rows<Transfer>) -> Result<()> {
+		let rows = models.map(|m| -> Result<Vec<Value>> {
+			Ok(vec![
+				m.uuid.into(),
+				(m.module_id as i32).into(),
+				(m.network_id as i64).into(),
+				(m.block_height as i64).into(),
+				m.tx_hash.into(),
+				m.from_address.into(),
+				m.to_address.into(),
+				m.asset_address.into(),
+				m.token_id.into(),
+				to_numeric(m.relative_amount)?,
+				to_numeric(m.batch_amount)?,
+				(m.created_at as i64).into(),
+			])
+		});
+
+		self.insert(
+			transfer::TABLE,
+			&[
+				"uuid",
+				"module_id",
+				"network_id",
+				"block_height",
+				"tx_hash",
+				"from_address",
+				"to_address",
+				"asset_address",
+				"token_id",
+				"relative_amount",
+				"batch_amount",
+				"created_at",
+			],
+			rows,
+			"ON CONFLICT DO NOTHING",
+		)
+		.await
+	}
+
+	async fn get_first_transfer_by_source(
+		&self,
+		network_id: PrimaryId,
+		address: &str,
+	) -> Result<Option<Transfer>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT {TRANSFER_COLUMNS}
+					FROM {}
+					WHERE network_id = $1 AND from_address = $2
+					ORDER BY created_at ASC
+					LIMIT 1
+				"#,
+				transfer::TABLE
+			),
+			vec![network_id.into(), address.into()],
+		)
+		.await?
+		.iter()
+		.map(get_transfer)
+		.next()
+		.transpose()
+	}
+
+	async fn get_first_transfer_by_target(
+		&self,
+		network_id: PrimaryId,
+		address: &str,
+	) -> Result<Option<Transfer>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT {TRANSFER_COLUMNS}
+					FROM {}
+					WHERE network_id = $1 AND to_address = $2
+					ORDER BY created_at ASC
+					LIMIT 1
+				"#,
+				transfer::TABLE
+			),
+			vec![network_id.into(), address.into()],
+		)
+		.await?
+		.iter()
+		.map(get_transfer)
+		.next()
+		.transpose()
+	}
+
+	async fn get_transfers_by_targets(
+		&self,
+		network_id: PrimaryId,
+		addresses: Vec<String>,
+		(block_height_min, block_height_max): (BlockHeight, BlockHeight),
+	) -> Result<Vec<Transfer>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT {TRANSFER_COLUMNS}
+					FROM {}
+					WHERE
+						network_id = $1 AND
+						to_address = ANY($2) AND
+						length(from_address) > 0 AND
+						block_height >= $3 AND
+						block_height <= $4
+					ORDER BY block_height DESC
+				"#,
+				transfer::TABLE
+			),
+			vec![
+				network_id.into(),
+				addresses.into(),
+				(block_height_min as i64).into(),
+				(block_height_max as i64).into(),
+			],
+		)
+		.await?
+		.iter()
+		.map(get_transfer)
+		.collect()
+	}
+
+	async fn get_transfers_by_addresses(
+		&self,
+		network_id: PrimaryId,
+		addresses: Vec<String>,
+		max_block_height: BlockHeight,
+	) -> Result<Vec<Transfer>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT {TRANSFER_COLUMNS}
+					FROM {}
+					WHERE
+						network_id = $1 AND
+						(from_address = ANY($2) OR to_address = ANY($2)) AND
+						block_height <= $3
+				"#,
+				transfer::TABLE
+			),
+			vec![network_id.into(), addresses.into(), (max_block_height as i64).into()],
+		)
+		.await?
+		.iter()
+		.map(get_transfer)
+		.collect()
+	}
+
+	async fn get_transfers_by_block_range(
+		&self,
+		network_id: PrimaryId,
+		(block_height_min, block_height_max): (BlockHeight, BlockHeight),
+	) -> Result<Vec<Transfer>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT {TRANSFER_COLUMNS}
+					FROM {}
+					WHERE
+						network_id = $1 AND
+						block_height >= $2 AND
+						block_height <= $3
+					ORDER BY block_height ASC
+				"#,
+				transfer::TABLE
+			),
+			vec![
+				network_id.into(),
+				(block_height_min as i64).into(),
+				(block_height_max as i64).into(),
+			],
+		)
+		.await?
+		.iter()
+		.map(get_transfer)
+		.collect()
+	}
+
+	async fn get_transfers_by_uuids(&self, uuids: Vec<Uuid>) -> Result<Vec<Transfer>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT {TRANSFER_COLUMNS}
+					FROM {}
+					WHERE uuid = ANY($1)
+				"#,
+				transfer::TABLE
+			),
+			vec![uuids.into()],
+		)
+		.await?
+		.iter()
+		.map(get_transfer)
+		.collect()
+	}
+
+	async fn create_amounts(&self, models: Rows<Amount>) -> Result<()> {
+		let rows = models.map(|m| -> Result<Vec<Value>> {
+			Ok(vec![
+				m.uuid.into(),
+				(m.module_id as i32).into(),
+				(m.network_id as i64).into(),
+				(m.block_height as i64).into(),
+				m.tx_hash.into(),
+				m.address.into(),
+				m.asset_address.into(),
+				to_numeric(m.amount_in)?,
+				to_numeric(m.amount_out)?,
+				(m.created_at as i64).into(),
+			])
+		});
+
+		self.insert(
+			amount::TABLE,
+			&[
+				"uuid",
+				"module_id",
+				"network_id",
+				"block_height",
+				"tx_hash",
+				"address",
+				"asset_address",
+				"amount_in",
+				"amount_out",
+				"created_at",
+			],
+			rows,
+			"ON CONFLICT DO NOTHING",
+		)
+		.await
+	}
+
+	async fn get_amount_network_ids_by_addresses(
+		&self,
+		addresses: Vec<String>,
+	) -> Result<Vec<PrimaryId>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT DISTINCT network_id
+					FROM {}
+					WHERE address = ANY($1)
+				"#,
+				amount::TABLE
+			),
+			vec![addresses.into()],
+		)
+		.await?
+		.iter()
+		.map(|r| Ok(r.try_get::<i64>("", "network_id")?))
+		.collect()
+	}
+
+	async fn get_amounts_received_by_addresses(
+		&self,
+		addresses: Vec<String>,
+		(since, until): (u32, u32),
+	) -> Result<HashMap<(PrimaryId, String), U256>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT network_id, asset_address, SUM(amount_in)::text AS amount_in
+					FROM {}
+					WHERE
+						address = ANY($1) AND
+						created_at >= $2 AND
+						created_at <= $3
+					GROUP BY network_id, asset_address
+				"#,
+				amount::TABLE
+			),
+			vec![addresses.into(), (since as i64).into(), (until as i64).into()],
+		)
+		.await?
+		.iter()
+		.map(|r| {
+			Ok((
+				(r.try_get::<i64>("", "network_id")?, r.try_get::<String>("", "asset_address")?),
+				get_u256(r, "amount_in")?,
+			))
+		})
+		.collect()
+	}
+
+	async fn get_balances_by_addresses(&self, addresses: Vec<String>) -> Result<Vec<Balance>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT
+						network_id,
+						address,
+						asset_address,
+						SUM(balance)::text AS balance
+					FROM {}
+					WHERE address = ANY($1)
+					GROUP BY network_id, address, asset_address
+					HAVING SUM(balance) >= 0
+				"#,
+				balance::TABLE
+			),
+			vec![addresses.into()],
+		)
+		.await?
+		.iter()
+		.map(get_balance)
+		.collect()
+	}
+
+	async fn get_balances_random_sample(
+		&self,
+		network_id: PrimaryId,
+		limit: u64,
+	) -> Result<Vec<Balance>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT
+						network_id,
+						address,
+						asset_address,
+						SUM(balance)::text AS balance
+					FROM {}
+					WHERE
+						network_id = $1 AND
+						(address, asset_address) IN (
+							SELECT address, asset_address
+							FROM {}
+							WHERE network_id = $1
+							GROUP BY address, asset_address
+							ORDER BY random()
+							LIMIT $2
+						)
+					GROUP BY network_id, address, asset_address
+					HAVING SUM(balance) >= 0
+				"#,
+				balance::TABLE,
+				balance::TABLE
+			),
+			vec![network_id.into(), (limit as i64).into()],
+		)
+		.await?
+		.iter()
+		.map(get_balance)
+		.collect()
+	}
+
+	async fn create_links(&self, models: Rows<Link>) -> Result<()> {
+		let rows = models
+			.map(|m| {
+				get_link_row(
+					m.network_id,
+					m.block_height,
+					m.from_address,
+					m.to_address,
+					m.transfer_uuids,
+					m.created_at,
+				)
+			})
+			.map(Ok);
+
+		self.insert(link::TABLE, LINK_COLUMNS, rows, "ON CONFLICT DO NOTHING").await
+	}
+
+	async fn get_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+		filters: &LinkFilters,
+	) -> Result<Vec<Link>> {
+		let mut values = vec![addresses.into()];
+		let conditions = get_link_conditions(filters, &mut values)?;
+
+		self.query(
+			&format!(
+				r#"
+					SELECT {}
+					FROM {}
+					WHERE to_address = ANY($1){conditions}
+				"#,
+				LINK_COLUMNS.join(", "),
+				link::TABLE
+			),
+			values,
+		)
+		.await?
+		.iter()
+		.map(get_link)
+		.collect()
+	}
+
+	async fn get_distinct_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+		filters: &LinkFilters,
+	) -> Result<Vec<Link>> {
+		let mut values = vec![addresses.into()];
+		let conditions = get_link_conditions(filters, &mut values)?;
+
+		self.query(
+			&format!(
+				r#"
+					SELECT DISTINCT ON (network_id, from_address) {}
+					FROM {}
+					WHERE to_address = ANY($1){conditions}
+					ORDER BY network_id, from_address, cardinality(transfer_uuids) ASC
+				"#,
+				LINK_COLUMNS.join(", "),
+				link::TABLE
+			),
+			values,
+		)
+		.await?
+		.iter()
+		.map(get_link)
+		.collect()
+	}
+
+	async fn get_links_to_seed_blocks(
+		&self,
+		network_id: PrimaryId,
+		(block_height_min, block_height_max): (BlockHeight, BlockHeight),
+	) -> Result<Vec<Link>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT {}
+					FROM {}
+					WHERE network_id = $1 AND to_address IN (
+						SELECT from_address
+						FROM {}
+						WHERE
+							network_id = $1 AND
+							length(from_address) > 0 AND
+							length(to_address) > 0 AND
+							block_height >= $2 AND
+							block_height <= $3
+					)
+				"#,
+				LINK_COLUMNS.join(", "),
+				link::TABLE,
+				transfer::TABLE
+			),
+			vec![
+				network_id.into(),
+				(block_height_min as i64).into(),
+				(block_height_max as i64).into(),
+			],
+		)
+		.await?
+		.iter()
+		.map(get_link)
+		.collect()
+	}
+
+	async fn delete_links_by_sources(
+		&self,
+		sources: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()> {
+		let mut values = vec![];
+		let conditions =
+			Self::get_network_id_address_conditions(sources, "from_address", &mut values);
+
+		self.execute(&format!("DELETE FROM {} WHERE {conditions}", link::TABLE), values).await
+	}
+
+	async fn delete_links_by_newly_added_addresses(
+		&self,
+		targets: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()> {
+		// `transfer_uuids[2:cardinality(transfer_uuids) - 1]` leaves out the first and the last
+		// transfers
+		let mut values = vec![];
+		let conditions =
+			Self::get_network_id_address_conditions(targets, "to_address", &mut values);
+
+		self.execute(
+			&format!(
+				r#"
+					DELETE FROM {}
+					WHERE
+						cardinality(transfer_uuids) > 2 AND
+						transfer_uuids[2:cardinality(transfer_uuids) - 1] && ARRAY(
+							SELECT DISTINCT transfer_uuids[cardinality(transfer_uuids)]
+							FROM {}
+							WHERE cardinality(transfer_uuids) > 0 AND ({conditions})
+						)
+				"#,
+				link::TABLE,
+				link::TABLE
+			),
+			values,
+		)
+		.await
+	}
+
+	async fn create_downstream_links(&self, models: Rows<DownstreamLink>) -> Result<()> {
+		let rows = models
+			.map(|m| {
+				get_link_row(
+					m.network_id,
+					m.block_height,
+					m.from_address,
+					m.to_address,
+					m.transfer_uuids,
+					m.created_at,
+				)
+			})
+			.map(Ok);
+
+		self.insert(downstream_link::TABLE, LINK_COLUMNS, rows, "ON CONFLICT DO NOTHING").await
+	}
+
+	async fn get_downstream_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+	) -> Result<Vec<DownstreamLink>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT {}
+					FROM {}
+					WHERE from_address = ANY($1)
+				"#,
+				LINK_COLUMNS.join(", "),
+				downstream_link::TABLE
+			),
+			vec![addresses.into()],
+		)
+		.await?
+		.iter()
+		.map(get_downstream_link)
+		.collect()
+	}
+
+	async fn get_distinct_downstream_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+	) -> Result<Vec<DownstreamLink>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT DISTINCT ON (network_id, to_address) {}
+					FROM {}
+					WHERE from_address = ANY($1)
+					ORDER BY network_id, to_address, cardinality(transfer_uuids) ASC
+				"#,
+				LINK_COLUMNS.join(", "),
+				downstream_link::TABLE
+			),
+			vec![addresses.into()],
+		)
+		.await?
+		.iter()
+		.map(get_downstream_link)
+		.collect()
+	}
+
+	async fn delete_downstream_links_by_targets(
+		&self,
+		targets: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()> {
+		let mut values = vec![];
+		let conditions =
+			Self::get_network_id_address_conditions(targets, "to_address", &mut values);
+
+		self.execute(&format!("DELETE FROM {} WHERE {conditions}", downstream_link::TABLE), values)
+			.await
+	}
+
+	async fn delete_downstream_links_by_newly_added_addresses(
+		&self,
+		targets: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()> {
+		let mut values = vec![];
+		let conditions =
+			Self::get_network_id_address_conditions(targets, "to_address", &mut values);
+
+		self.execute(
+			&format!(
+				r#"
+					DELETE FROM {}
+					WHERE
+						cardinality(transfer_uuids) > 1 AND
+						transfer_uuids[1:cardinality(transfer_uuids) - 1] && ARRAY(
+							SELECT uuid
+							FROM {}
+							WHERE {conditions}
+						)
+				"#,
+				downstream_link::TABLE,
+				transfer::TABLE
+			),
+			values,
+		)
+		.await
+	}
+
+	async fn create_relations(&self, models: Rows<Relation>) -> Result<()> {
+		let rows = models
+			.map(|m| {
+				vec![
+					m.uuid.into(),
+					(m.module_id as i32).into(),
+					(m.network_id as i64).into(),
+					(m.block_height as i64).into(),
+					m.tx_hash.into(),
+					m.from_address.into(),
+					m.to_address.into(),
+					(m.reason as i32).into(),
+					(m.created_at as i64).into(),
+				]
+			})
+			.map(Ok);
+
+		self.insert(
+			relation::TABLE,
+			&[
+				"uuid",
+				"module_id",
+				"network_id",
+				"block_height",
+				"tx_hash",
+				"from_address",
+				"to_address",
+				"reason",
+				"created_at",
+			],
+			rows,
+			"ON CONFLICT DO NOTHING",
+		)
+		.await
+	}
+
+	async fn get_relations_by_block_range(
+		&self,
+		network_id: PrimaryId,
+		reason: RelationReason,
+		(block_height_min, block_height_max): (BlockHeight, BlockHeight),
+	) -> Result<Vec<Relation>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE
+						network_id = $1 AND
+						reason = $2 AND
+						block_height >= $3 AND
+						block_height <= $4
+				"#,
+				relation::TABLE
+			),
+			vec![
+				network_id.into(),
+				(reason as i32).into(),
+				(block_height_min as i64).into(),
+				(block_height_max as i64).into(),
+			],
+		)
+		.await?
+		.iter()
+		.map(get_relation)
+		.collect()
+	}
+
+	async fn get_relations_by_addresses(
+		&self,
+		network_id: PrimaryId,
+		reason: RelationReason,
+		addresses: Vec<String>,
+		max_block_height: BlockHeight,
+	) -> Result<Vec<Relation>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE
+						network_id = $1 AND
+						reason = $2 AND
+						(from_address = ANY($3) OR to_address = ANY($3)) AND
+						block_height <= $4
+				"#,
+				relation::TABLE
+			),
+			vec![
+				network_id.into(),
+				(reason as i32).into(),
+				addresses.into(),
+				(max_block_height as i64).into(),
+			],
+		)
+		.await?
+		.iter()
+		.map(get_relation)
+		.collect()
+	}
+
+	async fn get_relations_by_address_paginated(
+		&self,
+		address: &str,
+		offset: u64,
+		limit: u64,
+	) -> Result<Vec<Relation>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE from_address = $1 OR to_address = $1
+					ORDER BY block_height DESC, tx_hash, from_address, to_address
+					LIMIT $2 OFFSET $3
+				"#,
+				relation::TABLE
+			),
+			vec![address.into(), (limit as i64).into(), (offset as i64).into()],
+		)
+		.await?
+		.iter()
+		.map(get_relation)
+		.collect()
+	}
+
+	async fn create_clusters(&self, models: Rows<Cluster>) -> Result<()> {
+		// one row per address (the latest one), since a row can't be upserted twice in the
+		// same statement
+		let mut latest = HashMap::<(u64, String), Cluster>::new();
+		for model in models.into_iter() {
+			let key = (model.network_id, model.address.clone());
+			if latest.get(&key).is_none_or(|c| c.block_height <= model.block_height) {
+				latest.insert(key, model);
+			}
+		}
+
+		let rows = latest
+			.into_values()
+			.map(|m| {
+				vec![
+					(m.network_id as i64).into(),
+					m.address.into(),
+					m.cluster_id.into(),
+					(m.block_height as i64).into(),
+				]
+			})
+			.map(Ok);
+
+		self.insert(
+			cluster::TABLE,
+			&["network_id", "address", "cluster_id", "block_height"],
+			rows,
+			&format!(
+				r#"
+					ON CONFLICT (network_id, address) DO UPDATE
+					SET cluster_id = EXCLUDED.cluster_id, block_height = EXCLUDED.block_height
+					WHERE {}.block_height <= EXCLUDED.block_height
+				"#,
+				cluster::TABLE
+			),
+		)
+		.await
+	}
+
+	async fn get_clusters_by_addresses(&self, addresses: Vec<String>) -> Result<Vec<Cluster>> {
+		self.query(
+			&format!("SELECT * FROM {} WHERE address = ANY($1)", cluster::TABLE),
+			vec![addresses.into()],
+		)
+		.await?
+		.iter()
+		.map(get_cluster)
+		.collect()
+	}
+
+	async fn get_clusters_by_cluster_ids(
+		&self,
+		network_id: PrimaryId,
+		cluster_ids: Vec<String>,
+	) -> Result<Vec<Cluster>> {
+		self.query(
+			&format!(
+				"SELECT * FROM {} WHERE network_id = $1 AND cluster_id = ANY($2)",
+				cluster::TABLE
+			),
+			vec![network_id.into(), cluster_ids.into()],
+		)
+		.await?
+		.iter()
+		.map(get_cluster)
+		.collect()
+	}
+
+	async fn get_clusters_after_block_height(
+		&self,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+	) -> Result<Vec<Cluster>> {
+		self.query(
+			&format!(
+				"SELECT * FROM {} WHERE network_id = $1 AND block_height > $2",
+				cluster::TABLE
+			),
+			vec![network_id.into(), (block_height as i64).into()],
+		)
+		.await?
+		.iter()
+		.map(get_cluster)
+		.collect()
+	}
+
+	async fn get_clusters_by_member(
+		&self,
+		network_id: PrimaryId,
+		address: &str,
+	) -> Result<Vec<Cluster>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE network_id = $1 AND cluster_id IN (
+						SELECT cluster_id
+						FROM {}
+						WHERE network_id = $1 AND address = $2
+					)
+				"#,
+				cluster::TABLE,
+				cluster::TABLE
+			),
+			vec![network_id.into(), address.into()],
+		)
+		.await?
+		.iter()
+		.map(get_cluster)
+		.collect()
+	}
+
+	async fn create_reconciliations(&self, models: Rows<Reconciliation>) -> Result<()> {
+		let rows = models.map(|m| -> Result<Vec<Value>> {
+			Ok(vec![
+				(m.network_id as i64).into(),
+				(m.module_id as i32).into(),
+				(m.block_height as i64).into(),
+				m.address.into(),
+				m.asset_address.into(),
+				to_numeric(m.indexed_balance)?,
+				to_numeric(m.live_balance)?,
+				(m.created_at as i64).into(),
+			])
+		});
+
+		self.insert(
+			reconciliation::TABLE,
+			&[
+				"network_id",
+				"module_id",
+				"block_height",
+				"address",
+				"asset_address",
+				"indexed_balance",
+				"live_balance",
+				"created_at",
+			],
+			rows,
+			"",
+		)
+		.await?;
+
+		self.execute(
+			&format!("DELETE FROM {} WHERE created_at < $1", reconciliation::TABLE),
+			vec![(utils::now().timestamp() - RECONCILIATIONS_TTL).into()],
+		)
+		.await
+	}
+
+	async fn get_reconciliation_stats(&self, since: u32) -> Result<Vec<ReconciliationStats>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT
+						network_id,
+						module_id,
+						COUNT(*) AS checked,
+						COUNT(*) FILTER (WHERE indexed_balance != live_balance) AS drifted,
+						MAX(created_at) AS checked_at
+					FROM {}
+					WHERE created_at >= $1
+					GROUP BY network_id, module_id
+					ORDER BY network_id, module_id
+				"#,
+				reconciliation::TABLE
+			),
+			vec![(since as i64).into()],
+		)
+		.await?
+		.iter()
+		.map(|r| {
+			Ok(ReconciliationStats {
+				network_id: r.try_get::<i64>("", "network_id")? as u64,
+				module_id: r.try_get::<i32>("", "module_id")? as u16,
+				checked: r.try_get::<i64>("", "checked")? as u64,
+				drifted: r.try_get::<i64>("", "drifted")? as u64,
+				checked_at: r.try_get::<i64>("", "checked_at")? as u32,
+			})
+		})
+		.collect()
+	}
+}
+
+// extra conditions for `filters`, with values appended to `values` (which already holds the
+// addresses links are being fetched for as `$1`)
+fn get_link_conditions(filters: &LinkFilters, values: &mut Vec<Value>) -> Result<String> {
+	let mut ret = String::new();
+
+	let mut push = |condition: &str, value: Value| {
+		values.push(value);
+		ret.push_str(&condition.replace('?', &format!("${}", values.len())));
+	};
+
+	if let Some(min_hops) = filters.min_hops {
+		push(" AND cardinality(transfer_uuids) >= ?", (min_hops as i64).into());
+	}
+	if let Some(max_hops) = filters.max_hops {
+		push(" AND cardinality(transfer_uuids) <= ?", (max_hops as i64).into());
+	}
+	if let Some(since) = filters.since {
+		push(" AND created_at >= ?", (since as i64).into());
+	}
+	if let Some(until) = filters.until {
+		push(" AND created_at <= ?", (until as i64).into());
+	}
+	if let Some((asset_address, min_amount)) = &filters.min_amount {
+		push(
+			&format!(
+				r#"
+					AND NOT EXISTS (
+						SELECT 1
+						FROM {}
+						WHERE uuid = ANY(transfer_uuids) AND (asset_address <> ?
+				"#,
+				transfer::TABLE
+			),
+			asset_address.clone().into(),
+		);
+		push(" OR relative_amount < ?))", to_numeric(*min_amount)?);
+	}
+
+	Ok(ret)
+}
+
+// shared by links and downstream links
+fn get_link_row(
+	network_id: u64,
+	block_height: u64,
+	from_address: String,
+	to_address: String,
+	transfer_uuids: Vec<LinkUuid>,
+	created_at: u32,
+) -> Vec<Value> {
+	vec![
+		(network_id as i64).into(),
+		(block_height as i64).into(),
+		from_address.into(),
+		to_address.into(),
+		transfer_uuids.into_iter().map(|u| u.0).collect::<Vec<Uuid>>().into(),
+		(created_at as i64).into(),
+	]
+}
+
+fn to_numeric(value: U256) -> Result<Value> {
+	Ok(BigDecimal::from_str(&value.to_string())?.into())
+}
+
+fn get_u256(r: &QueryResult, column: &str) -> Result<U256> {
+	Ok(U256::from_dec_str(&r.try_get::<String>("", column)?)?)
+}
+
+fn get_transfer(r: &QueryResult) -> Result<Transfer> {
+	Ok(Transfer {
+		uuid: r.try_get("", "uuid")?,
+		module_id: r.try_get::<i32>("", "module_id")? as u16,
+		network_id: r.try_get::<i64>("", "network_id")? as u64,
+		block_height: r.try_get::<i64>("", "block_height")? as u64,
+		tx_hash: r.try_get("", "tx_hash")?,
+		from_address: r.try_get("", "from_address")?,
+		to_address: r.try_get("", "to_address")?,
+		asset_address: r.try_get("", "asset_address")?,
+		token_id: r.try_get("", "token_id")?,
+		relative_amount: get_u256(r, "relative_amount")?,
+		batch_amount: get_u256(r, "batch_amount")?,
+		created_at: r.try_get::<i64>("", "created_at")? as u32,
+	})
+}
+
+fn get_balance(r: &QueryResult) -> Result<Balance> {
+	Ok(Balance {
+		network_id: r.try_get::<i64>("", "network_id")? as u64,
+		address: r.try_get("", "address")?,
+		asset_address: r.try_get("", "asset_address")?,
+		balance: get_u256(r, "balance")?,
+	})
+}
+
+fn get_link(r: &QueryResult) -> Result<Link> {
+	Ok(Link {
+		network_id: r.try_get::<i64>("", "network_id")? as u64,
+		block_height: r.try_get::<i64>("", "block_height")? as u64,
+		from_address: r.try_get("", "from_address")?,
+		to_address: r.try_get("", "to_address")?,
+		transfer_uuids: r
+			.try_get::<Vec<Uuid>>("", "transfer_uuids")?
+			.into_iter()
+			.map(LinkUuid)
+			.collect(),
+		created_at: r.try_get::<i64>("", "created_at")? as u32,
+	})
+}
+
+fn get_downstream_link(r: &QueryResult) -> Result<DownstreamLink> {
+	let link = get_link(r)?;
+
+	Ok(DownstreamLink {
+		network_id: link.network_id,
+		block_height: link.block_height,
+		from_address: link.from_address,
+		to_address: link.to_address,
+		transfer_uuids: link.transfer_uuids,
+		created_at: link.created_at,
+	})
+}
+
+fn get_relation(r: &QueryResult) -> Result<Relation> {
+	Ok(Relation {
+		uuid: r.try_get("", "uuid")?,
+		module_id: r.try_get::<i32>("", "module_id")? as u16,
+		network_id: r.try_get::<i64>("", "network_id")? as u64,
+		block_height: r.try_get::<i64>("", "block_height")? as u64,
+		tx_hash: r.try_get("", "tx_hash")?,
+		from_address: r.try_get("", "from_address")?,
+		to_address: r.try_get("", "to_address")?,
+		reason: r.try_get::<i32>("", "reason")? as u16,
+		created_at: r.try_get::<i64>("", "created_at")? as u32,
+	})
+}
+
+fn get_cluster(r: &QueryResult) -> Result<Cluster> {
+	Ok(Cluster {
+		network_id: r.try_get::<i64>("", "network_id")? as u64,
+		address: r.try_get("", "address")?,
+		cluster_id: r.try_get("", "cluster_id")?,
+		block_height: r.try_get::<i64>("", "block_height")? as u64,
+	})
+}
@@ -0,0 +1,1574 @@
+use async_trait::async_trait;
+use eyre::{Result, WrapErr};
+use log::LevelFilter;
+use sea_orm::{
+	ConnectOptions, ConnectionTrait, Database, DatabaseConnection, DbBackend, QueryResult,
+	Statement, TransactionTrait, Value,
+};
+use std::{
+	collections::{HashMap, HashSet},
+	fs,
+	path::PathBuf,
+};
+use uuid::Uuid;
+
+use crate::{
+	chain::U256,
+	models::{
+		amount, balance, cluster, downstream_link, link, reconciliation, relation, transfer,
+		Amount, Balance, Cluster, DownstreamLink, Link, LinkFilters, LinkUuid, PrimaryId,
+		Reconciliation, ReconciliationStats, Relation, RelationReason, Transfer,
+	},
+	utils,
+	warehouse::{migrations, Rows, WarehouseTrait},
+	BlockHeight,
+};
+
+// sqlite caps the number of bind parameters per statement
+const MAX_PARAMS: usize = 32_766;
+
+// how long reconciliation results are kept around for (clickhouse does this with a ttl)
+const RECONCILIATIONS_TTL: i64 = 30 * 24 * 60 * 60;
+
+// u256 values are stored as text, zero-padded to the max length so they compare as numbers
+const U256_DIGITS: usize = 78;
+
+static LINK_COLUMNS: &[&str] =
+	&["network_id", "block_height", "from_address", "to_address", "transfer_uuids", "created_at"];
+
+// embedded warehouse for running without any external services. there are no arrays or
+// 256-bit integers, so lists are passed in (and `transfer_uuids` stored) as json arrays, and
+// u256 sums are done after reading rows back out
+pub struct SQLite {
+	path: String,
+	db: DatabaseConnection,
+}
+
+impl SQLite {
+	pub async fn new(url: &str) -> Result<Self> {
+		let path = match utils::get_db_path(url) {
+			path if path.is_empty() => utils::project_dir(Some("warehouse")),
+			path => PathBuf::from(path),
+		};
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent).wrap_err(path.display().to_string())?;
+		}
+
+		let path = path.display().to_string();
+
+		// writes are not guaranteed to be executed serially otherwise
+		let mut opt = ConnectOptions::new(format!("sqlite://{path}?mode=rwc"));
+		opt.max_connections(1)
+			.min_connections(1)
+			.sqlx_logging(false)
+			.sqlx_logging_level(LevelFilter::Warn);
+
+		Ok(Self { path: path.clone(), db: Database::connect(opt).await.wrap_err(path)? })
+	}
+
+	async fn execute(&self, sql: &str, values: Vec<Value>) -> Result<()> {
+		self.db.execute(Statement::from_sql_and_values(DbBackend::Sqlite, sql, values)).await?;
+
+		Ok(())
+	}
+
+	async fn query(&self, sql: &str, values: Vec<Value>) -> Result<Vec<QueryResult>> {
+		Ok(self
+			.db
+			.query_all(Statement::from_sql_and_values(DbBackend::Sqlite, sql, values))
+			.await?)
+	}
+
+	// multi-row insert, pulling rows a statement's worth at a time (so they're never all in
+	// memory at once) and staying under the bind parameter limit
+	async fn insert(
+		&self,
+		table: &str,
+		columns: &[&str],
+		mut rows: impl Iterator<Item = Result<Vec<Value>>> + Send,
+		on_conflict: &str,
+	) -> Result<()> {
+		let tuple = format!("({})", vec!["?"; columns.len()].join(", "));
+
+		loop {
+			let chunk =
+				rows.by_ref().take(MAX_PARAMS / columns.len()).collect::<Result<Vec<_>>>()?;
+			if chunk.is_empty() {
+				break;
+			}
+
+			self.execute(
+				&format!(
+					"INSERT INTO {table} ({}) VALUES {} {on_conflict}",
+					columns.join(", "),
+					vec![tuple.as_str(); chunk.len()].join(", ")
+				),
+				chunk.into_iter().flatten().collect(),
+			)
+			.await?;
+		}
+
+		Ok(())
+	}
+
+	// `(network_id = ? AND field IN (...)) OR ...`, with values appended to `values`
+	fn get_network_id_address_conditions(
+		map: HashMap<PrimaryId, HashSet<String>>,
+		field: &str,
+		values: &mut Vec<Value>,
+	) -> Result<String> {
+		let mut ret = vec![];
+
+		for (network_id, addresses) in map.into_iter() {
+			values.push(network_id.into());
+			values.push(to_json(addresses)?);
+
+			ret.push(format!("(network_id = ? AND {field} IN (SELECT value FROM json_each(?)))"));
+		}
+
+		Ok(ret.join(" OR "))
+	}
+
+	// rows of `network_id, address, asset_address, amount_in, amount_out` summed up into
+	// balances (negative ones are left out)
+	fn get_balances(rows: Vec<QueryResult>) -> Result<Vec<Balance>> {
+		let mut totals = HashMap::<(u64, String, String), (U256, U256)>::new();
+		for r in rows.iter() {
+			let (amount_in, amount_out) = totals
+				.entry((
+					r.try_get::<i64>("", "network_id")? as u64,
+					r.try_get("", "address")?,
+					r.try_get("", "asset_address")?,
+				))
+				.or_default();
+
+			*amount_in = amount_in.saturating_add(get_u256(r, "amount_in")?);
+			*amount_out = amount_out.saturating_add(get_u256(r, "amount_out")?);
+		}
+
+		Ok(totals
+			.into_iter()
+			.filter(|(_, (amount_in, amount_out))| amount_in >= amount_out)
+			.map(|((network_id, address, asset_address), (amount_in, amount_out))| Balance {
+				network_id,
+				address,
+				asset_address,
+				balance: amount_in - amount_out,
+			})
+			.collect())
+	}
+}
+
+#[async_trait]
+impl WarehouseTrait for SQLite {
+	async fn create_migrations_table(&self) -> Result<()> {
+		self.execute(
+			&format!(
+				r#"
+					CREATE TABLE IF NOT EXISTS {}
+					(
+						version TEXT NOT NULL PRIMARY KEY,
+						applied_at BIGINT NOT NULL
+					)
+				"#,
+				migrations::TABLE
+			),
+			vec![],
+		)
+		.await
+		.wrap_err(self.path.clone())
+	}
+
+	async fn get_applied_migrations(&self) -> Result<Vec<String>> {
+		let exists = self
+			.query(
+				"SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?",
+				vec![migrations::TABLE.into()],
+			)
+			.await?;
+		if exists.is_empty() {
+			return Ok(vec![]);
+		}
+
+		self.query(&format!("SELECT version FROM {} ORDER BY version", migrations::TABLE), vec![])
+			.await?
+			.iter()
+			.map(|r| Ok(r.try_get("", "version")?))
+			.collect()
+	}
+
+	// steps and their bookkeeping run in one transaction, so a failed migration leaves no trace
+	async fn apply_migration(&self, version: &str, statements: Vec<String>) -> Result<()> {
+		let txn = self.db.begin().await?;
+		for statement in statements.into_iter() {
+			txn.execute(Statement::from_string(DbBackend::Sqlite, statement))
+				.await
+				.wrap_err(version.to_string())?;
+		}
+
+		txn.execute(Statement::from_sql_and_values(
+			DbBackend::Sqlite,
+			&format!("INSERT INTO {} (version, applied_at) VALUES (?, ?)", migrations::TABLE),
+			[version.into(), utils::now().timestamp().into()],
+		))
+		.await?;
+
+		Ok(txn.commit().await?)
+	}
+
+	async fn revert_migration(&self, version: &str, statements: Vec<String>) -> Result<()> {
+		let txn = self.db.begin().await?;
+		for statement in statements.into_iter() {
+			txn.execute(Statement::from_string(DbBackend::Sqlite, statement))
+				.await
+				.wrap_err(version.to_string())?;
+		}
+
+		txn.execute(Statement::from_sql_and_values(
+			DbBackend::Sqlite,
+			&format!("DELETE FROM {} WHERE version = ?", migrations::TABLE),
+			[version.into()],
+		))
+		.await?;
+
+		Ok(txn.commit().await?)
+	}
+
+	async fn delete_all_by_network_ids(
+		&self,
+		table: &str,
+		network_ids: Vec<PrimaryId>,
+	) -> Result<()> {
+		// balances are summed up from amounts, which get deleted on their own
+		if table == balance::TABLE {
+			return Ok(());
+		}
+
+		self.execute(
+			&format!("DELETE FROM {table} WHERE network_id IN (SELECT value FROM json_each(?))"),
+			vec![to_json(network_ids)?],
+		)
+		.await
+	}
+
+	async fn delete_all_after_block_height(
+		&self,
+		table: &str,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+	) -> Result<()> {
+		self.execute(
+			&format!("DELETE FROM {table} WHERE network_id = ? AND block_height > ?"),
+			vec![network_id.into(), (block_height as i64).into()],
+		)
+		.await
+	}
+
+	async fn create_transfers(&self, models: Rows<Transfer>) -> Result<()> {
+		let rows = models
+			.map(|m| {
+				vec![
+					m.uuid.to_string().into(),
+					(m.module_id as i32).into(),
+					(m.network_id as i64).into(),
+					(m.block_height as i64).into(),
+					m.tx_hash.into(),
+					m.from_address.into(),
+					m.to_address.into(),
+					m.asset_address.into(),
+					m.token_id.into(),
+					to_text(m.relative_amount),
+					to_text(m.batch_amount),
+					(m.created_at as i64).into(),
+				]
+			})
+			.map(Ok);
+
+		self.insert(
+			transfer::TABLE,
+			&[
+				"uuid",
+				"module_id",
+				"network_id",
+				"block_height",
+				"tx_hash",
+				"from_address",
+				"to_address",
+				"asset_address",
+				"token_id",
+				"relative_amount",
+				"batch_amount",
+				"created_at",
+			],
+			rows,
+			"ON CONFLICT DO NOTHING",
+		)
+		.await
+	}
+
+	async fn get_first_transfer_by_source(
+		&self,
+		network_id: PrimaryId,
+		address: &str,
+	) -> Result<Option<Transfer>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE network_id = ? AND from_address = ?
+					ORDER BY created_at ASC
+					LIMIT 1
+				"#,
+				transfer::TABLE
+			),
+			vec![network_id.into(), address.into()],
+		)
+		.await?
+		.iter()
+		.map(get_transfer)
+		.next()
+		.transpose()
+	}
+
+	async fn get_first_transfer_by_target(
+		&self,
+		network_id: PrimaryId,
+		address: &str,
+	) -> Result<Option<Transfer>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE network_id = ? AND to_address = ?
+					ORDER BY created_at ASC
+					LIMIT 1
+				"#,
+				transfer::TABLE
+			),
+			vec![network_id.into(), address.into()],
+		)
+		.await?
+		.iter()
+		.map(get_transfer)
+		.next()
+		.transpose()
+	}
+
+	async fn get_transfers_by_targets(
+		&self,
+		network_id: PrimaryId,
+		addresses: Vec<String>,
+		(block_height_min, block_height_max): (BlockHeight, BlockHeight),
+	) -> Result<Vec<Transfer>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE
+						network_id = ? AND
+						to_address IN (SELECT value FROM json_each(?)) AND
+						length(from_address) > 0 AND
+						block_height >= ? AND
+						block_height <= ?
+					ORDER BY block_height DESC
+				"#,
+				transfer::TABLE
+			),
+			vec![
+				network_id.into(),
+				to_json(addresses)?,
+				(block_height_min as i64).into(),
+				(block_height_max as i64).into(),
+			],
+		)
+		.await?
+		.iter()
+		.map(get_transfer)
+		.collect()
+	}
+
+	async fn get_transfers_by_addresses(
+		&self,
+		network_id: PrimaryId,
+		addresses: Vec<String>,
+		max_block_height: BlockHeight,
+	) -> Result<Vec<Transfer>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE
+						network_id = ? AND
+						(
+							from_address IN (SELECT value FROM json_each(?)) OR
+							to_address IN (SELECT value FROM json_each(?))
+						) AND
+						block_height <= ?
+				"#,
+				transfer::TABLE
+			),
+			vec![
+				network_id.into(),
+				to_json(&addresses)?,
+				to_json(addresses)?,
+				(max_block_height as i64).into(),
+			],
+		)
+		.await?
+		.iter()
+		.map(get_transfer)
+		.collect()
+	}
+
+	async fn get_transfers_by_block_range(
+		&self,
+		network_id: PrimaryId,
+		(block_height_min, block_height_max): (BlockHeight, BlockHeight),
+	) -> Result<Vec<Transfer>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE
+						network_id = ? AND
+						block_height >= ? AND
+						block_height <= ?
+					ORDER BY block_height ASC
+				"#,
+				transfer::TABLE
+			),
+			vec![
+				network_id.into(),
+				(block_height_min as i64).into(),
+				(block_height_max as i64).into(),
+			],
+		)
+		.await?
+		.iter()
+		.map(get_transfer)
+		.collect()
+	}
+
+	async fn get_transfers_by_uuids(&self, uuids: Vec<Uuid>) -> Result<Vec<Transfer>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE uuid IN (SELECT value FROM json_each(?))
+				"#,
+				transfer::TABLE
+			),
+			vec![to_json(uuids)?],
+		)
+		.await?
+		.iter()
+		.map(get_transfer)
+		.collect()
+	}
+
+	async fn create_amounts(&self, models: Rows<Amount>) -> Result<()> {
+		let rows = models
+			.map(|m| {
+				vec![
+					m.uuid.to_string().into(),
+					(m.module_id as i32).into(),
+					(m.network_id as i64).into(),
+					(m.block_height as i64).into(),
+					m.tx_hash.into(),
+					m.address.into(),
+					m.asset_address.into(),
+					to_text(m.amount_in),
+					to_text(m.amount_out),
+					(m.created_at as i64).into(),
+				]
+			})
+			.map(Ok);
+
+		self.insert(
+			amount::TABLE,
+			&[
+				"uuid",
+				"module_id",
+				"network_id",
+				"block_height",
+				"tx_hash",
+				"address",
+				"asset_address",
+				"amount_in",
+				"amount_out",
+				"created_at",
+			],
+			rows,
+			"ON CONFLICT DO NOTHING",
+		)
+		.await
+	}
+
+	async fn get_amount_network_ids_by_addresses(
+		&self,
+		addresses: Vec<String>,
+	) -> Result<Vec<PrimaryId>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT DISTINCT network_id
+					FROM {}
+					WHERE address IN (SELECT value FROM json_each(?))
+				"#,
+				amount::TABLE
+			),
+			vec![to_json(addresses)?],
+		)
+		.await?
+		.iter()
+		.map(|r| Ok(r.try_get::<i64>("", "network_id")?))
+		.collect()
+	}
+
+	async fn get_amounts_received_by_addresses(
+		&self,
+		addresses: Vec<String>,
+		(since, until): (u32, u32),
+	) -> Result<HashMap<(PrimaryId, String), U256>> {
+		let rows = self
+			.query(
+				&format!(
+					r#"
+						SELECT network_id, asset_address, amount_in
+						FROM {}
+						WHERE
+							address IN (SELECT value FROM json_each(?)) AND
+							created_at >= ? AND
+							created_at <= ?
+					"#,
+					amount::TABLE
+				),
+				vec![to_json(addresses)?, (since as i64).into(), (until as i64).into()],
+			)
+			.await?;
+
+		let mut ret = HashMap::<(PrimaryId, String), U256>::new();
+		for r in rows.iter() {
+			let amount_in = ret
+				.entry((r.try_get::<i64>("", "network_id")?, r.try_get("", "asset_address")?))
+				.or_default();
+
+			*amount_in = amount_in.saturating_add(get_u256(r, "amount_in")?);
+		}
+
+		Ok(ret)
+	}
+
+	async fn get_balances_by_addresses(&self, addresses: Vec<String>) -> Result<Vec<Balance>> {
+		Self::get_balances(
+			self.query(
+				&format!(
+					r#"
+						SELECT network_id, address, asset_address, amount_in, amount_out
+						FROM {}
+						WHERE address IN (SELECT value FROM json_each(?))
+					"#,
+					amount::TABLE
+				),
+				vec![to_json(addresses)?],
+			)
+			.await?,
+		)
+	}
+
+	async fn get_balances_random_sample(
+		&self,
+		network_id: PrimaryId,
+		limit: u64,
+	) -> Result<Vec<Balance>> {
+		Self::get_balances(
+			self.query(
+				&format!(
+					r#"
+						SELECT network_id, address, asset_address, amount_in, amount_out
+						FROM {}
+						WHERE
+							network_id = ? AND
+							(address, asset_address) IN (
+								SELECT address, asset_address
+								FROM {}
+								WHERE network_id = ?
+								GROUP BY address, asset_address
+								ORDER BY random()
+								LIMIT ?
+							)
+					"#,
+					amount::TABLE,
+					amount::TABLE
+				),
+				vec![network_id.into(), network_id.into(), (limit as i64).into()],
+			)
+			.await?,
+		)
+	}
+
+	async fn create_links(&self, models: Rows<Link>) -> Result<()> {
+		let rows = models.map(|m| {
+			get_link_row(
+				m.network_id,
+				m.block_height,
+				m.from_address,
+				m.to_address,
+				m.transfer_uuids,
+				m.created_at,
+			)
+		});
+
+		self.insert(link::TABLE, LINK_COLUMNS, rows, "ON CONFLICT DO NOTHING").await
+	}
+
+	async fn get_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+		filters: &LinkFilters,
+	) -> Result<Vec<Link>> {
+		let mut values = vec![to_json(addresses)?];
+		let conditions = get_link_conditions(filters, &mut values);
+
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE to_address IN (SELECT value FROM json_each(?)){conditions}
+				"#,
+				link::TABLE
+			),
+			values,
+		)
+		.await?
+		.iter()
+		.map(get_link)
+		.collect()
+	}
+
+	async fn get_distinct_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+		filters: &LinkFilters,
+	) -> Result<Vec<Link>> {
+		let mut values = vec![to_json(addresses)?];
+		let conditions = get_link_conditions(filters, &mut values);
+
+		self.query(
+			&format!(
+				r#"
+					SELECT {}
+					FROM (
+						SELECT
+							*,
+							ROW_NUMBER() OVER (
+								PARTITION BY network_id, from_address
+								ORDER BY json_array_length(transfer_uuids) ASC
+							) AS n
+						FROM {}
+						WHERE to_address IN (SELECT value FROM json_each(?)){conditions}
+					)
+					WHERE n = 1
+				"#,
+				LINK_COLUMNS.join(", "),
+				link::TABLE
+			),
+			values,
+		)
+		.await?
+		.iter()
+		.map(get_link)
+		.collect()
+	}
+
+	async fn get_links_to_seed_blocks(
+		&self,
+		network_id: PrimaryId,
+		(block_height_min, block_height_max): (BlockHeight, BlockHeight),
+	) -> Result<Vec<Link>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE network_id = ? AND to_address IN (
+						SELECT from_address
+						FROM {}
+						WHERE
+							network_id = ? AND
+							length(from_address) > 0 AND
+							length(to_address) > 0 AND
+							block_height >= ? AND
+							block_height <= ?
+					)
+				"#,
+				link::TABLE,
+				transfer::TABLE
+			),
+			vec![
+				network_id.into(),
+				network_id.into(),
+				(block_height_min as i64).into(),
+				(block_height_max as i64).into(),
+			],
+		)
+		.await?
+		.iter()
+		.map(get_link)
+		.collect()
+	}
+
+	async fn delete_links_by_sources(
+		&self,
+		sources: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()> {
+		let mut values = vec![];
+		let conditions =
+			Self::get_network_id_address_conditions(sources, "from_address", &mut values)?;
+
+		self.execute(&format!("DELETE FROM {} WHERE {conditions}", link::TABLE), values).await
+	}
+
+	async fn delete_links_by_newly_added_addresses(
+		&self,
+		targets: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()> {
+		// keys of `json_each` are array indexes, so `0 < key < length - 1` leaves out the first
+		// and the last transfers
+		let mut values = vec![];
+		let conditions =
+			Self::get_network_id_address_conditions(targets, "to_address", &mut values)?;
+
+		self.execute(
+			&format!(
+				r#"
+					DELETE FROM {}
+					WHERE
+						json_array_length(transfer_uuids) > 2 AND
+						EXISTS (
+							SELECT 1
+							FROM json_each({}.transfer_uuids) AS u
+							WHERE
+								u.key > 0 AND
+								u.key < json_array_length({}.transfer_uuids) - 1 AND
+								u.value IN (
+									SELECT json_extract(transfer_uuids, '$[#-1]')
+									FROM {}
+									WHERE json_array_length(transfer_uuids) > 0 AND ({conditions})
+								)
+						)
+				"#,
+				link::TABLE,
+				link::TABLE,
+				link::TABLE,
+				link::TABLE
+			),
+			values,
+		)
+		.await
+	}
+
+	async fn create_downstream_links(&self, models: Rows<DownstreamLink>) -> Result<()> {
+		let rows = models.map(|m| {
+			get_link_row(
+				m.network_id,
+				m.block_height,
+				m.from_address,
+				m.to_address,
+				m.transfer_uuids,
+				m.created_at,
+			)
+		});
+
+		self.insert(downstream_link::TABLE, LINK_COLUMNS, rows, "ON CONFLICT DO NOTHING").await
+	}
+
+	async fn get_downstream_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+	) -> Result<Vec<DownstreamLink>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE from_address IN (SELECT value FROM json_each(?))
+				"#,
+				downstream_link::TABLE
+			),
+			vec![to_json(addresses)?],
+		)
+		.await?
+		.iter()
+		.map(get_downstream_link)
+		.collect()
+	}
+
+	async fn get_distinct_downstream_links_by_addresses(
+		&self,
+		addresses: Vec<String>,
+	) -> Result<Vec<DownstreamLink>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT {}
+					FROM (
+						SELECT
+							*,
+							ROW_NUMBER() OVER (
+								PARTITION BY network_id, to_address
+								ORDER BY json_array_length(transfer_uuids) ASC
+							) AS n
+						FROM {}
+						WHERE from_address IN (SELECT value FROM json_each(?))
+					)
+					WHERE n = 1
+				"#,
+				LINK_COLUMNS.join(", "),
+				downstream_link::TABLE
+			),
+			vec![to_json(addresses)?],
+		)
+		.await?
+		.iter()
+		.map(get_downstream_link)
+		.collect()
+	}
+
+	async fn delete_downstream_links_by_targets(
+		&self,
+		targets: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()> {
+		let mut values = vec![];
+		let conditions =
+			Self::get_network_id_address_conditions(targets, "to_address", &mut values)?;
+
+		self.execute(&format!("DELETE FROM {} WHERE {conditions}", downstream_link::TABLE), values)
+			.await
+	}
+
+	async fn delete_downstream_links_by_newly_added_addresses(
+		&self,
+		targets: HashMap<PrimaryId, HashSet<String>>,
+	) -> Result<()> {
+		let mut values = vec![];
+		let conditions =
+			Self::get_network_id_address_conditions(targets, "to_address", &mut values)?;
+
+		self.execute(
+			&format!(
+				r#"
+					DELETE FROM {}
+					WHERE
+						json_array_length(transfer_uuids) > 1 AND
+						EXISTS (
+							SELECT 1
+							FROM json_each({}.transfer_uuids) AS u
+							WHERE
+								u.key < json_array_length({}.transfer_uuids) - 1 AND
+								u.value IN (SELECT uuid FROM {} WHERE {conditions})
+						)
+				"#,
+				downstream_link::TABLE,
+				downstream_link::TABLE,
+				downstream_link::TABLE,
+				transfer::TABLE
+			),
+			values,
+		)
+		.await
+	}
+
+	async fn create_relations(&self, models: Rows<Relation>) -> Result<()> {
+		let rows = models
+			.map(|m| {
+				vec![
+					m.uuid.to_string().into(),
+					(m.module_id as i32).into(),
+					(m.network_id as i64).into(),
+					(m.block_height as i64).into(),
+					m.tx_hash.into(),
+					m.from_address.into(),
+					m.to_address.into(),
+					(m.reason as i32).into(),
+					(m.created_at as i64).into(),
+				]
+			})
+			.map(Ok);
+
+		self.insert(
+			relation::TABLE,
+			&[
+				"uuid",
+				"module_id",
+				"network_id",
+				"block_height",
+				"tx_hash",
+				"from_address",
+				"to_address",
+				"reason",
+				"created_at",
+			],
+			rows,
+			"ON CONFLICT DO NOTHING",
+		)
+		.await
+	}
+
+	async fn get_relations_by_block_range(
+		&self,
+		network_id: PrimaryId,
+		reason: RelationReason,
+		(block_height_min, block_height_max): (BlockHeight, BlockHeight),
+	) -> Result<Vec<Relation>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE
+						network_id = ? AND
+						reason = ? AND
+						block_height >= ? AND
+						block_height <= ?
+				"#,
+				relation::TABLE
+			),
+			vec![
+				network_id.into(),
+				(reason as i32).into(),
+				(block_height_min as i64).into(),
+				(block_height_max as i64).into(),
+			],
+		)
+		.await?
+		.iter()
+		.map(get_relation)
+		.collect()
+	}
+
+	async fn get_relations_by_addresses(
+		&self,
+		network_id: PrimaryId,
+		reason: RelationReason,
+		addresses: Vec<String>,
+		max_block_height: BlockHeight,
+	) -> Result<Vec<Relation>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE
+						network_id = ? AND
+						reason = ? AND
+						(
+							from_address IN (SELECT value FROM json_each(?)) OR
+							to_address IN (SELECT value FROM json_each(?))
+						) AND
+						block_height <= ?
+				"#,
+				relation::TABLE
+			),
+			vec![
+				network_id.into(),
+				(reason as i32).into(),
+				to_json(&addresses)?,
+				to_json(addresses)?,
+				(max_block_height as i64).into(),
+			],
+		)
+		.await?
+		.iter()
+		.map(get_relation)
+		.collect()
+	}
+
+	async fn get_relations_by_address_paginated(
+		&self,
+		address: &str,
+		offset: u64,
+		limit: u64,
+	) -> Result<Vec<Relation>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE from_address = ? OR to_address = ?
+					ORDER BY block_height DESC, tx_hash, from_address, to_address
+					LIMIT ? OFFSET ?
+				"#,
+				relation::TABLE
+			),
+			vec![address.into(), address.into(), (limit as i64).into(), (offset as i64).into()],
+		)
+		.await?
+		.iter()
+		.map(get_relation)
+		.collect()
+	}
+
+	async fn create_clusters(&self, models: Rows<Cluster>) -> Result<()> {
+		let rows = models
+			.map(|m| {
+				vec![
+					(m.network_id as i64).into(),
+					m.address.into(),
+					m.cluster_id.into(),
+					(m.block_height as i64).into(),
+				]
+			})
+			.map(Ok);
+
+		self.insert(
+			cluster::TABLE,
+			&["network_id", "address", "cluster_id", "block_height"],
+			rows,
+			&format!(
+				r#"
+					ON CONFLICT (network_id, address) DO UPDATE
+					SET cluster_id = excluded.cluster_id, block_height = excluded.block_height
+					WHERE {}.block_height <= excluded.block_height
+				"#,
+				cluster::TABLE
+			),
+		)
+		.await
+	}
+
+	async fn get_clusters_by_addresses(&self, addresses: Vec<String>) -> Result<Vec<Cluster>> {
+		self.query(
+			&format!(
+				"SELECT * FROM {} WHERE address IN (SELECT value FROM json_each(?))",
+				cluster::TABLE
+			),
+			vec![to_json(addresses)?],
+		)
+		.await?
+		.iter()
+		.map(get_cluster)
+		.collect()
+	}
+
+	async fn get_clusters_by_cluster_ids(
+		&self,
+		network_id: PrimaryId,
+		cluster_ids: Vec<String>,
+	) -> Result<Vec<Cluster>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE network_id = ? AND cluster_id IN (SELECT value FROM json_each(?))
+				"#,
+				cluster::TABLE
+			),
+			vec![network_id.into(), to_json(cluster_ids)?],
+		)
+		.await?
+		.iter()
+		.map(get_cluster)
+		.collect()
+	}
+
+	async fn get_clusters_after_block_height(
+		&self,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+	) -> Result<Vec<Cluster>> {
+		self.query(
+			&format!("SELECT * FROM {} WHERE network_id = ? AND block_height > ?", cluster::TABLE),
+			vec![network_id.into(), (block_height as i64).into()],
+		)
+		.await?
+		.iter()
+		.map(get_cluster)
+		.collect()
+	}
+
+	async fn get_clusters_by_member(
+		&self,
+		network_id: PrimaryId,
+		address: &str,
+	) -> Result<Vec<Cluster>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT *
+					FROM {}
+					WHERE network_id = ? AND cluster_id IN (
+						SELECT cluster_id
+						FROM {}
+						WHERE network_id = ? AND address = ?
+					)
+				"#,
+				cluster::TABLE,
+				cluster::TABLE
+			),
+			vec![network_id.into(), network_id.into(), address.into()],
+		)
+		.await?
+		.iter()
+		.map(get_cluster)
+		.collect()
+	}
+
+	async fn create_reconciliations(&self, models: Rows<Reconciliation>) -> Result<()> {
+		let rows = models
+			.map(|m| {
+				vec![
+					(m.network_id as i64).into(),
+					(m.module_id as i32).into(),
+					(m.block_height as i64).into(),
+					m.address.into(),
+					m.asset_address.into(),
+					to_text(m.indexed_balance),
+					to_text(m.live_balance),
+					(m.created_at as i64).into(),
+				]
+			})
+			.map(Ok);
+
+		self.insert(
+			reconciliation::TABLE,
+			&[
+				"network_id",
+				"module_id",
+				"block_height",
+				"address",
+				"asset_address",
+				"indexed_balance",
+				"live_balance",
+				"created_at",
+			],
+			rows,
+			"",
+		)
+		.await?;
+
+		self.execute(
+			&format!("DELETE FROM {} WHERE created_at < ?", reconciliation::TABLE),
+			vec![(utils::now().timestamp() - RECONCILIATIONS_TTL).into()],
+		)
+		.await
+	}
+
+	async fn get_reconciliation_stats(&self, since: u32) -> Result<Vec<ReconciliationStats>> {
+		self.query(
+			&format!(
+				r#"
+					SELECT
+						network_id,
+						module_id,
+						COUNT(*) AS checked,
+						COUNT(*) FILTER (WHERE indexed_balance != live_balance) AS drifted,
+						MAX(created_at) AS checked_at
+					FROM {}
+					WHERE created_at >= ?
+					GROUP BY network_id, module_id
+					ORDER BY network_id, module_id
+				"#,
+				reconciliation::TABLE
+			),
+			vec![(since as i64).into()],
+		)
+		.await?
+		.iter()
+		.map(|r| {
+			Ok(ReconciliationStats {
+				network_id: r.try_get::<i64>("", "network_id")? as u64,
+				module_id: r.try_get::<i32>("", "module_id")? as u16,
+				checked: r.try_get::<i64>("", "checked")? as u64,
+				drifted: r.try_get::<i64>("", "drifted")? as u64,
+				checked_at: r.try_get::<i64>("", "checked_at")? as u32,
+			})
+		})
+		.collect()
+	}
+}
+
+// extra conditions for `filters`, with values appended to `values` (which already holds the
+// addresses links are being fetched for)
+fn get_link_conditions(filters: &LinkFilters, values: &mut Vec<Value>) -> String {
+	let mut ret = String::new();
+
+	if let Some(min_hops) = filters.min_hops {
+		ret.push_str(" AND json_array_length(transfer_uuids) >= ?");
+		values.push((min_hops as i64).into());
+	}
+	if let Some(max_hops) = filters.max_hops {
+		ret.push_str(" AND json_array_length(transfer_uuids) <= ?");
+		values.push((max_hops as i64).into());
+	}
+	if let Some(since) = filters.since {
+		ret.push_str(" AND created_at >= ?");
+		values.push((since as i64).into());
+	}
+	if let Some(until) = filters.until {
+		ret.push_str(" AND created_at <= ?");
+		values.push((until as i64).into());
+	}
+	if let Some((asset_address, min_amount)) = &filters.min_amount {
+		ret.push_str(&format!(
+			r#"
+				AND NOT EXISTS (
+					SELECT 1
+					FROM json_each({}.transfer_uuids) AS u
+					JOIN {} AS t ON t.uuid = u.value
+					WHERE t.asset_address != ? OR t.relative_amount < ?
+				)
+			"#,
+			link::TABLE,
+			transfer::TABLE
+		));
+		values.push(asset_address.clone().into());
+		values.push(to_text(*min_amount));
+	}
+
+	ret
+}
+
+// shared by links and downstream links
+fn get_link_row(
+	network_id: u64,
+	block_height: u64,
+	from_address: String,
+	to_address: String,
+	transfer_uuids: Vec<LinkUuid>,
+	created_at: u32,
+) -> Result<Vec<Value>> {
+	Ok(vec![
+		(network_id as i64).into(),
+		(block_height as i64).into(),
+		from_address.into(),
+		to_address.into(),
+		to_json(transfer_uuids.into_iter().map(|u| u.0).collect::<Vec<Uuid>>())?,
+		(created_at as i64).into(),
+	])
+}
+
+fn to_json<T: serde::Serialize>(value: T) -> Result<Value> {
+	Ok(serde_json::to_string(&value)?.into())
+}
+
+fn to_text(value: U256) -> Value {
+	format!("{:0>U256_DIGITS$}", value.to_string()).into()
+}
+
+fn get_u256(r: &QueryResult, column: &str) -> Result<U256> {
+	Ok(U256::from_dec_str(&r.try_get::<String>("", column)?)?)
+}
+
+fn get_uuid(r: &QueryResult, column: &str) -> Result<Uuid> {
+	Ok(Uuid::parse_str(&r.try_get::<String>("", column)?)?)
+}
+
+fn get_transfer(r: &QueryResult) -> Result<Transfer> {
+	Ok(Transfer {
+		uuid: get_uuid(r, "uuid")?,
+		module_id: r.try_get::<i32>("", "module_id")? as u16,
+		network_id: r.try_get::<i64>("", "network_id")? as u64,
+		block_height: r.try_get::<i64>("", "block_height")? as u64,
+		tx_hash: r.try_get("", "tx_hash")?,
+		from_address: r.try_get("", "from_address")?,
+		to_address: r.try_get("", "to_address")?,
+		asset_address: r.try_get("", "asset_address")?,
+		token_id: r.try_get("", "token_id")?,
+		relative_amount: get_u256(r, "relative_amount")?,
+		batch_amount: get_u256(r, "batch_amount")?,
+		created_at: r.try_get::<i64>("", "created_at")? as u32,
+	})
+}
+
+fn get_link(r: &QueryResult) -> Result<Link> {
+	Ok(Link {
+		network_id: r.try_get::<i64>("", "network_id")? as u64,
+		block_height: r.try_get::<i64>("", "block_height")? as u64,
+		from_address: r.try_get("", "from_address")?,
+		to_address: r.try_get("", "to_address")?,
+		transfer_uuids: serde_json::from_str::<Vec<Uuid>>(
+			&r.try_get::<String>("", "transfer_uuids")?,
+		)?
+		.into_iter()
+		.map(LinkUuid)
+		.collect(),
+		created_at: r.try_get::<i64>("", "created_at")? as u32,
+	})
+}
+
+fn get_downstream_link(r: &QueryResult) -> Result<DownstreamLink> {
+	let link = get_link(r)?;
+
+	Ok(DownstreamLink {
+		network_id: link.network_id,
+		block_height: link.block_height,
+		from_address: link.from_address,
+		to_address: link.to_address,
+		transfer_uuids: link.transfer_uuids,
+		created_at: link.created_at,
+	})
+}
+
+fn get_relation(r: &QueryResult) -> Result<Relation> {
+	Ok(Relation {
+		uuid: get_uuid(r, "uuid")?,
+		module_id: r.try_get::<i32>("", "module_id")? as u16,
+		network_id: r.try_get::<i64>("", "network_id")? as u64,
+		block_height: r.try_get::<i64>("", "block_height")? as u64,
+		tx_hash: r.try_get("", "tx_hash")?,
+		from_address: r.try_get("", "from_address")?,
+		to_address: r.try_get("", "to_address")?,
+		reason: r.try_get::<i32>("", "reason")? as u16,
+		created_at: r.try_get::<i64>("", "created_at")? as u32,
+	})
+}
+
+fn get_cluster(r: &QueryResult) -> Result<Cluster> {
+	Ok(Cluster {
+		network_id: r.try_get::<i64>("", "network_id")? as u64,
+		address: r.try_get("", "address")?,
+		cluster_id: r.try_get("", "cluster_id")?,
+		block_height: r.try_get::<i64>("", "block_height")? as u64,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		chain::ModuleId,
+		warehouse::{migrations::Migrator, Driver},
+	};
+
+	// fresh warehouse in a temp file, with all migrations applied
+	async fn get_warehouse() -> Result<SQLite> {
+		let path = std::env::temp_dir().join(format!("barreleye-test-{}.db", Uuid::new_v4()));
+		let warehouse = SQLite::new(&format!("file://{}", path.display())).await?;
+
+		warehouse.create_migrations_table().await?;
+		for migration in Migrator::migrations().into_iter() {
+			warehouse.apply_migration(migration.name(), migration.up(&Driver::SQLite)).await?;
+		}
+
+		Ok(warehouse)
+	}
+
+	#[tokio::test]
+	async fn test_round_trip() -> Result<()> {
+		let warehouse = get_warehouse().await?;
+
+		// transfers (re-inserts are ignored)
+		let transfer = |block_height, from_address, to_address, amount: U256, created_at| {
+			let tx_hash = format!("0x{block_height}");
+			Transfer::new(
+				ModuleId::EvmTransfer,
+				1,
+				block_height,
+				&tx_hash,
+				0,
+				from_address,
+				to_address,
+				None,
+				amount,
+				amount,
+				created_at,
+			)
+		};
+
+		let big = U256::exp10(30);
+		let t1 = transfer(10, "a", "b", big, 100);
+		let t2 = transfer(11, "b", "c", 9.into(), 200);
+		let t3 = transfer(12, "c", "d", 100.into(), 300);
+		for _ in 0..2 {
+			warehouse
+				.create_transfers(Box::new(vec![t1.clone(), t2.clone(), t3.clone()].into_iter()))
+				.await?;
+		}
+
+		assert_eq!(warehouse.get_transfers_by_block_range(1, (0, 100)).await?.len(), 3);
+		assert_eq!(warehouse.get_first_transfer_by_source(1, "a").await?, Some(t1.clone()));
+		assert_eq!(warehouse.get_transfers_by_uuids(vec![t1.uuid]).await?, vec![t1.clone()]);
+		assert_eq!(
+			warehouse.get_transfers_by_targets(1, vec!["c".into(), "d".into()], (0, 11)).await?,
+			vec![t2.clone()]
+		);
+
+		let mut transfers = warehouse.get_transfers_by_addresses(1, vec!["b".into()], 11).await?;
+		transfers.sort_by_key(|t| t.block_height);
+		assert_eq!(transfers, vec![t1.clone(), t2.clone()]);
+
+		// amounts & balances (re-inserts are ignored, equal amounts in one tx are not)
+		let amount = |block_height, tx_hash, position, amount_in, amount_out, created_at| {
+			Amount::new(
+				ModuleId::EvmTransfer,
+				1,
+				block_height,
+				tx_hash,
+				position,
+				"b",
+				None,
+				amount_in,
+				amount_out,
+				created_at,
+			)
+		};
+		let amounts = vec![
+			amount(10, "0x10", 0, big, 0.into(), 100),
+			amount(11, "0x11", 0, 0.into(), 9.into(), 200),
+			amount(12, "0x12", 0, 100.into(), 0.into(), 300),
+			amount(12, "0x12", 1, 100.into(), 0.into(), 300),
+		];
+		for _ in 0..2 {
+			warehouse.create_amounts(Box::new(amounts.clone().into_iter())).await?;
+		}
+
+		let received =
+			warehouse.get_amounts_received_by_addresses(vec!["b".into()], (0, 200)).await?;
+		assert_eq!(received.get(&(1, "".to_string())), Some(&big));
+
+		let balances = warehouse.get_balances_by_addresses(vec!["b".into()]).await?;
+		assert_eq!(balances.len(), 1);
+		assert_eq!(balances[0].balance, big - U256::from(9) + U256::from(200));
+
+		// link filters (amounts have to compare as numbers, not as text: 100 > 9)
+		let link = |to_address: &str, transfers: Vec<&Transfer>| {
+			let uuids = transfers.iter().map(|t| LinkUuid(t.uuid)).collect::<Vec<LinkUuid>>();
+			let last = transfers.last().unwrap();
+			Link::new(1, last.block_height, "a", to_address, uuids, last.created_at)
+		};
+		let (l1, l2, l3) =
+			(link("b", vec![&t1]), link("c", vec![&t1, &t2]), link("d", vec![&t1, &t2, &t3]));
+		warehouse
+			.create_links(Box::new(vec![l1.clone(), l2.clone(), l3.clone()].into_iter()))
+			.await?;
+
+		let get_links = |filters: LinkFilters| {
+			let warehouse = &warehouse;
+			async move {
+				let mut links = warehouse
+					.get_links_by_addresses(vec!["b".into(), "c".into(), "d".into()], &filters)
+					.await?;
+				links.sort_by_key(|l| l.block_height);
+				Ok::<_, eyre::ErrReport>(links)
+			}
+		};
+
+		assert_eq!(
+			get_links(LinkFilters::default()).await?,
+			vec![l1.clone(), l2.clone(), l3.clone()]
+		);
+		assert_eq!(
+			get_links(LinkFilters { min_hops: Some(2), max_hops: Some(2), ..Default::default() })
+				.await?,
+			vec![l2.clone()]
+		);
+		assert_eq!(
+			get_links(LinkFilters { since: Some(150), until: Some(250), ..Default::default() })
+				.await?,
+			vec![l2.clone()]
+		);
+		assert_eq!(
+			get_links(LinkFilters {
+				min_amount: Some(("".into(), 9.into())),
+				..Default::default()
+			})
+			.await?,
+			vec![l1.clone(), l2.clone(), l3.clone()]
+		);
+		assert_eq!(
+			get_links(LinkFilters {
+				min_amount: Some(("".into(), 10.into())),
+				..Default::default()
+			})
+			.await?,
+			vec![l1.clone()]
+		);
+		assert!(get_links(LinkFilters {
+			min_amount: Some(("0xtoken".into(), 1.into())),
+			..Default::default()
+		})
+		.await?
+		.is_empty());
+
+		// relations & clusters
+		let relation = |block_height, from_address, to_address| {
+			Relation::new(
+				ModuleId::BitcoinTransfer,
+				1,
+				block_height,
+				&format!("0x{block_height}"),
+				from_address,
+				to_address,
+				RelationReason::CommonInputOwnership,
+				0,
+			)
+		};
+		let (r1, r2) = (relation(10, "a", "b"), relation(12, "c", "b"));
+		warehouse.create_relations(Box::new(vec![r1.clone(), r2.clone()].into_iter())).await?;
+		assert_eq!(
+			warehouse
+				.get_relations_by_addresses(
+					1,
+					RelationReason::CommonInputOwnership,
+					vec!["b".into()],
+					11
+				)
+				.await?,
+			vec![r1.clone()]
+		);
+
+		let clusters = vec![Cluster::new(1, "a", "a", 10), Cluster::new(1, "c", "a", 12)];
+		warehouse.create_clusters(Box::new(clusters.clone().into_iter())).await?;
+		assert_eq!(
+			warehouse.get_clusters_after_block_height(1, 10).await?,
+			vec![clusters[1].clone()]
+		);
+
+		// rollback deletes
+		for table in [transfer::TABLE, amount::TABLE, link::TABLE, relation::TABLE, cluster::TABLE]
+		{
+			warehouse.delete_all_after_block_height(table, 1, 10).await?;
+		}
+		assert_eq!(warehouse.get_transfers_by_block_range(1, (0, 100)).await?, vec![t1.clone()]);
+		assert_eq!(warehouse.get_balances_by_addresses(vec!["b".into()]).await?[0].balance, big);
+		assert_eq!(get_links(LinkFilters::default()).await?, vec![l1.clone()]);
+		assert_eq!(
+			warehouse.get_clusters_by_addresses(vec!["a".into(), "c".into()]).await?,
+			vec![clusters[0].clone()]
+		);
+
+		fs::remove_file(&warehouse.path)?;
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_get_applied_migrations() -> Result<()> {
+		let path = std::env::temp_dir().join(format!("barreleye-test-{}.db", Uuid::new_v4()));
+		let warehouse = SQLite::new(&format!("file://{}", path.display())).await?;
+
+		// a dry run doesn't create the migrations table
+		assert!(warehouse.get_applied_migrations().await?.is_empty());
+		assert!(warehouse
+			.query("SELECT name FROM sqlite_master WHERE type = 'table'", vec![])
+			.await?
+			.is_empty());
+
+		warehouse.create_migrations_table().await?;
+		warehouse.apply_migration("m1", vec![]).await?;
+		assert_eq!(warehouse.get_applied_migrations().await?, vec!["m1".to_string()]);
+
+		fs::remove_file(&warehouse.path)?;
+
+		Ok(())
+	}
+}
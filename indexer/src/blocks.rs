@@ -1,8 +1,8 @@
 use console::style;
-use eyre::{ErrReport, Result};
+use eyre::{eyre, ErrReport, Result};
 use serde_json::{from_value as json_parse, json};
 use std::{
-	collections::{HashMap, HashSet},
+	collections::{HashMap, HashSet, VecDeque},
 	sync::{
 		atomic::{AtomicBool, Ordering},
 		Arc,
@@ -17,11 +17,73 @@ use tokio::{
 
 use crate::{IndexType, Indexer, NetworkParams, Pipe};
 use barreleye_common::{
-	chain::WarehouseData,
-	models::{Config, ConfigKey},
-	BlockHeight,
+	chain::{BoxedChain, WarehouseData},
+	models::{
+		Address, Amount, Cluster, Config, ConfigKey, DownstreamLink, Link, PrimaryId, Relation,
+		Transfer,
+	},
+	BlockHeight, Event,
 };
 
+// how far back a chain reorganization can be detected and rolled back
+const MAX_REORG_DEPTH: usize = 128;
+
+struct RecentBlocks {
+	data: VecDeque<(BlockHeight, String)>,
+}
+
+impl RecentBlocks {
+	pub fn new(hashes: Vec<(BlockHeight, String)>, last_read_block: BlockHeight) -> Self {
+		// hashes past the last read block belong to blocks that will be processed again
+		let data = hashes.into_iter().filter(|(block_height, _)| *block_height <= last_read_block);
+		Self { data: data.collect() }
+	}
+
+	pub fn get_all(&self) -> Vec<(BlockHeight, String)> {
+		self.data.clone().into_iter().collect()
+	}
+
+	pub fn is_parent(&self, block_height: BlockHeight, hash: &str) -> bool {
+		match self.data.back() {
+			Some((last_block_height, last_hash)) if *last_block_height == block_height => {
+				last_hash == hash
+			}
+			_ => true,
+		}
+	}
+
+	pub fn push(&mut self, block_height: BlockHeight, hash: String) {
+		self.data.push_back((block_height, hash));
+		while self.data.len() > MAX_REORG_DEPTH {
+			self.data.pop_front();
+		}
+	}
+
+	// drop hashes past the last block `chain` still agrees on and return its height
+	pub async fn rollback(&mut self, chain: &BoxedChain) -> Result<BlockHeight> {
+		let fork_block_height = self.find_fork_block_height(chain).await?;
+		self.data.retain(|(h, _)| *h <= fork_block_height);
+
+		Ok(fork_block_height)
+	}
+
+	async fn find_fork_block_height(&self, chain: &BoxedChain) -> Result<BlockHeight> {
+		for (block_height, hash) in self.data.iter().rev() {
+			if let Some(block_header) = chain.get_block_header(*block_height).await? {
+				if block_header.hash == *hash {
+					return Ok(*block_height);
+				}
+			}
+		}
+
+		Err(eyre!(
+			"{}: could not find a common ancestor within the last {} block(s)",
+			chain.get_network().name,
+			self.data.len()
+		))
+	}
+}
+
 impl Indexer {
 	pub async fn index_blocks(&self, mut networks_updated: Receiver<SystemTime>) -> Result<()> {
 		let mut warehouse_data = WarehouseData::new();
@@ -80,9 +142,14 @@ impl Indexer {
 					}
 				};
 
+				// only index blocks that have enough confirmations
+				let confirmations = chain.get_network().confirmations as BlockHeight;
+				let block_height = block_height.saturating_sub(confirmations);
+
 				// if first time, split up network into chunks for faster initial syncing
 				let chunks = num_cpus::get();
 				if last_read_block == 0 &&
+					block_height > 1 &&
 					chunks > 0 && Config::get_many_by_keywords::<_, (BlockHeight, BlockHeight)>(
 					self.app.db(),
 					vec![format!("chunk_sync_n{nid}")],
@@ -154,11 +221,12 @@ impl Indexer {
 				);
 
 				// push all fast-sync block ranges
-				for (config_key, block_range) in Config::get_many_by_keywords::<
-					_,
-					(BlockHeight, BlockHeight),
-				>(self.app.db(), vec![format!("chunk_sync_n{nid}")])
-				.await?
+				for (config_key, block_range) in
+					Config::get_many_by_keywords::<_, (BlockHeight, BlockHeight)>(
+						self.app.db(),
+						vec![format!("chunk_sync_n{nid}")],
+					)
+					.await?
 				{
 					network_params_map.insert(
 						config_key,
@@ -218,6 +286,7 @@ impl Indexer {
 			}
 
 			let (pipe_sender, mut pipe_receiver) = mpsc::channel(network_params_map.len());
+			let (rollback_sender, mut rollback_receiver) = mpsc::channel(network_params_map.len());
 			let (abort_sender, _) = broadcast::channel(network_params_map.len());
 			let should_keep_going = Arc::new(AtomicBool::new(true));
 			let mut receipts = HashMap::<ConfigKey, Sender<()>>::new();
@@ -242,6 +311,7 @@ impl Indexer {
 					let mut pipe = Pipe::new(
 						config_key,
 						pipe_sender.clone(),
+						rollback_sender.clone(),
 						receipt,
 						abort_sender.subscribe(),
 					);
@@ -253,6 +323,21 @@ impl Indexer {
 						let mut block_height = network_params.range.0;
 						let block_height_max = network_params.range.1;
 
+						// only the tail-sync thread keeps track of block hashes (reorg detection)
+						let mut recent_blocks = match config_key {
+							ConfigKey::IndexerTailSync(_) => Some(RecentBlocks::new(
+								Config::get::<_, Vec<(BlockHeight, String)>>(
+									&db,
+									ConfigKey::IndexerTailHashes(nid),
+								)
+								.await?
+								.map(|v| v.value)
+								.unwrap_or_default(),
+								block_height,
+							)),
+							_ => None,
+						};
+
 						let config_value = |block_height| match config_key {
 							ConfigKey::IndexerTailSync(_) => json!(block_height),
 							ConfigKey::IndexerChunkSync(_, _) |
@@ -270,7 +355,7 @@ impl Indexer {
 									if !warehouse_data.is_empty() {
 										pipe.push(
 											config_value(block_height),
-											warehouse_data.clone(),
+											warehouse_data.take(),
 										)
 										.await?;
 									}
@@ -285,7 +370,11 @@ impl Indexer {
 											.map(|v| v.value)
 											.unwrap_or(0);
 
-									if block_height + 1 > saved_block_height {
+									// stay `confirmations` blocks behind the head
+									let confirmations =
+										chain.get_network().confirmations as BlockHeight;
+
+									if block_height + confirmations + 1 > saved_block_height {
 										let latest_block_height = chain.get_block_height().await?;
 
 										if latest_block_height > saved_block_height {
@@ -295,7 +384,9 @@ impl Indexer {
 												latest_block_height,
 											)
 											.await?;
-										} else {
+										}
+
+										if block_height + confirmations + 1 > latest_block_height {
 											let timeout = chain.get_network().block_time_ms;
 											sleep(Duration::from_millis(timeout as u64)).await;
 											continue;
@@ -305,6 +396,34 @@ impl Indexer {
 								_ => {}
 							}
 
+							// make sure the next block builds on top of the last processed one;
+							// if it does not, roll back to the fork and re-index canonical blocks
+							let mut block_header = None;
+							if let Some(recent_blocks) = recent_blocks.as_mut() {
+								block_header = chain.get_block_header(block_height + 1).await?;
+
+								if let Some(header) = &block_header {
+									if !recent_blocks.is_parent(block_height, &header.parent_hash) {
+										let fork_block_height =
+											recent_blocks.rollback(&chain).await?;
+
+										warehouse_data.truncate(nid, fork_block_height);
+
+										Config::set::<_, Vec<(BlockHeight, String)>>(
+											&db,
+											ConfigKey::IndexerTailHashes(nid),
+											recent_blocks.get_all(),
+										)
+										.await?;
+
+										pipe.rollback(fork_block_height).await?;
+										block_height = fork_block_height;
+
+										continue;
+									}
+								}
+							}
+
 							block_height += 1;
 
 							let is_done = tokio::select! {
@@ -321,10 +440,26 @@ impl Indexer {
 								},
 							};
 
+							if let (Some(recent_blocks), Some(header)) =
+								(recent_blocks.as_mut(), block_header)
+							{
+								if !is_done {
+									recent_blocks.push(block_height, header.hash);
+								}
+							}
+
 							if is_done || warehouse_data.len() > 100 {
-								pipe.push(config_value(block_height), warehouse_data.clone())
+								if let Some(recent_blocks) = &recent_blocks {
+									Config::set::<_, Vec<(BlockHeight, String)>>(
+										&db,
+										ConfigKey::IndexerTailHashes(nid),
+										recent_blocks.get_all(),
+									)
+									.await?;
+								}
+
+								pipe.push(config_value(block_height), warehouse_data.take())
 									.await?;
-								warehouse_data.clear();
 							}
 
 							if is_done {
@@ -339,6 +474,7 @@ impl Indexer {
 
 			// drop the original non-cloned
 			drop(pipe_sender);
+			drop(rollback_sender);
 
 			// process thread returns + their outputs
 			let abort = || -> Result<()> {
@@ -363,6 +499,26 @@ impl Indexer {
 							break;
 						}
 					}
+					Some((config_key, block_height)) = rollback_receiver.recv() => {
+						if let ConfigKey::IndexerTailSync(nid) = config_key {
+							let networks = self.app.networks.read().await;
+							let chain = networks[&nid].clone();
+
+							self.log(IndexType::Blocks, false, &format!(
+								"{} reorg detected; rolling back to block {}",
+								style(chain.get_network().name).bold(),
+								style(self.format_number(block_height as usize)?).bold(),
+							));
+
+							self.rollback_blocks(nid, block_height, &mut warehouse_data).await?;
+							config_key_map.insert(config_key, json!(block_height));
+						}
+
+						// release thread so it can re-index from the fork
+						if let Some(receipt) = receipts.get(&config_key) {
+							receipt.send(()).await.unwrap();
+						}
+					}
 					Some((config_key, config_value, new_data)) = pipe_receiver.recv() => {
 						if !self.app.is_leading() {
 							abort()?;
@@ -391,7 +547,7 @@ impl Indexer {
 						//
 						// but that's still ok because commits will happen either when
 						// buffer fills up or enough time has passed
-						if warehouse_data.should_commit(false) {
+						if warehouse_data.should_commit(false, &self.app.settings) {
 							let mut updated_network_ids = HashSet::new();
 
 								self.log(IndexType::Blocks, true, &format!(
@@ -400,11 +556,18 @@ impl Indexer {
 								));
 
 							// push to warehouse
+							//
+							// @NOTE markers only move forward once data is in, but tables are
+							// written one by one, so if the process dies in between the same
+							// blocks get re-indexed and written again. transfers that are already
+							// stored are skipped; duplicate amounts & relations are dropped by
+							// their unique key (or, in clickhouse, replaced on the next merge)
 							warehouse_data.commit(self.app.warehouse.clone()).await?;
 
-							// commit config marker updates
+							// commit config marker updates (all at once)
+							let tx = self.app.db_tx().await?;
 							for (config_key, config_value) in config_key_map.iter() {
-								let db = self.app.db();
+								let db = &tx;
 								let key = *config_key;
 								let value = config_value.clone();
 
@@ -465,9 +628,10 @@ impl Indexer {
 							for (config_key, _) in config_key_map.iter() {
 								if let ConfigKey::IndexerModuleSynced(nid, mid) = config_key {
 									let ck_block_range = ConfigKey::IndexerModuleSync(*nid, *mid);
-									Config::delete(self.app.db(), ck_block_range).await?;
+									Config::delete(&tx, ck_block_range).await?;
 								}
 							}
+							tx.commit().await?;
 
 							// reset config key markers
 							config_key_map.clear();
@@ -486,7 +650,8 @@ impl Indexer {
 								)
 								.await?
 								.map(|v| v.value)
-								.unwrap_or(0);
+								.unwrap_or(0)
+								.saturating_sub(chain.get_network().confirmations as BlockHeight);
 
 								if block_height == 0 {
 									scores.push(0.0);
@@ -546,6 +711,12 @@ impl Indexer {
 								)
 								.await?;
 
+								self.app.publish(Event::Progress {
+									network: chain.get_network().id,
+									confirmed_block_height: block_height,
+									sync: progress,
+								});
+
 								self.log(IndexType::Blocks, false, &format!(
 									"{} @ {:.4}%…",
 									style(chain.get_network().name).bold(),
@@ -558,4 +729,197 @@ impl Indexer {
 			}
 		}
 	}
+
+	async fn rollback_blocks(
+		&self,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+		warehouse_data: &mut WarehouseData,
+	) -> Result<()> {
+		let warehouse = &self.app.warehouse;
+
+		// drop whatever has not been committed yet
+		warehouse_data.truncate(network_id, block_height);
+
+		// cluster rows rewritten after the fork have lost their previous version, so they
+		// are rebuilt below once the orphaned rows are gone
+		let rolled_back_addresses =
+			Cluster::get_all_after_block_height(warehouse, network_id, block_height)
+				.await?
+				.into_iter()
+				.map(|c| c.address)
+				.collect::<Vec<String>>();
+
+		// delete orphaned data from warehouse
+		let (
+			transfers_deleted,
+			amounts_deleted,
+			relations_deleted,
+			links_deleted,
+			downstream_links_deleted,
+			clusters_deleted,
+		) = tokio::join!(
+			Transfer::delete_all_after_block_height(warehouse, network_id, block_height),
+			Amount::delete_all_after_block_height(warehouse, network_id, block_height),
+			Relation::delete_all_after_block_height(warehouse, network_id, block_height),
+			Link::delete_all_after_block_height(warehouse, network_id, block_height),
+			DownstreamLink::delete_all_after_block_height(warehouse, network_id, block_height),
+			Cluster::delete_all_after_block_height(warehouse, network_id, block_height),
+		);
+
+		transfers_deleted
+			.and(amounts_deleted)
+			.and(relations_deleted)
+			.and(links_deleted)
+			.and(downstream_links_deleted)
+			.and(clusters_deleted)?;
+
+		self.rebuild_clusters(network_id, block_height, rolled_back_addresses).await?;
+
+		// move tail-sync, upstream, downstream and cluster markers back to the fork
+		Config::set::<_, BlockHeight>(
+			self.app.db(),
+			ConfigKey::IndexerTailSync(network_id),
+			block_height,
+		)
+		.await?;
+
+		// (exact keys, since keyword lookups for network 1 would also match networks 10+)
+		let mut config_keys = vec![ConfigKey::IndexerClusterSync(network_id)];
+		for address in Address::get_all_by_network_ids(self.app.db(), network_id.into(), None)
+			.await?
+			.into_iter()
+		{
+			config_keys.push(ConfigKey::IndexerUpstreamSync(network_id, address.address_id));
+			config_keys.push(ConfigKey::IndexerDownstreamSync(network_id, address.address_id));
+		}
+
+		let sync_markers = Config::get_many::<_, BlockHeight>(self.app.db(), config_keys)
+			.await?
+			.into_iter()
+			.filter_map(|(config_key, hit)| {
+				(hit.value > block_height).then_some((config_key, block_height))
+			})
+			.collect::<HashMap<ConfigKey, BlockHeight>>();
+		if !sync_markers.is_empty() {
+			Config::set_many::<_, BlockHeight>(self.app.db(), sync_markers).await?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use async_trait::async_trait;
+	use barreleye_common::{
+		chain::{BlockHeader, ChainTrait, ModuleId},
+		models::Network,
+		RateLimiter,
+	};
+
+	// chain that only knows the hashes of its canonical blocks
+	struct StandIn {
+		hashes: HashMap<BlockHeight, String>,
+	}
+
+	#[async_trait]
+	impl ChainTrait for StandIn {
+		async fn connect(&mut self) -> Result<bool> {
+			Ok(true)
+		}
+
+		fn is_connected(&self) -> bool {
+			true
+		}
+
+		fn get_network(&self) -> Network {
+			Network::default()
+		}
+
+		fn get_rpc(&self) -> Option<String> {
+			None
+		}
+
+		fn get_module_ids(&self) -> Vec<ModuleId> {
+			vec![]
+		}
+
+		fn format_address(&self, address: &str) -> String {
+			address.to_string()
+		}
+
+		fn get_rate_limiter(&self) -> Option<Arc<RateLimiter>> {
+			None
+		}
+
+		async fn get_block_height(&self) -> Result<BlockHeight> {
+			Ok(self.hashes.keys().max().copied().unwrap_or_default())
+		}
+
+		async fn get_block_header(&self, block_height: BlockHeight) -> Result<Option<BlockHeader>> {
+			Ok(self.hashes.get(&block_height).map(|hash| BlockHeader {
+				hash: hash.clone(),
+				parent_hash: self.hashes.get(&(block_height - 1)).cloned().unwrap_or_default(),
+			}))
+		}
+
+		async fn process_block(
+			&self,
+			_block_height: BlockHeight,
+			_modules: Vec<ModuleId>,
+		) -> Result<Option<WarehouseData>> {
+			Ok(None)
+		}
+	}
+
+	fn hashes(
+		range: std::ops::RangeInclusive<BlockHeight>,
+		fork: &str,
+	) -> Vec<(BlockHeight, String)> {
+		range.map(|h| (h, format!("{fork}{h}"))).collect()
+	}
+
+	#[test]
+	fn test_recent_blocks() {
+		// persisted hashes past the last read block are dropped
+		let mut recent_blocks = RecentBlocks::new(hashes(1..=10, "a"), 8);
+		assert_eq!(recent_blocks.get_all(), hashes(1..=8, "a"));
+
+		// the next block has to build on top of the last one
+		assert!(recent_blocks.is_parent(8, "a8"));
+		assert!(!recent_blocks.is_parent(8, "b8"));
+		assert!(RecentBlocks::new(vec![], 0).is_parent(8, "b8"));
+
+		// only the last `MAX_REORG_DEPTH` hashes are kept
+		for (block_height, hash) in hashes(9..=200, "a") {
+			recent_blocks.push(block_height, hash);
+		}
+		assert_eq!(recent_blocks.get_all(), hashes(73..=200, "a"));
+	}
+
+	#[tokio::test]
+	async fn test_recent_blocks_rollback() -> Result<()> {
+		// the chain forked off after block 5
+		let chain: BoxedChain = Box::new(StandIn {
+			hashes: hashes(1..=5, "a").into_iter().chain(hashes(6..=10, "b")).collect(),
+		});
+
+		let mut recent_blocks = RecentBlocks::new(hashes(1..=8, "a"), 8);
+		assert!(!recent_blocks.is_parent(8, &chain.get_block_header(9).await?.unwrap().parent_hash));
+		assert_eq!(recent_blocks.rollback(&chain).await?, 5);
+		assert_eq!(recent_blocks.get_all(), hashes(1..=5, "a"));
+
+		// re-indexed blocks extend what's left
+		recent_blocks.push(6, "b6".to_string());
+		assert!(recent_blocks.is_parent(6, &chain.get_block_header(7).await?.unwrap().parent_hash));
+
+		// nothing in common
+		let mut recent_blocks = RecentBlocks::new(hashes(1..=8, "c"), 8);
+		assert!(recent_blocks.rollback(&chain).await.is_err());
+		assert_eq!(recent_blocks.get_all(), hashes(1..=8, "c"));
+
+		Ok(())
+	}
 }
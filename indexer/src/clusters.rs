@@ -0,0 +1,293 @@
+use eyre::Result;
+use std::{
+	cmp::{self, Ordering},
+	collections::{HashMap, HashSet},
+};
+use tokio::time::{sleep, Duration};
+
+use crate::{IndexType, Indexer};
+use barreleye_common::{
+	models::{Cluster, Config, ConfigKey, PrimaryId, Relation, RelationReason},
+	BlockHeight,
+};
+
+const BLOCKS_PER_LOOP: BlockHeight = 1_000;
+
+// disjoint sets of addresses, where the smallest address of each set is its root
+#[derive(Default)]
+struct UnionFind {
+	parents: HashMap<String, String>,
+}
+
+impl UnionFind {
+	pub fn find(&mut self, address: &str) -> String {
+		if !self.parents.contains_key(address) {
+			self.parents.insert(address.to_string(), address.to_string());
+			return address.to_string();
+		}
+
+		let mut root = address.to_string();
+		while let Some(parent) = self.parents.get(&root).filter(|&p| *p != root) {
+			root = parent.clone();
+		}
+
+		// compress the path, so next lookups are quick
+		let mut current = address.to_string();
+		while current != root {
+			let parent = self.parents.insert(current, root.clone()).unwrap_or_else(|| root.clone());
+			current = parent;
+		}
+
+		root
+	}
+
+	pub fn union(&mut self, a: &str, b: &str) {
+		let (root_a, root_b) = (self.find(a), self.find(b));
+
+		match root_a.cmp(&root_b) {
+			Ordering::Less => {
+				self.parents.insert(root_b, root_a);
+			}
+			Ordering::Greater => {
+				self.parents.insert(root_a, root_b);
+			}
+			Ordering::Equal => {}
+		}
+	}
+
+	pub fn get_addresses(&self) -> Vec<String> {
+		self.parents.keys().cloned().collect()
+	}
+}
+
+impl Indexer {
+	pub async fn index_clusters(&self) -> Result<()> {
+		let mut started_indexing = false;
+
+		loop {
+			if !self.app.is_leading() {
+				if started_indexing {
+					self.log(IndexType::Clusters, false, "Stopping…");
+				}
+
+				started_indexing = false;
+				sleep(Duration::from_secs(1)).await;
+				continue;
+			}
+
+			if !started_indexing {
+				started_indexing = true;
+				self.log(IndexType::Clusters, false, "Starting…");
+			}
+
+			let block_height_map = self.get_synced_block_heights().await?;
+			if block_height_map.is_empty() {
+				self.log(IndexType::Clusters, true, "No fully-synced active networks. Waiting…");
+				sleep(Duration::from_secs(5)).await;
+				continue;
+			}
+
+			// marker to test whether we're all caught up
+			let mut is_at_the_tip = true;
+
+			for (network_id, latest_block_height) in block_height_map.into_iter() {
+				let config_key = ConfigKey::IndexerClusterSync(network_id);
+				let block_height = Config::get::<_, BlockHeight>(self.app.db(), config_key)
+					.await?
+					.map_or(0, |hit| hit.value);
+
+				if block_height >= latest_block_height {
+					continue;
+				}
+
+				let min_block_height = block_height + 1;
+				let max_block_height =
+					cmp::min(block_height + BLOCKS_PER_LOOP, latest_block_height);
+
+				if max_block_height != latest_block_height {
+					is_at_the_tip = false;
+				}
+
+				let clusters =
+					self.get_clusters(network_id, (min_block_height, max_block_height)).await?;
+				if !clusters.is_empty() {
+					self.log(
+						IndexType::Clusters,
+						true,
+						&format!(
+							"Pushing {} clustered address(es) to warehouse",
+							self.format_number(clusters.len())?,
+						),
+					);
+
+					Cluster::create_many(&self.app.warehouse, clusters).await?;
+				}
+
+				Config::set::<_, BlockHeight>(self.app.db(), config_key, max_block_height).await?;
+			}
+
+			if is_at_the_tip {
+				sleep(Duration::from_secs(1)).await;
+			}
+		}
+	}
+
+	// re-derive the rows of `addresses` as of `block_height` from the relations that survived a
+	// rollback (merges replace rows, so the versions these addresses had before are gone)
+	pub async fn rebuild_clusters(
+		&self,
+		network_id: PrimaryId,
+		block_height: BlockHeight,
+		addresses: Vec<String>,
+	) -> Result<()> {
+		let warehouse = &self.app.warehouse;
+		if addresses.is_empty() {
+			return Ok(());
+		}
+
+		// addresses without a surviving relation were not clustered before, so they're left out
+		let mut union_find = UnionFind::default();
+		for relation in Relation::get_all_by_addresses(
+			warehouse,
+			network_id,
+			RelationReason::CommonInputOwnership,
+			addresses,
+			block_height,
+		)
+		.await?
+		.into_iter()
+		{
+			union_find.union(&relation.from_address, &relation.to_address);
+		}
+
+		let addresses = union_find.get_addresses();
+		if addresses.is_empty() {
+			return Ok(());
+		}
+
+		// neighbors that kept their rows still point at the cluster everyone belonged to
+		let existing_clusters = Cluster::get_all_by_addresses(warehouse, addresses)
+			.await?
+			.into_iter()
+			.filter(|c| c.network_id == network_id as u64 && c.block_height <= block_height)
+			.map(|c| (c.address, c.cluster_id))
+			.collect::<HashMap<String, String>>();
+		for (address, cluster_id) in existing_clusters.iter() {
+			union_find.union(address, cluster_id);
+		}
+
+		let clusters = union_find
+			.get_addresses()
+			.into_iter()
+			.filter_map(|address| {
+				let cluster_id = union_find.find(&address);
+				(existing_clusters.get(&address) != Some(&cluster_id))
+					.then(|| Cluster::new(network_id, &address, &cluster_id, block_height))
+			})
+			.collect::<Vec<Cluster>>();
+		if !clusters.is_empty() {
+			Cluster::create_many(warehouse, clusters).await?;
+		}
+
+		Ok(())
+	}
+
+	// new & updated cluster rows after applying co-spend relations from a block range
+	async fn get_clusters(
+		&self,
+		network_id: PrimaryId,
+		(min_block_height, max_block_height): (BlockHeight, BlockHeight),
+	) -> Result<Vec<Cluster>> {
+		let warehouse = &self.app.warehouse;
+		let mut union_find = UnionFind::default();
+
+		for relation in Relation::get_all_by_block_range(
+			warehouse,
+			network_id,
+			RelationReason::CommonInputOwnership,
+			(min_block_height, max_block_height),
+		)
+		.await?
+		.into_iter()
+		{
+			union_find.union(&relation.from_address, &relation.to_address);
+		}
+
+		let addresses = union_find.get_addresses();
+		if addresses.is_empty() {
+			return Ok(vec![]);
+		}
+
+		// pull in the clusters these addresses already belong to
+		let existing_clusters = Cluster::get_all_by_addresses(warehouse, addresses)
+			.await?
+			.into_iter()
+			.filter(|c| c.network_id == network_id as u64)
+			.map(|c| (c.address, c.cluster_id))
+			.collect::<HashMap<String, String>>();
+		for (address, cluster_id) in existing_clusters.iter() {
+			union_find.union(address, cluster_id);
+		}
+
+		// clusters that were merged into another one need all of their members moved over
+		let merged_cluster_ids = existing_clusters
+			.values()
+			.filter(|cluster_id| union_find.find(cluster_id) != **cluster_id)
+			.cloned()
+			.collect::<HashSet<String>>();
+		let merged_members = match merged_cluster_ids.is_empty() {
+			true => vec![],
+			_ => {
+				Cluster::get_all_by_cluster_ids(
+					warehouse,
+					network_id,
+					merged_cluster_ids.into_iter().collect(),
+				)
+				.await?
+			}
+		};
+
+		let mut ret = HashMap::new();
+		for address in union_find.get_addresses().into_iter() {
+			let cluster_id = union_find.find(&address);
+			if existing_clusters.get(&address) != Some(&cluster_id) {
+				ret.insert(
+					address.clone(),
+					Cluster::new(network_id, &address, &cluster_id, max_block_height),
+				);
+			}
+		}
+		for member in merged_members.into_iter() {
+			let cluster_id = union_find.find(&member.cluster_id);
+			ret.insert(
+				member.address.clone(),
+				Cluster::new(network_id, &member.address, &cluster_id, max_block_height),
+			);
+		}
+
+		Ok(ret.into_values().collect())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_union_find() {
+		let mut union_find = UnionFind::default();
+
+		union_find.union("d", "c");
+		union_find.union("e", "f");
+		assert_eq!(union_find.find("d"), "c");
+		assert_eq!(union_find.find("f"), "e");
+
+		union_find.union("f", "d");
+		union_find.union("b", "z");
+		for address in ["c", "d", "e", "f"] {
+			assert_eq!(union_find.find(address), "c");
+		}
+		assert_eq!(union_find.find("z"), "b");
+		assert_eq!(union_find.find("x"), "x");
+	}
+}
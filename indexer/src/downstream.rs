@@ -0,0 +1,340 @@
+use console::style;
+use eyre::{ErrReport, Result};
+use std::{
+	cmp,
+	collections::{HashMap, HashSet},
+	time::SystemTime,
+};
+use tokio::{
+	sync::watch::Receiver,
+	task::JoinSet,
+	time::{sleep, Duration},
+};
+
+use crate::{IndexType, Indexer};
+use barreleye_common::{
+	chain::WarehouseData,
+	models::{
+		Address, Config, ConfigKey, DownstreamLink, LinkUuid, PrimaryId, PrimaryIds, Transfer,
+	},
+	BlockHeight,
+};
+
+const BLOCKS_PER_LOOP: BlockHeight = 10;
+const MAX_ADDRESSES_PER_JOIN_SET: usize = 100;
+
+// hot wallets & exchanges fan out quickly, so only the most recent branches of each hop are
+// followed further
+const MAX_BRANCHES_PER_HOP: usize = 1_000;
+
+// a chain of transfers that ends at the entity address
+#[derive(Clone)]
+struct Branch {
+	block_height: BlockHeight,
+	transfer_uuids: Vec<LinkUuid>,
+	link_block_height: BlockHeight,
+	link_created_at: u32,
+}
+
+impl Indexer {
+	pub async fn index_downstream(&self, mut networks_updated: Receiver<SystemTime>) -> Result<()> {
+		let mut warehouse_data = WarehouseData::new();
+		let mut config_key_map = HashMap::<ConfigKey, BlockHeight>::new();
+		let mut started_indexing = false;
+
+		'indexing: loop {
+			if !self.app.is_leading() {
+				if started_indexing {
+					self.log(IndexType::Downstream, false, "Stopping…");
+				}
+
+				started_indexing = false;
+				sleep(Duration::from_secs(1)).await;
+				continue;
+			}
+
+			if !started_indexing {
+				started_indexing = true;
+				self.log(IndexType::Downstream, false, "Starting…");
+			}
+
+			// create a map of `network_id` -> `latest_block` for fully-synced networks
+			let block_height_map = self.get_synced_block_heights().await?;
+			if block_height_map.is_empty() {
+				self.log(IndexType::Downstream, true, "No fully-synced active networks. Waiting…");
+				sleep(Duration::from_secs(5)).await;
+				continue;
+			}
+
+			// drop uncommitted links past the tail (possible after a chain reorg rollback)
+			for (network_id, block_height) in block_height_map.iter() {
+				warehouse_data.truncate(*network_id, *block_height);
+			}
+
+			// fetch all addresses
+			let network_ids: PrimaryIds =
+				block_height_map.clone().into_keys().collect::<Vec<PrimaryId>>().into();
+			let addresses =
+				Address::get_all_by_network_ids(self.app.db(), network_ids, Some(false)).await?;
+			let all_entity_addresses = addresses
+				.iter()
+				.map(|a| (a.network_id, a.address.clone()))
+				.collect::<HashSet<(PrimaryId, String)>>();
+			if addresses.is_empty() {
+				self.log(IndexType::Downstream, true, "Nothing to do (no addresses)");
+				sleep(Duration::from_secs(5)).await;
+				continue;
+			}
+
+			// how far back to walk from each entity address
+			let max_hops_map = self
+				.get_max_hops(&addresses, self.app.settings.indexer_downstream_max_hops)
+				.await?;
+
+			// how many blocks before a link funds are followed back to (0 for no limit)
+			let lookback = self.app.settings.indexer_downstream_lookback * 1_000;
+			let lookback_map = self
+				.app
+				.networks
+				.read()
+				.await
+				.iter()
+				.map(|(network_id, chain)| {
+					let block_time_ms = cmp::max(chain.get_network().block_time_ms, 1) as u64;
+					(*network_id, lookback.div_ceil(block_time_ms))
+				})
+				.collect::<HashMap<PrimaryId, BlockHeight>>();
+
+			// marker to test whether we're all caught up
+			let mut is_at_the_tip = true;
+
+			// process a chunk of blocks per address
+			let mut futures = JoinSet::new();
+			for address in addresses.into_iter() {
+				let network_id = address.network_id;
+				let latest_block_height = block_height_map[&network_id];
+
+				// get latest block for this address (same as upstream, except that
+				// fast-forwarding goes to the first time the address received funds)
+				let config_key = ConfigKey::IndexerDownstreamSync(network_id, address.address_id);
+				let block_height = match config_key_map.get(&config_key) {
+					Some(&block_height) => block_height,
+					_ => {
+						let block_height =
+							match Config::get::<_, BlockHeight>(self.app.db(), config_key).await? {
+								Some(hit) => hit.value,
+								_ => Transfer::get_first_by_target(
+									&self.app.warehouse,
+									network_id,
+									&address.address,
+								)
+								.await?
+								.map_or_else(|| latest_block_height, |t| t.block_height - 1),
+							};
+
+						config_key_map.insert(config_key, block_height);
+						block_height
+					}
+				};
+
+				// marker might be ahead of the tail after a chain reorg rollback
+				let block_height = if block_height > latest_block_height {
+					config_key_map.insert(config_key, latest_block_height);
+					latest_block_height
+				} else {
+					block_height
+				};
+
+				// process a new block range if we're not at the tip
+				if block_height < latest_block_height {
+					let warehouse = self.app.warehouse.clone();
+					let min_block_height = block_height + 1;
+					let max_block_height =
+						cmp::min(block_height + BLOCKS_PER_LOOP, latest_block_height);
+
+					if max_block_height != latest_block_height {
+						is_at_the_tip = false;
+					}
+
+					let max_hops = match max_hops_map[&(network_id, address.address.clone())] {
+						0 => usize::MAX,
+						max_hops => max_hops,
+					};
+
+					let min_branch_block_height =
+						match lookback_map.get(&network_id).copied().unwrap_or_default() {
+							0 => 0,
+							lookback => min_block_height.saturating_sub(lookback),
+						};
+
+					let network_entity_addresses = all_entity_addresses
+						.iter()
+						.filter(|(nid, _)| *nid == network_id)
+						.map(|(_, address)| address.clone())
+						.collect::<HashSet<String>>();
+
+					futures.spawn(async move {
+						let mut ret = WarehouseData::new();
+
+						// funds that reached this address within the block range
+						let mut branches = HashMap::<String, Branch>::new();
+						for transfer in Transfer::get_all_by_targets(
+							&warehouse,
+							network_id,
+							vec![address.address.clone()],
+							(min_block_height, max_block_height),
+						)
+						.await?
+						.into_iter()
+						{
+							if transfer.from_address != address.address {
+								let branch = Branch {
+									block_height: transfer.block_height,
+									transfer_uuids: vec![LinkUuid(transfer.uuid)],
+									link_block_height: transfer.block_height,
+									link_created_at: transfer.created_at,
+								};
+
+								ret.downstream_links.insert(DownstreamLink::new(
+									network_id,
+									branch.link_block_height,
+									&transfer.from_address,
+									&address.address,
+									branch.transfer_uuids.clone(),
+									branch.link_created_at,
+								));
+
+								// transfers come in descending order, so the latest one wins
+								branches.entry(transfer.from_address).or_insert(branch);
+							}
+						}
+						truncate_branches(&mut branches);
+
+						// walk back in time, one hop at a time
+						let mut visited = HashSet::from([address.address.clone()]);
+						for _ in 1..max_hops {
+							visited.extend(branches.keys().cloned());
+
+							// make sure we don't track past existing entity addresses
+							branches.retain(|a, _| !network_entity_addresses.contains(a));
+							if branches.is_empty() {
+								break;
+							}
+
+							let max_branch_block_height =
+								branches.values().map(|b| b.block_height).max().unwrap_or(0);
+
+							let mut new_branches = HashMap::<String, Branch>::new();
+							for transfer in Transfer::get_all_by_targets(
+								&warehouse,
+								network_id,
+								branches.keys().cloned().collect(),
+								(min_branch_block_height, max_branch_block_height),
+							)
+							.await?
+							.into_iter()
+							{
+								let branch = &branches[&transfer.to_address];
+
+								// funds have to arrive before they can be sent further
+								if transfer.block_height > branch.block_height ||
+									visited.contains(&transfer.from_address)
+								{
+									continue;
+								}
+
+								let new_branch = Branch {
+									block_height: transfer.block_height,
+									transfer_uuids: [
+										vec![LinkUuid(transfer.uuid)],
+										branch.transfer_uuids.clone(),
+									]
+									.concat(),
+									..branch.clone()
+								};
+
+								ret.downstream_links.insert(DownstreamLink::new(
+									network_id,
+									new_branch.link_block_height,
+									&transfer.from_address,
+									&address.address,
+									new_branch.transfer_uuids.clone(),
+									new_branch.link_created_at,
+								));
+
+								new_branches.entry(transfer.from_address).or_insert(new_branch);
+							}
+
+							truncate_branches(&mut new_branches);
+							branches = new_branches;
+						}
+
+						Ok::<_, ErrReport>((config_key, max_block_height, ret))
+					});
+				}
+
+				// don't process too many addresses at once
+				if futures.len() >= MAX_ADDRESSES_PER_JOIN_SET {
+					break;
+				}
+			}
+
+			// collect results
+			loop {
+				tokio::select! {
+					_ = networks_updated.changed() => {
+						self.log(IndexType::Downstream, true, "Restarting… (networks updated)");
+						break 'indexing Ok(());
+					}
+					result = futures.join_next() => {
+						if let Some(res) = result {
+							if let Ok((config_key, block_height, new_warehouse_data)) = res? {
+								warehouse_data += new_warehouse_data;
+								config_key_map.insert(config_key, block_height);
+							}
+						} else {
+							break;
+						}
+					}
+				}
+			}
+
+			// commit if collected enough
+			if warehouse_data.should_commit(is_at_the_tip, &self.app.settings) &&
+				self.app.is_leading()
+			{
+				self.log(
+					IndexType::Downstream,
+					true,
+					&format!(
+						"Pushing {} record(s) to warehouse",
+						style(self.format_number(warehouse_data.len())?).bold(),
+					),
+				);
+
+				// push to warehouse
+				warehouse_data.commit(self.app.warehouse.clone()).await?;
+
+				// commit config marker updates
+				Config::set_many::<_, BlockHeight>(self.app.db(), config_key_map.clone()).await?;
+				config_key_map.clear();
+			}
+
+			// if no threads ever started, pause
+			if is_at_the_tip {
+				sleep(Duration::from_secs(1)).await;
+			}
+		}
+	}
+}
+
+// keep the most recent branches only, if there are too many of them
+fn truncate_branches(branches: &mut HashMap<String, Branch>) {
+	if branches.len() > MAX_BRANCHES_PER_HOP {
+		let mut sorted = branches.drain().collect::<Vec<(String, Branch)>>();
+		sorted.sort_by_key(|(_, b)| cmp::Reverse(b.block_height));
+		sorted.truncate(MAX_BRANCHES_PER_HOP);
+
+		branches.extend(sorted);
+	}
+}
@@ -24,15 +24,20 @@ use uuid::Uuid;
 use barreleye_common::{
 	chain::{ModuleId, WarehouseData},
 	models::{
-		Address, AddressColumn, Amount, Balance, Config, ConfigKey, Entity, Link, Network,
-		NetworkColumn, PrimaryId, PrimaryIds, Relation, SoftDeleteModel, Transfer,
+		Address, AddressColumn, Amount, Balance, Cluster, Config, ConfigKey, DownstreamLink,
+		Entity, Link, Network, NetworkColumn, PrimaryId, PrimaryIds, Reconciliation, Relation,
+		SoftDeleteModel, Transfer, WatchedAddress,
 	},
-	utils, App, AppError, BlockHeight, Progress, ProgressReadyType, ProgressStep, Verbosity,
+	utils, App, AppError, BlockHeight, Event, Progress, ProgressReadyType, ProgressStep, Verbosity,
 	Warnings, INDEXER_HEARTBEAT,
 };
 
 mod blocks;
+mod clusters;
+mod downstream;
+mod reconciliation;
 mod upstream;
+mod webhooks;
 
 #[derive(Clone, Debug)]
 struct NetworkParams {
@@ -58,6 +63,14 @@ enum IndexType {
 	Blocks,
 	#[display(fmt = "upstream")]
 	Upstream,
+	#[display(fmt = "downstream")]
+	Downstream,
+	#[display(fmt = "clusters")]
+	Clusters,
+	#[display(fmt = "reconciliation")]
+	Reconciliation,
+	#[display(fmt = "webhooks")]
+	Webhooks,
 }
 
 #[derive(Clone)]
@@ -93,6 +106,24 @@ impl Indexer {
 				async move { s.index_upstream(r).await }
 			});
 
+			set.spawn({
+				let s = self.clone();
+				let r = rx.clone();
+				async move { s.index_downstream(r).await }
+			});
+
+			set.spawn({
+				let s = self.clone();
+				async move { s.index_clusters().await }
+			});
+
+			if self.app.settings.indexer_reconciliation_interval > 0 {
+				set.spawn({
+					let s = self.clone();
+					async move { s.reconcile_balances().await }
+				});
+			}
+
 			let ret = tokio::select! {
 				_ = signal::ctrl_c() => {
 					break Ok(())
@@ -160,6 +191,18 @@ impl Indexer {
 				Some(value) if value.updated_at != networks_updated_at => {
 					networks_updated_at = value.updated_at;
 					tx.send(SystemTime::now())?;
+
+					self.app.publish(Event::Networks {
+						networks: Network::get_all_by_env(
+							self.app.db(),
+							self.app.settings.env,
+							Some(false),
+						)
+						.await?
+						.into_iter()
+						.map(|n| n.id)
+						.collect(),
+					});
 				}
 				_ => {}
 			}
@@ -172,12 +215,17 @@ impl Indexer {
 		// prune all soft-deleted addresses
 		let addresses = Address::get_all_deleted(self.app.db()).await?;
 		if !addresses.is_empty() {
-			// delete all upstream configs
+			// delete all upstream & downstream configs
 			Config::delete_many(
 				self.app.db(),
 				addresses
 					.iter()
-					.map(|a| ConfigKey::IndexerUpstreamSync(a.network_id, a.address_id))
+					.flat_map(|a| {
+						[
+							ConfigKey::IndexerUpstreamSync(a.network_id, a.address_id),
+							ConfigKey::IndexerDownstreamSync(a.network_id, a.address_id),
+						]
+					})
 					.collect(),
 			)
 			.await?;
@@ -198,7 +246,8 @@ impl Indexer {
 					sources.insert(address.network_id, HashSet::from([address.address]));
 				}
 			}
-			Link::delete_all_by_sources(&self.app.warehouse, sources).await?;
+			Link::delete_all_by_sources(&self.app.warehouse, sources.clone()).await?;
+			DownstreamLink::delete_all_by_targets(&self.app.warehouse, sources).await?;
 		}
 
 		// prune all soft-deleted entities
@@ -222,6 +271,7 @@ impl Indexer {
 				AddressColumn::NetworkId.is_in(network_ids.clone()),
 			)
 			.await?;
+			WatchedAddress::delete_all_by_network_ids(self.app.db(), network_ids.clone()).await?;
 
 			// delete from warehouse
 			let (
@@ -230,19 +280,28 @@ impl Indexer {
 				balances_deleted,
 				amounts_deleted,
 				links_deleted,
+				downstream_links_deleted,
+				clusters_deleted,
+				reconciliations_deleted,
 			) = tokio::join!(
 				Transfer::delete_all_by_network_id(&self.app.warehouse, network_ids.clone()),
 				Relation::delete_all_by_network_id(&self.app.warehouse, network_ids.clone()),
 				Balance::delete_all_by_network_id(&self.app.warehouse, network_ids.clone()),
 				Amount::delete_all_by_network_id(&self.app.warehouse, network_ids.clone()),
 				Link::delete_all_by_network_id(&self.app.warehouse, network_ids.clone()),
+				DownstreamLink::delete_all_by_network_id(&self.app.warehouse, network_ids.clone()),
+				Cluster::delete_all_by_network_id(&self.app.warehouse, network_ids.clone()),
+				Reconciliation::delete_all_by_network_id(&self.app.warehouse, network_ids.clone()),
 			);
 
 			transfers_deleted
 				.and(relations_deleted)
 				.and(balances_deleted)
 				.and(amounts_deleted)
-				.and(links_deleted)?;
+				.and(links_deleted)
+				.and(downstream_links_deleted)
+				.and(clusters_deleted)
+				.and(reconciliations_deleted)?;
 
 			// finally delete only the networks we grabbed earlier
 			Network::prune_all_where(self.app.db(), NetworkColumn::NetworkId.is_in(network_ids))
@@ -252,6 +311,74 @@ impl Indexer {
 		Ok(())
 	}
 
+	// networks whose tail is syncing and are not catching up on chunks or modules, mapped to
+	// their latest indexed block
+	async fn get_synced_block_heights(&self) -> Result<HashMap<PrimaryId, BlockHeight>> {
+		// get all networks that are not syncing in chunks
+		let mut networks = vec![];
+		for network in Network::get_all_by_env(self.app.db(), self.app.settings.env, Some(false))
+			.await?
+			.into_iter()
+		{
+			let tail_is_syncing = Config::exist_by_keywords(
+				self.app.db(),
+				vec![format!("tail_sync_n{}", network.network_id)],
+			);
+
+			let is_actively_syncing = Config::exist_by_keywords(
+				self.app.db(),
+				vec![
+					format!("chunk_sync_n{}", network.network_id),
+					format!("module_sync_n{}", network.network_id),
+				],
+			);
+
+			if tail_is_syncing.await? && !is_actively_syncing.await? {
+				networks.push(network);
+			}
+		}
+
+		// create a map of `network_id` -> `latest_block`
+		let map = networks
+			.into_iter()
+			.map(|n| (ConfigKey::IndexerTailSync(n.network_id), n.network_id))
+			.collect::<HashMap<ConfigKey, PrimaryId>>();
+
+		Ok(Config::get_many::<_, BlockHeight>(self.app.db(), map.clone().into_keys().collect())
+			.await?
+			.into_iter()
+			.filter_map(|(config_key, hit)| match hit.value > 0 {
+				true => map.get(&config_key).map(|&network_id| (network_id, hit.value)),
+				_ => None,
+			})
+			.collect())
+	}
+
+	// hop limit for each entity address (`0` means no limit); entity setting wins over
+	// `default_max_hops`
+	async fn get_max_hops(
+		&self,
+		addresses: &[Address],
+		default_max_hops: u64,
+	) -> Result<HashMap<(PrimaryId, String), usize>> {
+		let entity_ids = addresses.iter().map(|a| a.entity_id).collect::<Vec<PrimaryId>>();
+		let entity_max_hops = Entity::get_all_by_entity_ids(self.app.db(), entity_ids.into(), None)
+			.await?
+			.into_iter()
+			.filter_map(|e| e.max_hops.map(|max_hops| (e.entity_id, max_hops as usize)))
+			.collect::<HashMap<PrimaryId, usize>>();
+
+		Ok(addresses
+			.iter()
+			.map(|a| {
+				let max_hops =
+					entity_max_hops.get(&a.entity_id).copied().unwrap_or(default_max_hops as usize);
+
+				((a.network_id, a.address.clone()), max_hops)
+			})
+			.collect())
+	}
+
 	fn log(&self, index_type: IndexType, detailed: bool, message: &str) {
 		if self.app.settings.verbosity > Verbosity::Silent || !detailed {
 			println!(
@@ -271,6 +398,7 @@ impl Indexer {
 pub struct Pipe {
 	config_key: ConfigKey,
 	sender: Sender<(ConfigKey, JsonValue, WarehouseData)>,
+	rollback_sender: Sender<(ConfigKey, BlockHeight)>,
 	receipt: Receiver<()>,
 	pub abort: broadcast::Receiver<()>,
 }
@@ -279,10 +407,11 @@ impl Pipe {
 	pub fn new(
 		config_key: ConfigKey,
 		sender: Sender<(ConfigKey, JsonValue, WarehouseData)>,
+		rollback_sender: Sender<(ConfigKey, BlockHeight)>,
 		receipt: Receiver<()>,
 		abort: broadcast::Receiver<()>,
 	) -> Self {
-		Self { config_key, sender, receipt, abort }
+		Self { config_key, sender, rollback_sender, receipt, abort }
 	}
 
 	pub async fn push(
@@ -299,4 +428,15 @@ impl Pipe {
 
 		Ok(())
 	}
+
+	pub async fn rollback(&mut self, block_height: BlockHeight) -> Result<()> {
+		self.rollback_sender.send((self.config_key, block_height)).await?;
+
+		tokio::select! {
+			_ = self.receipt.recv() => {}
+			_ = self.abort.recv() => {}
+		}
+
+		Ok(())
+	}
 }
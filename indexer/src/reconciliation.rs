@@ -0,0 +1,111 @@
+use eyre::Result;
+use tokio::time::{sleep, Duration};
+
+use crate::{IndexType, Indexer};
+use barreleye_common::{
+	models::{Balance, Config, ConfigKey, Reconciliation},
+	utils, BlockHeight,
+};
+
+impl Indexer {
+	pub async fn reconcile_balances(&self) -> Result<()> {
+		let mut started_reconciling = false;
+
+		loop {
+			if !self.app.is_leading() {
+				if started_reconciling {
+					self.log(IndexType::Reconciliation, false, "Stopping…");
+				}
+
+				started_reconciling = false;
+				sleep(Duration::from_secs(1)).await;
+				continue;
+			}
+
+			if !started_reconciling {
+				started_reconciling = true;
+				self.log(IndexType::Reconciliation, false, "Starting…");
+			}
+
+			let block_height_map = self.get_synced_block_heights().await?;
+			if block_height_map.is_empty() {
+				self.log(
+					IndexType::Reconciliation,
+					true,
+					"No fully-synced active networks. Waiting…",
+				);
+				sleep(Duration::from_secs(5)).await;
+				continue;
+			}
+
+			for (network_id, block_height) in block_height_map.into_iter() {
+				let chain = match self.app.networks.read().await.get(&network_id) {
+					Some(chain) => chain.clone(),
+					_ => continue,
+				};
+
+				let mut reconciliations = vec![];
+				let created_at = utils::now().timestamp() as u32;
+
+				for balance in Balance::get_random_sample(
+					&self.app.warehouse,
+					network_id,
+					self.app.settings.indexer_reconciliation_sample,
+				)
+				.await?
+				.into_iter()
+				{
+					let module_id = match chain.get_balance_module_id(&balance.asset_address) {
+						Some(module_id) => module_id,
+						_ => continue,
+					};
+
+					// rpc hiccups are skipped; the next run samples again anyway
+					if let Ok(Some(live_balance)) = chain
+						.get_balance(block_height, &balance.address, &balance.asset_address)
+						.await
+					{
+						reconciliations.push(Reconciliation::new(
+							network_id,
+							module_id,
+							block_height,
+							&balance.address,
+							&balance.asset_address,
+							balance.balance,
+							live_balance,
+							created_at,
+						));
+					}
+				}
+
+				// indexed balances are only comparable if the tail did not move in the meantime
+				let tail_block_height = Config::get::<_, BlockHeight>(
+					self.app.db(),
+					ConfigKey::IndexerTailSync(network_id),
+				)
+				.await?
+				.map(|hit| hit.value);
+				if reconciliations.is_empty() || tail_block_height != Some(block_height) {
+					continue;
+				}
+
+				let drifting = reconciliations.iter().filter(|r| r.is_drifting()).count();
+				self.log(
+					IndexType::Reconciliation,
+					true,
+					&format!(
+						"{} @ block {}: {} drifting out of {} sampled balance(s)",
+						chain.get_network().name,
+						self.format_number(block_height as usize)?,
+						self.format_number(drifting)?,
+						self.format_number(reconciliations.len())?,
+					),
+				);
+
+				Reconciliation::create_many(&self.app.warehouse, reconciliations).await?;
+			}
+
+			sleep(Duration::from_secs(self.app.settings.indexer_reconciliation_interval)).await;
+		}
+	}
+}
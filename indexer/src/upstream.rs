@@ -4,6 +4,7 @@ use sea_orm::{ColumnTrait, Condition};
 use std::{
 	cmp,
 	collections::{HashMap, HashSet},
+	mem,
 	time::SystemTime,
 };
 use tokio::{
@@ -16,8 +17,8 @@ use crate::{IndexType, Indexer};
 use barreleye_common::{
 	chain::WarehouseData,
 	models::{
-		Address, AddressColumn, BasicModel, Config, ConfigKey, Link, LinkUuid, Network, PrimaryId,
-		PrimaryIds, Transfer,
+		Address, AddressColumn, BasicModel, Cluster, Config, ConfigKey, DownstreamLink, Link,
+		LinkFilters, LinkUuid, PrimaryId, PrimaryIds, Transfer, WatchedAddress,
 	},
 	BlockHeight,
 };
@@ -26,13 +27,13 @@ const BLOCKS_PER_LOOP: BlockHeight = 10;
 const MAX_ADDRESSES_PER_JOIN_SET: usize = 100;
 
 struct IndexedLinks {
-	address: String,
+	addresses: HashSet<String>,
 	data: HashMap<String, HashSet<Link>>,
 }
 
 impl IndexedLinks {
-	pub fn new(address: &str, links: Vec<Link>) -> Self {
-		let mut s = Self { address: address.to_string(), data: HashMap::new() };
+	pub fn new(addresses: HashSet<String>, links: Vec<Link>) -> Self {
+		let mut s = Self { addresses, data: HashMap::new() };
 		s.push(links);
 		s
 	}
@@ -41,8 +42,63 @@ impl IndexedLinks {
 		self.data.get(key)
 	}
 
-	pub fn contains(&self, key: &str) -> bool {
-		self.address == key || self.data.get(key).is_some()
+	pub fn is_source(&self, key: &str) -> bool {
+		self.addresses.contains(key)
+	}
+
+	// links created by `transfer`: either existing branches extended by it, or a new branch
+	// when it leaves a source. `max_hops` is keyed by every source address (cluster members
+	// included), since that's what links start from
+	pub fn get_new_links(
+		&self,
+		transfer: &Transfer,
+		entity_addresses: &HashSet<String>,
+		max_hops: &HashMap<String, usize>,
+		default_max_hops: usize,
+	) -> Vec<Link> {
+		let mut ret = vec![];
+
+		if let Some(set) = self.get(&transfer.from_address) {
+			// make sure we don't track past existing entity addresses
+			if !entity_addresses.contains(&transfer.from_address) {
+				// extend branch
+				for prev_link in set.iter() {
+					// stop growing links that reached their hop limit
+					let max_hops =
+						max_hops.get(&prev_link.from_address).copied().unwrap_or(default_max_hops);
+					if max_hops > 0 && prev_link.transfer_uuids.len() >= max_hops {
+						continue;
+					}
+
+					let mut transfer_uuids = prev_link.transfer_uuids.clone();
+					transfer_uuids.push(LinkUuid(transfer.uuid));
+
+					// avoid loopbacks
+					if prev_link.from_address != transfer.to_address {
+						ret.push(Link::new(
+							transfer.network_id as PrimaryId,
+							transfer.block_height,
+							&prev_link.from_address,
+							&transfer.to_address,
+							transfer_uuids,
+							transfer.created_at,
+						));
+					}
+				}
+			}
+		} else if self.is_source(&transfer.from_address) && !self.is_source(&transfer.to_address) {
+			// start a new branch (unless funds move within the cluster)
+			ret.push(Link::new(
+				transfer.network_id as PrimaryId,
+				transfer.block_height,
+				&transfer.from_address,
+				&transfer.to_address,
+				vec![LinkUuid(transfer.uuid)],
+				transfer.created_at,
+			));
+		}
+
+		ret
 	}
 
 	pub fn push(&mut self, links: Vec<Link>) {
@@ -56,10 +112,40 @@ impl IndexedLinks {
 	}
 }
 
+// links also start from addresses clustered with an entity address, so they're bound by that
+// entity's hop limit (the least strict one, should a cluster hold several entity addresses)
+fn extend_max_hops(
+	mut max_hops_map: HashMap<(PrimaryId, String), usize>,
+	clusters: Vec<Cluster>,
+) -> HashMap<(PrimaryId, String), usize> {
+	let mut cluster_max_hops = HashMap::<(PrimaryId, String), usize>::new();
+	for cluster in clusters.iter() {
+		let network_id = cluster.network_id as PrimaryId;
+		if let Some(&max_hops) = max_hops_map.get(&(network_id, cluster.address.clone())) {
+			cluster_max_hops
+				.entry((network_id, cluster.cluster_id.clone()))
+				.and_modify(|v| {
+					*v = if *v == 0 || max_hops == 0 { 0 } else { cmp::max(*v, max_hops) }
+				})
+				.or_insert(max_hops);
+		}
+	}
+
+	for cluster in clusters.into_iter() {
+		let network_id = cluster.network_id as PrimaryId;
+		if let Some(&max_hops) = cluster_max_hops.get(&(network_id, cluster.cluster_id)) {
+			max_hops_map.entry((network_id, cluster.address)).or_insert(max_hops);
+		}
+	}
+
+	max_hops_map
+}
+
 impl Indexer {
 	pub async fn index_upstream(&self, mut networks_updated: Receiver<SystemTime>) -> Result<()> {
 		let mut warehouse_data = WarehouseData::new();
 		let mut config_key_map = HashMap::<ConfigKey, BlockHeight>::new();
+		let mut notifications = HashSet::<(PrimaryId, Link)>::new();
 		let mut started_indexing = false;
 
 		'indexing: loop {
@@ -78,61 +164,31 @@ impl Indexer {
 				self.log(IndexType::Upstream, false, "Starting…");
 			}
 
-			// get all networks that are not syncing in chunks
-			let mut networks = vec![];
-			for network in
-				Network::get_all_by_env(self.app.db(), self.app.settings.env, Some(false))
-					.await?
-					.into_iter()
-			{
-				let tail_is_syncing = Config::exist_by_keywords(
-					self.app.db(),
-					vec![format!("tail_sync_n{}", network.network_id)],
-				);
-
-				let is_actively_syncing = Config::exist_by_keywords(
-					self.app.db(),
-					vec![
-						format!("chunk_sync_n{}", network.network_id),
-						format!("module_sync_n{}", network.network_id),
-					],
-				);
-
-				if tail_is_syncing.await? && !is_actively_syncing.await? {
-					networks.push(network);
-				}
-			}
-
-			// create a map of `network_id` -> `latest_block`
-			let block_height_map = {
-				let map = networks
-					.into_iter()
-					.map(|n| (ConfigKey::IndexerTailSync(n.network_id), n.network_id))
-					.collect::<HashMap<ConfigKey, PrimaryId>>();
-
-				Config::get_many::<_, BlockHeight>(self.app.db(), map.clone().into_keys().collect())
-					.await?
-					.into_iter()
-					.filter_map(|(config_key, hit)| match hit.value > 0 {
-						true => map.get(&config_key).map(|&network_id| (network_id, hit.value)),
-						_ => None,
-					})
-					.collect::<HashMap<PrimaryId, BlockHeight>>()
-			};
+			// create a map of `network_id` -> `latest_block` for fully-synced networks
+			let block_height_map = self.get_synced_block_heights().await?;
 			if block_height_map.is_empty() {
 				self.log(IndexType::Upstream, true, "No fully-synced active networks. Waiting…");
 				sleep(Duration::from_secs(5)).await;
 				continue;
 			}
 
+			// drop uncommitted links past the tail (possible after a chain reorg rollback)
+			for (network_id, block_height) in block_height_map.iter() {
+				warehouse_data.truncate(*network_id, *block_height);
+				notifications.retain(|(_, l)| {
+					l.network_id != *network_id as u64 || l.block_height <= *block_height
+				});
+			}
+
 			// break the link chains that contain newly added addresses in the middle
 			let network_ids: PrimaryIds =
 				block_height_map.clone().into_keys().collect::<Vec<PrimaryId>>().into();
 			self.break_in_new_addresses(network_ids.clone()).await?;
 
 			// fetch all addresses
-			let addresses =
-				Address::get_all_by_network_ids(self.app.db(), network_ids, Some(false)).await?;
+			let mut addresses =
+				Address::get_all_by_network_ids(self.app.db(), network_ids.clone(), Some(false))
+					.await?;
 			let all_entity_addresses = addresses
 				.iter()
 				.map(|a| (a.network_id, a.address.clone()))
@@ -143,6 +199,49 @@ impl Indexer {
 				continue;
 			}
 
+			// customer addresses whose new links should not wait in the buffer
+			let mut watched_addresses = HashMap::<PrimaryId, HashSet<String>>::new();
+			for watched_address in
+				WatchedAddress::get_all_by_network_ids(self.app.db(), network_ids)
+					.await?
+					.into_iter()
+			{
+				watched_addresses
+					.entry(watched_address.network_id)
+					.or_default()
+					.insert(watched_address.address);
+			}
+
+			// entity addresses whose funds already reached watched addresses go first, so they
+			// get a slot in the join set below before the rest
+			if !watched_addresses.is_empty() {
+				let watched_sources = Link::get_all_disinct_by_addresses(
+					&self.app.warehouse,
+					watched_addresses.values().flatten().cloned().collect(),
+					&LinkFilters::default(),
+				)
+				.await?
+				.into_iter()
+				.filter(|l| {
+					watched_addresses
+						.get(&(l.network_id as PrimaryId))
+						.is_some_and(|set| set.contains(&l.to_address))
+				})
+				.map(|l| (l.network_id as PrimaryId, l.from_address))
+				.collect::<HashSet<(PrimaryId, String)>>();
+
+				addresses
+					.sort_by_key(|a| !watched_sources.contains(&(a.network_id, a.address.clone())));
+			}
+
+			// how far links are allowed to grow from each entity address (and its cluster)
+			let max_hops_map = self
+				.get_cluster_max_hops(
+					self.get_max_hops(&addresses, self.app.settings.indexer_max_hops).await?,
+				)
+				.await?;
+			let default_max_hops = self.app.settings.indexer_max_hops as usize;
+
 			// marker to test whether we're all caught up
 			let mut is_at_the_tip = true;
 
@@ -178,6 +277,14 @@ impl Indexer {
 					}
 				};
 
+				// marker might be ahead of the tail after a chain reorg rollback
+				let block_height = if block_height > latest_block_height {
+					config_key_map.insert(config_key, latest_block_height);
+					latest_block_height
+				} else {
+					block_height
+				};
+
 				// process a new block range if we're not at the tip
 				if block_height < latest_block_height {
 					let warehouse = self.app.warehouse.clone();
@@ -201,6 +308,12 @@ impl Indexer {
 							})
 							.collect::<HashSet<String>>();
 
+					let network_max_hops = max_hops_map
+						.iter()
+						.filter(|((nid, _), _)| *nid == network_id)
+						.map(|((_, address), max_hops)| (address.clone(), *max_hops))
+						.collect::<HashMap<String, usize>>();
+
 					futures.spawn({
 						let uncommitted_links = warehouse_data
 							.clone()
@@ -212,9 +325,22 @@ impl Indexer {
 						async move {
 							let mut ret = WarehouseData::new();
 
+							// addresses clustered with the entity address start links as well
+							let mut sources = HashSet::from([address.address.clone()]);
+							sources.extend(
+								Cluster::get_all_by_member(
+									&warehouse,
+									network_id,
+									&address.address,
+								)
+								.await?
+								.into_iter()
+								.map(|c| c.address),
+							);
+
 							// seed data from processed but uncommitted links
 							let mut indexed_links =
-								IndexedLinks::new(&address.address.clone(), uncommitted_links);
+								IndexedLinks::new(sources.clone(), uncommitted_links);
 
 							// seed data from warehouse
 							indexed_links.push(
@@ -235,58 +361,27 @@ impl Indexer {
 							.await?
 							.into_iter()
 							{
-								if indexed_links.contains(&transfer.from_address) {
-									let mut new_links = vec![];
-
-									// create new links
-									if let Some(set) = indexed_links.get(&transfer.from_address) {
-										// make sure we don't track past existing entity addresses
-										if !network_entity_addresses
-											.contains(&transfer.from_address)
-										{
-											// extend branch
-											for prev_link in set.iter() {
-												let mut transfer_uuids =
-													prev_link.transfer_uuids.clone();
-												transfer_uuids.push(LinkUuid(transfer.uuid));
-
-												// avoid loopbacks
-												if prev_link.from_address != transfer.to_address {
-													let link = Link::new(
-														address.network_id,
-														transfer.block_height,
-														&prev_link.from_address,
-														&transfer.to_address,
-														transfer_uuids,
-														transfer.created_at,
-													);
-
-													ret.links.insert(link.clone());
-													new_links.push(link);
-												}
-											}
-										}
-									} else if transfer.from_address != transfer.to_address {
-										// start a new branch
-										let link = Link::new(
-											address.network_id,
-											transfer.block_height,
-											&transfer.from_address,
-											&transfer.to_address,
-											vec![LinkUuid(transfer.uuid)],
-											transfer.created_at,
-										);
-
-										ret.links.insert(link.clone());
-										new_links.push(link);
-									}
-
-									// add to indexed data
-									indexed_links.push(new_links);
-								}
+								let new_links = indexed_links.get_new_links(
+									&transfer,
+									&network_entity_addresses,
+									&network_max_hops,
+									default_max_hops,
+								);
+
+								// add to indexed data
+								ret.links.extend(new_links.iter().cloned());
+								indexed_links.push(new_links);
 							}
 
-							Ok::<_, ErrReport>((config_key, max_block_height, ret))
+							// links starting at this entity are reported to webhooks once committed
+							let entity_links = ret
+								.links
+								.iter()
+								.filter(|l| sources.contains(&l.from_address))
+								.map(|l| (address.entity_id, l.clone()))
+								.collect::<HashSet<(PrimaryId, Link)>>();
+
+							Ok::<_, ErrReport>((config_key, max_block_height, ret, entity_links))
 						}
 					});
 				}
@@ -306,8 +401,11 @@ impl Indexer {
 					}
 					result = futures.join_next() => {
 						if let Some(res) = result {
-							if let Ok((config_key, block_height, new_warehouse_data)) = res? {
+							if let Ok((config_key, block_height, new_warehouse_data, entity_links)) =
+								res?
+							{
 								warehouse_data += new_warehouse_data;
+								notifications.extend(entity_links);
 								config_key_map.insert(config_key, block_height);
 							}
 						} else {
@@ -318,7 +416,15 @@ impl Indexer {
 			}
 
 			// commit if collected enough
-			if warehouse_data.should_commit(is_at_the_tip) && self.app.is_leading() {
+			// commit right away when funds reach a watched address
+			let reached_watchlist = warehouse_data.links.iter().any(|l| {
+				watched_addresses
+					.get(&(l.network_id as PrimaryId))
+					.is_some_and(|set| set.contains(&l.to_address))
+			});
+			if warehouse_data.should_commit(is_at_the_tip || reached_watchlist, &self.app.settings) &&
+				self.app.is_leading()
+			{
 				self.log(
 					IndexType::Upstream,
 					true,
@@ -334,6 +440,9 @@ impl Indexer {
 				// commit config marker updates
 				Config::set_many::<_, BlockHeight>(self.app.db(), config_key_map.clone()).await?;
 				config_key_map.clear();
+
+				// let subscribers know about the new links
+				self.notify_links(mem::take(&mut notifications)).await?;
 			}
 
 			// if no threads ever started, pause
@@ -343,6 +452,31 @@ impl Indexer {
 		}
 	}
 
+	async fn get_cluster_max_hops(
+		&self,
+		max_hops_map: HashMap<(PrimaryId, String), usize>,
+	) -> Result<HashMap<(PrimaryId, String), usize>> {
+		let addresses = max_hops_map.keys().map(|(_, a)| a.clone()).collect::<Vec<String>>();
+
+		let mut cluster_ids = HashMap::<PrimaryId, Vec<String>>::new();
+		for cluster in Cluster::get_all_by_addresses(&self.app.warehouse, addresses).await? {
+			cluster_ids
+				.entry(cluster.network_id as PrimaryId)
+				.or_default()
+				.push(cluster.cluster_id);
+		}
+
+		let mut clusters = vec![];
+		for (network_id, cluster_ids) in cluster_ids.into_iter() {
+			clusters.extend(
+				Cluster::get_all_by_cluster_ids(&self.app.warehouse, network_id, cluster_ids)
+					.await?,
+			);
+		}
+
+		Ok(extend_max_hops(max_hops_map, clusters))
+	}
+
 	async fn break_in_new_addresses(&self, network_ids: PrimaryIds) -> Result<()> {
 		// get all newly added addresses for the provided networks
 		let address_ids = Config::get_many_by_keywords::<_, PrimaryId>(
@@ -362,7 +496,7 @@ impl Indexer {
 			)
 			.await?;
 
-			// delete all links that contain newly added entity addresses in the middle
+			// delete all links (both ways) that contain newly added entity addresses in the middle
 			let mut address_map: HashMap<PrimaryId, HashSet<String>> = HashMap::new();
 			for address in newly_added_addresses.clone().into_iter() {
 				if let Some(set) = address_map.get_mut(&address.network_id) {
@@ -371,7 +505,10 @@ impl Indexer {
 					address_map.insert(address.network_id, HashSet::from([address.address]));
 				}
 			}
-			Link::delete_all_by_newly_added_addresses(&self.app.warehouse, address_map).await?;
+			Link::delete_all_by_newly_added_addresses(&self.app.warehouse, address_map.clone())
+				.await?;
+			DownstreamLink::delete_all_by_newly_added_addresses(&self.app.warehouse, address_map)
+				.await?;
 
 			// delete configs for the newly added addresses
 			Config::delete_many(
@@ -387,3 +524,64 @@ impl Indexer {
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use barreleye_common::chain::{ModuleId, U256};
+
+	fn transfer(tx_hash: &str, from_address: &str, to_address: &str) -> Transfer {
+		Transfer::new(
+			ModuleId::EvmTransfer,
+			1,
+			1,
+			tx_hash,
+			0,
+			from_address,
+			to_address,
+			None,
+			U256::from(1),
+			U256::from(1),
+			0,
+		)
+	}
+
+	#[test]
+	fn test_cluster_member_max_hops() {
+		// entity address "e" is clustered with "m" and allows 2 hops; "x" is clustered with nobody
+		let max_hops_map = extend_max_hops(
+			HashMap::from([((1, "e".to_string()), 2)]),
+			vec![
+				Cluster::new(1, "e", "e", 1),
+				Cluster::new(1, "m", "e", 1),
+				Cluster::new(1, "x", "x", 1),
+			],
+		);
+		assert_eq!(max_hops_map.get(&(1, "m".to_string())), Some(&2));
+		assert_eq!(max_hops_map.get(&(1, "x".to_string())), None);
+
+		let max_hops = max_hops_map
+			.into_iter()
+			.map(|((_, address), max_hops)| (address, max_hops))
+			.collect::<HashMap<String, usize>>();
+		let entity_addresses = HashSet::from(["e".to_string()]);
+
+		// funds leave the cluster member and keep moving: m -> a -> b -> c
+		let mut indexed_links =
+			IndexedLinks::new(HashSet::from(["e".to_string(), "m".to_string()]), vec![]);
+		let mut links = vec![];
+		for transfer in [transfer("1", "m", "a"), transfer("2", "a", "b"), transfer("3", "b", "c")]
+		{
+			let new_links =
+				indexed_links.get_new_links(&transfer, &entity_addresses, &max_hops, 10);
+			links.extend(new_links.iter().map(|l| (l.from_address.clone(), l.to_address.clone())));
+			indexed_links.push(new_links);
+		}
+
+		// the branch is cut at the entity's limit, not the default one
+		assert_eq!(
+			links,
+			vec![("m".to_string(), "a".to_string()), ("m".to_string(), "b".to_string())]
+		);
+	}
+}
@@ -0,0 +1,312 @@
+use eyre::{bail, eyre, Result};
+use hmac::{Hmac, Mac};
+use reqwest::{header::CONTENT_TYPE, Client, StatusCode};
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
+use tokio::time::{sleep, Duration};
+
+use crate::{IndexType, Indexer};
+use barreleye_common::{
+	models::{Entity, Link, PrimaryId, Tag, WatchedAddress, Webhook},
+	utils, Event,
+};
+
+const MAX_ATTEMPTS: u32 = 5;
+const BACKOFF: Duration = Duration::from_secs(1);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Notification {
+	webhook: String,
+	links: Vec<NotificationLink>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct NotificationLink {
+	network: String,
+	address: String,
+	entity: String,
+	from_address: String,
+	hops: usize,
+	block_height: u64,
+}
+
+impl Indexer {
+	// publish newly committed links (paired with the entity they start from) to the event stream
+	// and deliver them to matching webhooks
+	pub async fn notify_links(&self, links: HashSet<(PrimaryId, Link)>) -> Result<()> {
+		if links.is_empty() {
+			return Ok(());
+		}
+
+		let webhooks = Webhook::get_all_active(self.app.db()).await?;
+		if webhooks.is_empty() && !self.app.has_subscribers() {
+			return Ok(());
+		}
+
+		let mut entity_ids = links.iter().map(|(entity_id, _)| *entity_id).collect::<Vec<_>>();
+		entity_ids.sort_unstable();
+		entity_ids.dedup();
+
+		let entities =
+			Entity::get_all_by_entity_ids(self.app.db(), entity_ids.clone().into(), Some(false))
+				.await?
+				.into_iter()
+				.map(|e| (e.entity_id, e.id))
+				.collect::<HashMap<PrimaryId, String>>();
+
+		let mut tags = HashMap::<PrimaryId, Vec<String>>::new();
+		for tag in Tag::get_all_by_entity_ids(self.app.db(), entity_ids.into()).await?.into_iter() {
+			tags.entry(tag.entity_id).or_default().push(tag.id);
+		}
+
+		let watched_addresses = WatchedAddress::get_all_by_addresses(
+			self.app.db(),
+			links.iter().map(|(_, l)| l.to_address.clone()).collect(),
+		)
+		.await?
+		.into_iter()
+		.map(|w| (w.network_id, w.address))
+		.collect::<HashSet<(PrimaryId, String)>>();
+
+		let networks = self
+			.app
+			.networks
+			.read()
+			.await
+			.iter()
+			.map(|(network_id, chain)| (*network_id, chain.get_network().id))
+			.collect::<HashMap<PrimaryId, String>>();
+
+		// (link, whether it reached a watched address, tags of its entity)
+		let mut resolved_links = vec![];
+		for (entity_id, link) in links.into_iter() {
+			let network_id = link.network_id as PrimaryId;
+			if let (Some(entity), Some(network)) =
+				(entities.get(&entity_id), networks.get(&network_id))
+			{
+				let is_watched = watched_addresses.contains(&(network_id, link.to_address.clone()));
+				let entity_tags = tags.get(&entity_id).cloned().unwrap_or_default();
+
+				resolved_links.push((
+					NotificationLink {
+						network: network.clone(),
+						address: link.to_address,
+						entity: entity.clone(),
+						from_address: link.from_address,
+						hops: link.transfer_uuids.len(),
+						block_height: link.block_height,
+					},
+					is_watched,
+					entity_tags,
+				));
+			}
+		}
+
+		for (link, _, _) in resolved_links.iter() {
+			self.app.publish(Event::Upstream {
+				network: link.network.clone(),
+				address: link.address.clone(),
+				entity: link.entity.clone(),
+				from_address: link.from_address.clone(),
+				hops: link.hops,
+				block_height: link.block_height,
+			});
+		}
+
+		let client = Client::builder().timeout(TIMEOUT).build()?;
+		for webhook in webhooks.into_iter() {
+			let notification_links = resolved_links
+				.iter()
+				.filter(|(link, is_watched, entity_tags)| {
+					webhook.is_match(
+						&link.address,
+						*is_watched,
+						&link.entity,
+						entity_tags,
+						link.hops,
+					)
+				})
+				.map(|(link, _, _)| link.clone())
+				.collect::<Vec<NotificationLink>>();
+
+			if notification_links.is_empty() {
+				continue;
+			}
+
+			let body = serde_json::to_string(&Notification {
+				webhook: webhook.id.clone(),
+				links: notification_links,
+			})?;
+
+			// deliver in the background, so slow endpoints don't hold up indexing
+			tokio::spawn({
+				let s = self.clone();
+				let client = client.clone();
+				async move {
+					if let Err(e) =
+						deliver(&client, &webhook.url, &webhook.secret, body, BACKOFF).await
+					{
+						s.log(
+							IndexType::Webhooks,
+							false,
+							&format!("Could not deliver to {}: {e}", webhook.id),
+						);
+					}
+				}
+			});
+		}
+
+		Ok(())
+	}
+}
+
+// POST a signed payload, retrying with exponential backoff on connection and server errors
+async fn deliver(
+	client: &Client,
+	url: &str,
+	secret: &str,
+	body: String,
+	backoff: Duration,
+) -> Result<()> {
+	let mut attempt = 1;
+
+	loop {
+		let timestamp = utils::now().timestamp();
+		let res = client
+			.post(url)
+			.header(CONTENT_TYPE, "application/json")
+			.header("X-Barreleye-Timestamp", timestamp)
+			.header("X-Barreleye-Signature", sign(secret, timestamp, &body)?)
+			.body(body.clone())
+			.send()
+			.await;
+
+		let error = match res {
+			Ok(r) if r.status().is_success() => return Ok(()),
+			Ok(r) => {
+				let status = r.status();
+				let is_retryable = status.is_server_error() ||
+					status == StatusCode::REQUEST_TIMEOUT ||
+					status == StatusCode::TOO_MANY_REQUESTS;
+				if !is_retryable {
+					bail!("endpoint responded with {status}");
+				}
+
+				format!("endpoint responded with {status}")
+			}
+			Err(e) => e.to_string(),
+		};
+
+		if attempt >= MAX_ATTEMPTS {
+			bail!("gave up after {attempt} attempts ({error})");
+		}
+
+		sleep(backoff * 2u32.pow(attempt - 1)).await;
+		attempt += 1;
+	}
+}
+
+// hex-encoded hmac-sha256 of `{timestamp}.{body}`, so receivers can verify origin & freshness
+fn sign(secret: &str, timestamp: i64, body: &str) -> Result<String> {
+	let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+		.map_err(|_| eyre!("invalid webhook secret"))?;
+
+	mac.update(format!("{timestamp}.").as_bytes());
+	mac.update(body.as_bytes());
+
+	Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::{Arc, Mutex};
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpListener,
+	};
+
+	// minimal http endpoint that answers with `statuses` in order and records raw requests
+	async fn stand_in(statuses: Vec<u16>) -> Result<(String, Arc<Mutex<Vec<String>>>)> {
+		let listener = TcpListener::bind("127.0.0.1:0").await?;
+		let url = format!("http://{}/hook", listener.local_addr()?);
+		let requests = Arc::new(Mutex::new(vec![]));
+
+		tokio::spawn({
+			let requests = requests.clone();
+			async move {
+				for status in statuses.into_iter() {
+					let (mut socket, _) = listener.accept().await.unwrap();
+
+					let mut data = vec![];
+					let mut buf = [0; 1024];
+					let request = loop {
+						let n = socket.read(&mut buf).await.unwrap();
+						data.extend_from_slice(&buf[..n]);
+
+						let request = String::from_utf8_lossy(&data).to_string();
+						if let Some((head, body)) = request.split_once("\r\n\r\n") {
+							let content_length = head
+								.lines()
+								.find_map(|l| {
+									l.to_lowercase()
+										.strip_prefix("content-length: ")
+										.map(|v| v.to_string())
+								})
+								.and_then(|v| v.parse::<usize>().ok())
+								.unwrap_or(0);
+							if body.len() >= content_length {
+								break request;
+							}
+						}
+					};
+					requests.lock().unwrap().push(request);
+
+					let response = format!(
+						"HTTP/1.1 {status} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+					);
+					socket.write_all(response.as_bytes()).await.unwrap();
+				}
+			}
+		});
+
+		Ok((url, requests))
+	}
+
+	#[tokio::test]
+	async fn test_deliver() -> Result<()> {
+		let client = Client::new();
+		let body = r#"{"webhook":"whk_test"}"#.to_string();
+
+		// retries past server errors, signing every attempt
+		let (url, requests) = stand_in(vec![500, 503, 200]).await?;
+		deliver(&client, &url, "secret", body.clone(), Duration::from_millis(1)).await?;
+
+		let requests = requests.lock().unwrap().clone();
+		assert_eq!(requests.len(), 3);
+		for request in requests.iter() {
+			let header = |name: &str| {
+				request.lines().find_map(|l| {
+					l.to_lowercase()
+						.starts_with(&format!("{name}: "))
+						.then(|| l[name.len() + 2..].to_string())
+				})
+			};
+
+			let timestamp = header("x-barreleye-timestamp").unwrap().parse::<i64>()?;
+			assert_eq!(header("x-barreleye-signature"), Some(sign("secret", timestamp, &body)?));
+			assert!(request.ends_with(&body));
+		}
+
+		// client errors are not retried
+		let (url, requests) = stand_in(vec![400, 200]).await?;
+		assert!(deliver(&client, &url, "secret", body, Duration::from_millis(1)).await.is_err());
+		assert_eq!(requests.lock().unwrap().len(), 1);
+
+		Ok(())
+	}
+}
@@ -0,0 +1,130 @@
+use axum::{extract::State, Json};
+use axum_extra::extract::Query;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::{
+	utils::{get_addresses_from_params, get_entities_data, get_networks, get_transfers},
+	ServerResult,
+};
+use barreleye_common::{
+	models::{DownstreamLink, PrimaryId, SanitizedEntity, SanitizedNetwork, SanitizedTag},
+	App,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Payload {
+	#[serde(default, rename = "address")]
+	addresses: Vec<String>,
+	#[serde(default, rename = "entity")]
+	entities: Vec<String>,
+	detailed: Option<bool>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseTransaction {
+	hash: String,
+	from_address: String,
+	to_address: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	token_id: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseDownstream {
+	network: String,
+	address: String,
+	entity: String,
+	transactions: Vec<ResponseTransaction>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+	downstream: Vec<ResponseDownstream>,
+	networks: Vec<SanitizedNetwork>,
+	entities: Vec<SanitizedEntity>,
+	tags: Vec<SanitizedTag>,
+}
+
+pub async fn handler(
+	State(app): State<Arc<App>>,
+	Query(payload): Query<Payload>,
+) -> ServerResult<Json<Response>> {
+	// get addresses
+	let addresses =
+		get_addresses_from_params(app.clone(), payload.addresses, payload.entities).await?;
+
+	// find links
+	let links = match payload.detailed {
+		Some(true) => {
+			DownstreamLink::get_all_by_addresses(&app.warehouse, addresses.clone()).await?
+		}
+		_ => {
+			DownstreamLink::get_all_disinct_by_addresses(&app.warehouse, addresses.clone()).await?
+		}
+	};
+
+	let (transfers, networks, entities_data) = tokio::join!(
+		get_transfers(
+			app.clone(),
+			links.iter().flat_map(|l| l.transfer_uuids.iter().map(|u| u.0)).collect(),
+		),
+		get_networks(app.clone(), addresses.clone()),
+		get_entities_data(app.clone(), {
+			let mut to_addresses =
+				links.iter().map(|l| l.to_address.clone()).collect::<Vec<String>>();
+
+			to_addresses.sort_unstable();
+			to_addresses.dedup();
+
+			to_addresses
+		}),
+	);
+
+	let transfers = transfers?;
+	let (address_map, entities_map, tags) = entities_data?;
+
+	// assemble downstream
+	let mut downstream = vec![];
+	let n = app.networks.read().await;
+	for link in links.into_iter() {
+		let network_id = link.network_id as PrimaryId;
+		if let Some(chain) = n.get(&network_id) {
+			let network = chain.get_network();
+
+			if let Some(&entity_id) = address_map.get(&(network_id, link.to_address.clone())) {
+				if let Some(entity) = entities_map.get(&entity_id) {
+					downstream.push(ResponseDownstream {
+						network: network.id,
+						address: link.to_address,
+						entity: entity.id.clone(),
+						transactions: link
+							.transfer_uuids
+							.into_iter()
+							.filter_map(|uuid| {
+								transfers.get(&uuid.0).map(|t| ResponseTransaction {
+									hash: t.tx_hash.clone(),
+									from_address: t.from_address.clone(),
+									to_address: t.to_address.clone(),
+									token_id: (!t.token_id.is_empty()).then(|| t.token_id.clone()),
+								})
+							})
+							.collect(),
+					});
+				}
+			}
+		}
+	}
+
+	Ok(Response {
+		downstream,
+		networks: networks?.into_iter().map(|n| n.into()).collect(),
+		entities: entities_map.into_values().map(|e| e.into()).collect(),
+		tags: tags.into_iter().map(|t| t.into()).collect(),
+	}
+	.into())
+}
@@ -15,6 +15,7 @@ pub struct Payload {
 	name: Option<String>,
 	description: String,
 	url: String,
+	max_hops: Option<u64>,
 	tags: Option<Vec<String>>,
 }
 
@@ -51,7 +52,12 @@ pub async fn handler(
 	// create new
 	let entity_id = Entity::create(
 		app.db(),
-		Entity::new_model(payload.name, &payload.description, &payload.url),
+		Entity::new_model(
+			payload.name,
+			&payload.description,
+			&payload.url,
+			payload.max_hops.map(|v| v as i64),
+		),
 	)
 	.await?;
 
@@ -22,6 +22,7 @@ pub struct Payload {
 	name: Option<Option<String>>,
 	description: Option<String>,
 	url: Option<String>,
+	max_hops: Option<Option<u64>>,
 	tags: Option<Vec<String>>,
 }
 
@@ -59,6 +60,7 @@ pub async fn handler(
 			name: optional_set(payload.name),
 			description: optional_set(payload.description),
 			url: optional_set(payload.url),
+			max_hops: optional_set(payload.max_hops.map(|v| v.map(|v| v as i64))),
 			..Default::default()
 		};
 		if update_data.is_changed() {
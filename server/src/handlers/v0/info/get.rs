@@ -2,16 +2,17 @@ use axum::{extract::State, Json};
 use axum_extra::extract::Query;
 use eyre::Result;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 
 use crate::{
-	utils::{get_addresses_from_params, get_networks},
+	utils::{
+		get_addresses_from_params, get_entities_data, get_exposure, get_networks, ResponseExposure,
+	},
 	ServerResult,
 };
 use barreleye_common::{
-	models::{
-		Address, Balance, Entity, PrimaryId, SanitizedEntity, SanitizedNetwork, SanitizedTag, Tag,
-	},
+	models::{Balance, LinkFilters, PrimaryId, SanitizedEntity, SanitizedNetwork, SanitizedTag},
+	taint::TaintModel,
 	App,
 };
 
@@ -22,6 +23,8 @@ pub struct Payload {
 	addresses: Vec<String>,
 	#[serde(default, rename = "entity")]
 	entities: Vec<String>,
+	#[serde(default)]
+	taint_model: TaintModel,
 }
 
 #[derive(Serialize)]
@@ -36,6 +39,7 @@ pub struct ResponseAsset {
 #[serde(rename_all = "camelCase")]
 pub struct Response {
 	assets: Vec<ResponseAsset>,
+	exposure: Vec<ResponseExposure>,
 	networks: Vec<SanitizedNetwork>,
 	entities: Vec<SanitizedEntity>,
 	tags: Vec<SanitizedTag>,
@@ -79,56 +83,32 @@ pub async fn handler(
 		Ok(ret)
 	}
 
-	// get entities
-	async fn get_entities_data(
-		app: Arc<App>,
-		addresses: Vec<String>,
-	) -> Result<(Vec<Entity>, Vec<Tag>)> {
-		let mut entities = vec![];
-		let mut tags = vec![];
-
-		let addresses = Address::get_all_by_addresses(app.db(), addresses, Some(false)).await?;
-		if !addresses.is_empty() {
-			let entity_ids = addresses.into_iter().map(|a| a.entity_id).collect::<Vec<PrimaryId>>();
-			entities =
-				Entity::get_all_by_entity_ids(app.db(), entity_ids.into(), Some(false)).await?;
-
-			if !entities.is_empty() {
-				let joined_tags =
-					Tag::get_all_by_entity_ids(app.db(), entities.clone().into()).await?;
-
-				let mut map = HashMap::<PrimaryId, Vec<String>>::new();
-				for joined_tag in joined_tags.iter() {
-					if let Some(ids) = map.get_mut(&joined_tag.entity_id) {
-						ids.push(joined_tag.id.clone());
-					} else {
-						map.insert(joined_tag.entity_id, vec![joined_tag.id.clone()]);
-					}
-				}
-
-				for entity in entities.iter_mut() {
-					entity.tags = map.get(&entity.entity_id).cloned().or(Some(vec![]));
-				}
-
-				tags = joined_tags.into_iter().map(|jt| jt.into()).collect();
-			}
-		}
-
-		Ok((entities, tags))
-	}
-
-	let (assets, networks, entities_data) = tokio::join!(
+	let filters = LinkFilters::default();
+	let (assets, exposure, networks, entities_data) = tokio::join!(
 		get_assets(app.clone(), addresses.clone()),
+		get_exposure(app.clone(), addresses.clone(), filters, payload.taint_model),
 		get_networks(app.clone(), addresses.clone()),
 		get_entities_data(app.clone(), addresses.clone()),
 	);
 
-	let (entities, tags) = entities_data?;
+	let (_, mut entities, mut tags) = entities_data?;
+	let (exposure, exposure_entities, exposure_tags) = exposure?;
+
+	// include entities that funds were traced back to
+	for (entity_id, entity) in exposure_entities.into_iter() {
+		entities.entry(entity_id).or_insert(entity);
+	}
+	for tag in exposure_tags.into_iter() {
+		if !tags.iter().any(|t| t.tag_id == tag.tag_id) {
+			tags.push(tag);
+		}
+	}
 
 	Ok(Response {
 		assets: assets?,
+		exposure,
 		networks: networks?.into_iter().map(|n| n.into()).collect(),
-		entities: entities.into_iter().map(|e| e.into()).collect(),
+		entities: entities.into_values().map(|e| e.into()).collect(),
 		tags: tags.into_iter().map(|t| t.into()).collect(),
 	}
 	.into())
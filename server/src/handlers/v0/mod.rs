@@ -4,19 +4,26 @@ use std::sync::Arc;
 use barreleye_common::App;
 
 mod addresses;
+mod downstream;
 mod entities;
 mod heartbeat;
 mod info;
 mod keys;
 mod networks;
+mod relations;
+mod risk;
 mod stats;
+mod stream;
 mod tags;
 mod upstream;
+mod watchlist;
+mod webhooks;
 
 pub fn get_routes() -> Router<Arc<App>> {
 	Router::new()
 		.nest("/heartbeat", heartbeat::get_routes())
 		.nest("/stats", stats::get_routes())
+		.nest("/stream", stream::get_routes())
 		.nest("/keys", keys::get_routes())
 		.nest("/networks", networks::get_routes())
 		.nest("/entities", entities::get_routes())
@@ -24,4 +31,9 @@ pub fn get_routes() -> Router<Arc<App>> {
 		.nest("/tags", tags::get_routes())
 		.nest("/info", info::get_routes())
 		.nest("/upstream", upstream::get_routes())
+		.nest("/downstream", downstream::get_routes())
+		.nest("/relations", relations::get_routes())
+		.nest("/risk", risk::get_routes())
+		.nest("/webhooks", webhooks::get_routes())
+		.nest("/watchlist", watchlist::get_routes())
 }
@@ -5,7 +5,7 @@ use std::sync::Arc;
 
 use crate::{errors::ServerError, ServerResult};
 use barreleye_common::{
-	chain::{Bitcoin, ChainTrait, Evm},
+	chain::{Bitcoin, ChainTrait, Evm, Solana, Tron},
 	models::{BasicModel, Config, ConfigKey, Network},
 	App, Blockchain, Env,
 };
@@ -20,6 +20,7 @@ pub struct Payload {
 	block_time_ms: u64,
 	rpc_endpoints: Vec<String>,
 	rps: u32,
+	confirmations: Option<u64>,
 }
 
 pub async fn handler(
@@ -54,6 +55,8 @@ pub async fn handler(
 	let mut boxed_chain: Box<dyn ChainTrait> = match payload.blockchain {
 		Blockchain::Bitcoin => Box::new(Bitcoin::new(c, n)),
 		Blockchain::Evm => Box::new(Evm::new(c, n)),
+		Blockchain::Tron => Box::new(Tron::new(c, n)),
+		Blockchain::Solana => Box::new(Solana::new(c, n)),
 	};
 	if !boxed_chain.connect().await? {
 		return Err(ServerError::InvalidService { name: boxed_chain.get_network().name });
@@ -70,6 +73,7 @@ pub async fn handler(
 			payload.block_time_ms as i64,
 			payload.rpc_endpoints,
 			payload.rps as i32,
+			payload.confirmations.unwrap_or(0) as i64,
 		),
 	)
 	.await?;
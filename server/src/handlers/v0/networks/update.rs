@@ -26,6 +26,7 @@ pub struct Payload {
 	block_time_ms: Option<u64>,
 	rpc_endpoints: Option<Vec<String>>,
 	rps: Option<u32>,
+	confirmations: Option<u64>,
 }
 
 pub async fn handler(
@@ -72,6 +73,7 @@ pub async fn handler(
 		block_time_ms: optional_set(payload.block_time_ms.map(|v| v as i64)),
 		rpc_endpoints: optional_set(payload.rpc_endpoints.clone().map(|v| json!(v))),
 		rps: optional_set(payload.rps.map(|v| v as i32)),
+		confirmations: optional_set(payload.confirmations.map(|v| v as i64)),
 		..Default::default()
 	};
 
@@ -0,0 +1,132 @@
+use axum::{extract::State, Json};
+use axum_extra::extract::Query;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::{
+	errors::ServerError,
+	utils::{get_entities_data, get_networks},
+	ServerResult,
+};
+use barreleye_common::{
+	models::{
+		PrimaryId, Relation, RelationReason, SanitizedEntity, SanitizedNetwork, SanitizedTag,
+	},
+	App,
+};
+
+const DEFAULT_LIMIT: u64 = 100;
+const MAX_LIMIT: u64 = 1_000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Payload {
+	address: String,
+	offset: Option<u64>,
+	limit: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseRelation {
+	network: String,
+	address: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	entity: Option<String>,
+	reason: String,
+	tx_hash: String,
+	block_height: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+	relations: Vec<ResponseRelation>,
+	networks: Vec<SanitizedNetwork>,
+	entities: Vec<SanitizedEntity>,
+	tags: Vec<SanitizedTag>,
+}
+
+pub async fn handler(
+	State(app): State<Arc<App>>,
+	Query(payload): Query<Payload>,
+) -> ServerResult<Json<Response>> {
+	if payload.address.is_empty() {
+		return Err(ServerError::MissingInputParams);
+	}
+
+	let limit = payload.limit.unwrap_or(DEFAULT_LIMIT);
+	if limit > MAX_LIMIT {
+		return Err(ServerError::ExceededLimit {
+			field: "limit".to_string(),
+			limit: MAX_LIMIT as usize,
+		});
+	}
+
+	let address = app.format_address(&payload.address).await?;
+
+	// find relations
+	let relations = Relation::get_all_by_address_paginated(
+		&app.warehouse,
+		&address,
+		payload.offset.unwrap_or(0),
+		limit,
+	)
+	.await?;
+
+	// the other side of each relation
+	let related_addresses = relations
+		.iter()
+		.map(|r| match r.from_address == address {
+			true => r.to_address.clone(),
+			_ => r.from_address.clone(),
+		})
+		.collect::<Vec<String>>();
+
+	let (networks, entities_data) = tokio::join!(
+		get_networks(app.clone(), vec![address.clone()]),
+		get_entities_data(app.clone(), {
+			let mut addresses = related_addresses.clone();
+
+			addresses.sort_unstable();
+			addresses.dedup();
+
+			addresses
+		}),
+	);
+
+	let (address_map, entities_map, tags) = entities_data?;
+
+	// assemble relations
+	let mut ret = vec![];
+	let n = app.networks.read().await;
+	for (relation, related_address) in relations.into_iter().zip(related_addresses) {
+		let network_id = relation.network_id as PrimaryId;
+		if let Some(chain) = n.get(&network_id) {
+			let reason = match RelationReason::try_from(relation.reason) {
+				Ok(reason) => reason,
+				_ => continue,
+			};
+
+			ret.push(ResponseRelation {
+				network: chain.get_network().id,
+				entity: address_map
+					.get(&(network_id, related_address.clone()))
+					.and_then(|entity_id| entities_map.get(entity_id))
+					.map(|entity| entity.id.clone()),
+				address: related_address,
+				reason: reason.to_string(),
+				tx_hash: relation.tx_hash,
+				block_height: relation.block_height,
+			});
+		}
+	}
+
+	Ok(Response {
+		relations: ret,
+		networks: networks?.into_iter().map(|n| n.into()).collect(),
+		entities: entities_map.into_values().map(|e| e.into()).collect(),
+		tags: tags.into_iter().map(|t| t.into()).collect(),
+	}
+	.into())
+}
@@ -0,0 +1,172 @@
+use axum::{extract::State, Json};
+use axum_extra::extract::Query;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+	errors::ServerError,
+	utils::{get_entities_data, get_exposure},
+	ServerResult,
+};
+use barreleye_common::{
+	models::{Entity, LinkFilters, SanitizedEntity, SanitizedTag},
+	risk::{self, Decision},
+	taint::TaintModel,
+	App,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Payload {
+	address: String,
+	#[serde(default)]
+	taint_model: TaintModel,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResponseReason {
+	Label,
+	Exposure,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseFactor {
+	reason: ResponseReason,
+	network: String,
+	entity: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	tag: Option<String>,
+	severity: u8,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	percentage: Option<f64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	hops: Option<usize>,
+	score: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+	address: String,
+	score: f64,
+	decision: Decision,
+	factors: Vec<ResponseFactor>,
+	entities: Vec<SanitizedEntity>,
+	tags: Vec<SanitizedTag>,
+}
+
+pub async fn handler(
+	State(app): State<Arc<App>>,
+	Query(payload): Query<Payload>,
+) -> ServerResult<Json<Response>> {
+	if payload.address.is_empty() {
+		return Err(ServerError::MissingInputParams);
+	}
+
+	let address = app.format_address(&payload.address).await?;
+
+	let filters = LinkFilters::default();
+	let (entities_data, exposure) = tokio::join!(
+		get_entities_data(app.clone(), vec![address.clone()]),
+		get_exposure(app.clone(), vec![address.clone()], filters, payload.taint_model),
+	);
+
+	let (address_map, mut entities, mut tags) = entities_data?;
+	let (exposure, exposure_entities, exposure_tags) = exposure?;
+
+	// include entities that funds were traced back to
+	for (entity_id, entity) in exposure_entities.into_iter() {
+		entities.entry(entity_id).or_insert(entity);
+	}
+	for tag in exposure_tags.into_iter() {
+		if !tags.iter().any(|t| t.tag_id == tag.tag_id) {
+			tags.push(tag);
+		}
+	}
+
+	// an entity is as severe as its most severe tag
+	let tag_severities =
+		tags.iter().map(|t| (t.id.clone(), t.severity as u8)).collect::<HashMap<String, u8>>();
+	let get_severity = |entity: &Entity| {
+		entity
+			.tags
+			.iter()
+			.flatten()
+			.filter_map(|tag_id| tag_severities.get(tag_id).map(|s| (tag_id.clone(), *s)))
+			.max_by_key(|(_, severity)| *severity)
+			.map_or((None, 0), |(tag_id, severity)| (Some(tag_id), severity))
+	};
+
+	let mut factors = vec![];
+
+	// the address itself is labeled
+	let n = app.networks.read().await;
+	for ((network_id, _), entity_id) in address_map.into_iter() {
+		if let (Some(chain), Some(entity)) = (n.get(&network_id), entities.get(&entity_id)) {
+			let (tag, severity) = get_severity(entity);
+
+			factors.push(ResponseFactor {
+				reason: ResponseReason::Label,
+				network: chain.get_network().id,
+				entity: entity.id.clone(),
+				tag,
+				severity,
+				percentage: None,
+				hops: None,
+				score: risk::get_label_score(severity),
+			});
+		}
+	}
+
+	// funds that can be traced back to labeled entities
+	let entities_by_id = entities.values().map(|e| (e.id.clone(), e)).collect::<HashMap<_, _>>();
+	for exposure in exposure.into_iter() {
+		if let Some(entity) = entities_by_id.get(&exposure.entity) {
+			let (tag, severity) = get_severity(entity);
+
+			factors.push(ResponseFactor {
+				reason: ResponseReason::Exposure,
+				network: exposure.network,
+				entity: exposure.entity,
+				tag,
+				severity,
+				percentage: Some(exposure.percentage),
+				hops: Some(exposure.hops),
+				score: risk::get_exposure_score(
+					severity,
+					exposure.percentage,
+					exposure.hops,
+					app.settings.risk_hop_decay,
+				),
+			});
+		}
+	}
+
+	factors.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+	// exposure comes per network & asset, but each entity only counts once (its strongest factor)
+	let mut entity_scores = HashMap::<String, f64>::new();
+	for factor in factors.iter() {
+		let score = entity_scores.entry(factor.entity.clone()).or_default();
+		*score = score.max(factor.score);
+	}
+
+	let score = risk::get_score(&entity_scores.into_values().collect::<Vec<f64>>());
+	let decision = Decision::new(
+		score,
+		app.settings.risk_review_threshold,
+		app.settings.risk_reject_threshold,
+	);
+
+	Ok(Response {
+		address,
+		score,
+		decision,
+		factors,
+		entities: entities.into_values().map(|e| e.into()).collect(),
+		tags: tags.into_iter().map(|t| t.into()).collect(),
+	}
+	.into())
+}
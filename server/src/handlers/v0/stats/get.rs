@@ -1,40 +1,59 @@
 use axum::{extract::State, Json};
+use sea_orm::prelude::DateTime;
 use serde::Serialize;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use crate::ServerResult;
 use barreleye_common::{
-	models::{BasicModel, Config, ConfigKey, Network},
-	App,
+	models::{BasicModel, Config, ConfigKey, Network, Reconciliation},
+	utils, App,
 };
 
+const RECONCILIATION_WINDOW: u64 = 24 * 60 * 60;
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ResponseNetwork {
 	name: String,
 	tail_index: u64,
 	block_height: u64,
+	confirmed_block_height: u64,
 	sync: f64,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseReconciliation {
+	network: String,
+	module_id: u16,
+	checked: u64,
+	drifted: u64,
+	checked_at: DateTime,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Response {
 	sync: f64,
 	networks: Vec<ResponseNetwork>,
+	reconciliation: Vec<ResponseReconciliation>,
 }
 
 pub async fn handler(State(app): State<Arc<App>>) -> ServerResult<Json<Response>> {
 	let mut networks = vec![];
+	let mut network_names = HashMap::new();
 
 	for network in Network::get_all(app.db()).await?.into_iter() {
 		let nid = network.network_id;
+		network_names.insert(nid as u64, network.name.clone());
 
 		let block_height = Config::get::<_, u64>(app.db(), ConfigKey::BlockHeight(nid))
 			.await?
 			.map(|v| v.value)
 			.unwrap_or(0);
 
+		let confirmed_block_height = block_height.saturating_sub(network.confirmations as u64);
+
 		let tail_index = Config::get::<_, u64>(app.db(), ConfigKey::IndexerTailSync(nid))
 			.await?
 			.map(|v| v.value)
@@ -45,12 +64,35 @@ pub async fn handler(State(app): State<Arc<App>>) -> ServerResult<Json<Response>
 			.map(|v| v.value)
 			.unwrap_or(0.0);
 
-		networks.push(ResponseNetwork { name: network.name, tail_index, block_height, sync });
+		networks.push(ResponseNetwork {
+			name: network.name,
+			tail_index,
+			block_height,
+			confirmed_block_height,
+			sync,
+		});
 	}
 
+	// balance samples checked against the nodes during the last day
+	let since = utils::ago_in_seconds(RECONCILIATION_WINDOW).timestamp() as u32;
+	let reconciliation = Reconciliation::get_stats(&app.warehouse, since)
+		.await?
+		.into_iter()
+		.filter_map(|stats| {
+			Some(ResponseReconciliation {
+				network: network_names.get(&stats.network_id)?.clone(),
+				module_id: stats.module_id,
+				checked: stats.checked,
+				drifted: stats.drifted,
+				checked_at: DateTime::from_timestamp_opt(stats.checked_at as i64, 0)?,
+			})
+		})
+		.collect();
+
 	Ok(Response {
 		sync: networks.iter().map(|n| n.sync).sum::<f64>() / networks.len() as f64,
 		networks,
+		reconciliation,
 	}
 	.into())
 }
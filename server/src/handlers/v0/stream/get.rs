@@ -0,0 +1,33 @@
+use axum::{
+	extract::State,
+	response::sse::{Event as SseEvent, KeepAlive, Sse},
+};
+use futures::{stream, Stream};
+use std::{convert::Infallible, sync::Arc};
+use tokio::sync::broadcast::error::RecvError;
+
+use barreleye_common::App;
+
+pub async fn handler(
+	State(app): State<Arc<App>>,
+) -> Sse<impl Stream<Item = Result<SseEvent, Infallible>>> {
+	let events = stream::unfold(app.subscribe(), |mut receiver| async move {
+		loop {
+			match receiver.recv().await {
+				Ok(event) => {
+					let sse_event = SseEvent::default()
+						.event(event.to_string())
+						.json_data(&event)
+						.unwrap_or_else(|_| SseEvent::default().comment("unserializable event"));
+
+					return Some((Ok(sse_event), receiver));
+				}
+				// slow clients miss some events, but stay connected
+				Err(RecvError::Lagged(_)) => continue,
+				Err(RecvError::Closed) => return None,
+			}
+		}
+	});
+
+	Sse::new(events).keep_alive(KeepAlive::default())
+}
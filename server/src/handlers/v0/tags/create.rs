@@ -5,6 +5,7 @@ use std::sync::Arc;
 use crate::{errors::ServerError, ServerResult};
 use barreleye_common::{
 	models::{BasicModel, Tag},
+	risk::MAX_SEVERITY,
 	App,
 };
 
@@ -12,19 +13,29 @@ use barreleye_common::{
 #[serde(rename_all = "camelCase")]
 pub struct Payload {
 	name: String,
+	severity: Option<u8>,
 }
 
 pub async fn handler(
 	State(app): State<Arc<App>>,
 	Json(payload): Json<Payload>,
 ) -> ServerResult<Json<Tag>> {
+	// validate severity
+	let severity = payload.severity.unwrap_or(0);
+	if severity > MAX_SEVERITY {
+		return Err(ServerError::InvalidParam {
+			field: "severity".to_string(),
+			value: severity.to_string(),
+		});
+	}
+
 	// check for duplicate name
 	if Tag::get_by_name(app.db(), &payload.name).await?.is_some() {
 		return Err(ServerError::Duplicate { field: "name".to_string(), value: payload.name });
 	}
 
 	// create new
-	let tag_id = Tag::create(app.db(), Tag::new_model(&payload.name)).await?;
+	let tag_id = Tag::create(app.db(), Tag::new_model(&payload.name, severity as i32)).await?;
 
 	// return newly created
 	Ok(Tag::get(app.db(), tag_id).await?.unwrap().into())
@@ -10,6 +10,7 @@ use std::sync::Arc;
 use crate::{errors::ServerError, ServerResult};
 use barreleye_common::{
 	models::{optional_set, BasicModel, Tag, TagActiveModel},
+	risk::MAX_SEVERITY,
 	App,
 };
 
@@ -17,6 +18,7 @@ use barreleye_common::{
 #[serde(rename_all = "camelCase")]
 pub struct Payload {
 	name: Option<String>,
+	severity: Option<u8>,
 }
 
 pub async fn handler(
@@ -34,8 +36,20 @@ pub async fn handler(
 			}
 		}
 
+		// validate severity
+		if let Some(severity) = payload.severity.filter(|s| *s > MAX_SEVERITY) {
+			return Err(ServerError::InvalidParam {
+				field: "severity".to_string(),
+				value: severity.to_string(),
+			});
+		}
+
 		// update
-		let update_data = TagActiveModel { name: optional_set(payload.name), ..Default::default() };
+		let update_data = TagActiveModel {
+			name: optional_set(payload.name),
+			severity: optional_set(payload.severity.map(|s| s as i32)),
+			..Default::default()
+		};
 		if update_data.is_changed() {
 			Tag::update_by_id(app.db(), &tag_id, update_data).await?;
 		}
@@ -1,22 +1,20 @@
 use axum::{extract::State, Json};
 use axum_extra::extract::Query;
-use eyre::Result;
 use serde::{Deserialize, Serialize};
-use std::{
-	collections::{HashMap, HashSet},
-	sync::Arc,
-};
-use uuid::Uuid;
+use std::sync::Arc;
 
 use crate::{
-	utils::{get_addresses_from_params, get_networks},
+	errors::ServerError,
+	utils::{
+		get_addresses_from_params, get_entities_data, get_exposure, get_networks, get_transfers,
+		ResponseExposure,
+	},
 	ServerResult,
 };
 use barreleye_common::{
-	models::{
-		Address, Entity, Link, PrimaryId, SanitizedEntity, SanitizedNetwork, SanitizedTag, Tag,
-		Transfer,
-	},
+	chain::U256,
+	models::{Link, LinkFilters, PrimaryId, SanitizedEntity, SanitizedNetwork, SanitizedTag},
+	taint::TaintModel,
 	App,
 };
 
@@ -28,6 +26,14 @@ pub struct Payload {
 	#[serde(default, rename = "entity")]
 	entities: Vec<String>,
 	detailed: Option<bool>,
+	min_hops: Option<u64>,
+	max_hops: Option<u64>,
+	since: Option<u32>,
+	until: Option<u32>,
+	min_amount: Option<String>,
+	min_amount_asset: Option<String>,
+	#[serde(default)]
+	taint_model: TaintModel,
 }
 
 #[derive(Serialize)]
@@ -36,6 +42,8 @@ pub struct ResponseTransaction {
 	hash: String,
 	from_address: String,
 	to_address: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	token_id: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -51,6 +59,7 @@ pub struct ResponseUpstream {
 #[serde(rename_all = "camelCase")]
 pub struct Response {
 	upstream: Vec<ResponseUpstream>,
+	exposure: Vec<ResponseExposure>,
 	networks: Vec<SanitizedNetwork>,
 	entities: Vec<SanitizedEntity>,
 	tags: Vec<SanitizedTag>,
@@ -64,87 +73,70 @@ pub async fn handler(
 	let addresses =
 		get_addresses_from_params(app.clone(), payload.addresses, payload.entities).await?;
 
-	// find links
-	let links = match payload.detailed {
-		Some(true) => Link::get_all_by_addresses(&app.warehouse, addresses.clone()).await?,
-		_ => Link::get_all_disinct_by_addresses(&app.warehouse, addresses.clone()).await?,
-	};
-
-	// get transfers (@TODO ideally this step would be combined with link fetching)
-	async fn get_transfers(app: Arc<App>, links: Vec<Link>) -> Result<HashMap<Uuid, Transfer>> {
-		let transfer_uuids = {
-			let mut ret = HashSet::new();
-
-			for link in links.into_iter() {
-				for transfer_uuid in link.transfer_uuids.into_iter() {
-					ret.insert(transfer_uuid.0);
-				}
-			}
-
-			ret
-		};
-
-		Ok(Transfer::get_all_by_uuids(&app.warehouse, transfer_uuids.into_iter().collect())
-			.await?
-			.into_iter()
-			.map(|t| (t.uuid, t))
-			.collect::<HashMap<Uuid, Transfer>>())
+	// validate hop range
+	if let (Some(min_hops), Some(max_hops)) = (payload.min_hops, payload.max_hops) {
+		if min_hops > max_hops {
+			return Err(ServerError::InvalidParam {
+				field: "minHops".to_string(),
+				value: min_hops.to_string(),
+			});
+		}
 	}
 
-	// get entities data
-	async fn get_entities_data(
-		app: Arc<App>,
-		addresses: Vec<String>,
-	) -> Result<(HashMap<(PrimaryId, String), PrimaryId>, HashMap<PrimaryId, Entity>, Vec<Tag>)> {
-		let mut address_map = HashMap::new();
-		let mut entities = HashMap::new();
-		let mut tags = vec![];
-
-		let addresses = Address::get_all_by_addresses(app.db(), addresses, Some(false)).await?;
-
-		if !addresses.is_empty() {
-			address_map = addresses
-				.iter()
-				.map(|a| ((a.network_id, a.address.clone()), a.entity_id))
-				.collect::<HashMap<(PrimaryId, String), PrimaryId>>();
-
-			let entity_ids = addresses.into_iter().map(|a| a.entity_id).collect::<Vec<PrimaryId>>();
-			for entity in Entity::get_all_by_entity_ids(app.db(), entity_ids.into(), Some(false))
-				.await?
-				.into_iter()
-			{
-				entities.insert(entity.entity_id, entity);
-			}
-
-			if !entities.is_empty() {
-				let joined_tags = Tag::get_all_by_entity_ids(
-					app.db(),
-					entities.clone().into_keys().collect::<Vec<PrimaryId>>().into(),
-				)
-				.await?;
-
-				let mut map = HashMap::<PrimaryId, Vec<String>>::new();
-				for joined_tag in joined_tags.iter() {
-					if let Some(ids) = map.get_mut(&joined_tag.entity_id) {
-						ids.push(joined_tag.id.clone());
-					} else {
-						map.insert(joined_tag.entity_id, vec![joined_tag.id.clone()]);
-					}
-				}
-
-				for (entity_id, entity) in entities.iter_mut() {
-					entity.tags = map.get(entity_id).cloned().or(Some(vec![]));
-				}
+	// validate time window
+	if let (Some(since), Some(until)) = (payload.since, payload.until) {
+		if since > until {
+			return Err(ServerError::InvalidParam {
+				field: "since".to_string(),
+				value: since.to_string(),
+			});
+		}
+	}
 
-				tags = joined_tags.into_iter().map(|jt| jt.into()).collect();
-			}
+	// validate amount threshold (amounts of different assets are not comparable, so the asset
+	// is required too: a token address or `native` for the network's own coin)
+	let min_amount = match (payload.min_amount, payload.min_amount_asset) {
+		(Some(min_amount), Some(asset)) => {
+			let min_amount = U256::from_dec_str(&min_amount).map_err(|_| {
+				ServerError::InvalidParam { field: "minAmount".to_string(), value: min_amount }
+			})?;
+			let asset_address = match asset.as_str() {
+				"native" => "".to_string(),
+				_ => app.format_address(&asset).await?,
+			};
+
+			Some((asset_address, min_amount))
 		}
+		(Some(_), None) => {
+			return Err(ServerError::Validation { field: "minAmountAsset".to_string() })
+		}
+		(None, Some(_)) => return Err(ServerError::Validation { field: "minAmount".to_string() }),
+		_ => None,
+	};
 
-		Ok((address_map, entities, tags))
-	}
+	// find links
+	let filters = LinkFilters {
+		min_hops: payload.min_hops,
+		max_hops: payload.max_hops,
+		since: payload.since,
+		until: payload.until,
+		min_amount,
+	};
+	let links = match payload.detailed {
+		Some(true) => {
+			Link::get_all_by_addresses(&app.warehouse, addresses.clone(), &filters).await?
+		}
+		_ => {
+			Link::get_all_disinct_by_addresses(&app.warehouse, addresses.clone(), &filters).await?
+		}
+	};
 
-	let (transfers, networks, entities_data) = tokio::join!(
-		get_transfers(app.clone(), links.clone()),
+	let (transfers, exposure, networks, entities_data) = tokio::join!(
+		get_transfers(
+			app.clone(),
+			links.iter().flat_map(|l| l.transfer_uuids.iter().map(|u| u.0)).collect(),
+		),
+		get_exposure(app.clone(), addresses.clone(), filters.clone(), payload.taint_model),
 		get_networks(app.clone(), addresses.clone()),
 		get_entities_data(app.clone(), {
 			let mut from_addresses =
@@ -158,6 +150,7 @@ pub async fn handler(
 	);
 
 	let transfers = transfers?;
+	let (exposure, _, _) = exposure?;
 	let (address_map, entities_map, tags) = entities_data?;
 
 	// assemble upstream
@@ -182,6 +175,7 @@ pub async fn handler(
 									hash: t.tx_hash.clone(),
 									from_address: t.from_address.clone(),
 									to_address: t.to_address.clone(),
+									token_id: (!t.token_id.is_empty()).then(|| t.token_id.clone()),
 								})
 							})
 							.collect(),
@@ -193,6 +187,7 @@ pub async fn handler(
 
 	Ok(Response {
 		upstream,
+		exposure,
 		networks: networks?.into_iter().map(|n| n.into()).collect(),
 		entities: entities_map.into_values().map(|e| e.into()).collect(),
 		tags: tags.into_iter().map(|t| t.into()).collect(),
@@ -0,0 +1,67 @@
+use axum::{extract::State, Json};
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{errors::ServerError, ServerResult};
+use barreleye_common::{
+	models::{BasicModel, Network, WatchedAddress},
+	App,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Payload {
+	network: String,
+	addresses: HashMap<String, String>, // address -> description
+}
+
+pub async fn handler(
+	State(app): State<Arc<App>>,
+	Json(payload): Json<Payload>,
+) -> ServerResult<Json<Vec<WatchedAddress>>> {
+	let network =
+		Network::get_by_id(app.db(), &payload.network).await?.ok_or(ServerError::InvalidParam {
+			field: "network".to_string(),
+			value: payload.network,
+		})?;
+
+	// check for duplicates
+	let watched_addresses = WatchedAddress::get_all_by_network_id_and_addresses(
+		app.db(),
+		network.network_id,
+		payload.addresses.clone().into_keys().collect(),
+	)
+	.await?;
+	if !watched_addresses.is_empty() {
+		return Err(ServerError::Duplicates {
+			field: "addresses".to_string(),
+			values: watched_addresses
+				.into_iter()
+				.map(|w| w.address)
+				.collect::<Vec<String>>()
+				.join(", "),
+		});
+	}
+
+	// create new
+	WatchedAddress::create_many(
+		app.db(),
+		payload
+			.addresses
+			.iter()
+			.map(|(address, description)| {
+				WatchedAddress::new_model(network.network_id, &network.id, address, description)
+			})
+			.collect(),
+	)
+	.await?;
+
+	// return newly created
+	Ok(WatchedAddress::get_all_by_network_id_and_addresses(
+		app.db(),
+		network.network_id,
+		payload.addresses.into_keys().collect(),
+	)
+	.await?
+	.into())
+}
@@ -0,0 +1,22 @@
+use axum::{
+	extract::{Path, State},
+	http::StatusCode,
+};
+use std::sync::Arc;
+
+use crate::{errors::ServerError, ServerResult};
+use barreleye_common::{
+	models::{BasicModel, WatchedAddress},
+	App,
+};
+
+pub async fn handler(
+	State(app): State<Arc<App>>,
+	Path(watched_address_id): Path<String>,
+) -> ServerResult<StatusCode> {
+	if WatchedAddress::delete_by_id(app.db(), &watched_address_id).await? {
+		Ok(StatusCode::NO_CONTENT)
+	} else {
+		Err(ServerError::NotFound)
+	}
+}
@@ -0,0 +1,40 @@
+use axum::{
+	extract::{Path, State},
+	http::StatusCode,
+	Json,
+};
+use sea_orm::ActiveModelTrait;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{errors::ServerError, ServerResult};
+use barreleye_common::{
+	models::{optional_set, BasicModel, WatchedAddress, WatchedAddressActiveModel},
+	App,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Payload {
+	description: Option<String>,
+}
+
+pub async fn handler(
+	State(app): State<Arc<App>>,
+	Path(watched_address_id): Path<String>,
+	Json(payload): Json<Payload>,
+) -> ServerResult<StatusCode> {
+	if WatchedAddress::get_by_id(app.db(), &watched_address_id).await?.is_some() {
+		let update_data = WatchedAddressActiveModel {
+			description: optional_set(payload.description),
+			..Default::default()
+		};
+		if update_data.is_changed() {
+			WatchedAddress::update_by_id(app.db(), &watched_address_id, update_data).await?;
+		}
+
+		Ok(StatusCode::NO_CONTENT)
+	} else {
+		Err(ServerError::NotFound)
+	}
+}
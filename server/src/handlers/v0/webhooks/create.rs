@@ -0,0 +1,87 @@
+use axum::{extract::State, Json};
+use hyper::Uri;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::{errors::ServerError, ServerResult};
+use barreleye_common::{
+	models::{BasicModel, Entity, SoftDeleteModel, Tag, Webhook},
+	App,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Payload {
+	url: String,
+	address: Option<String>,
+	entity: Option<String>,
+	tag: Option<String>,
+	min_hops: Option<u64>,
+	watchlist: Option<bool>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+	#[serde(flatten)]
+	webhook: Webhook,
+	secret: String,
+}
+
+pub async fn handler(
+	State(app): State<Arc<App>>,
+	Json(payload): Json<Payload>,
+) -> ServerResult<Json<Response>> {
+	// only absolute http(s) urls can be delivered to
+	let is_valid_url = payload
+		.url
+		.parse::<Uri>()
+		.is_ok_and(|uri| matches!(uri.scheme_str(), Some("http" | "https")));
+	if !is_valid_url {
+		return Err(ServerError::InvalidParam { field: "url".to_string(), value: payload.url });
+	}
+
+	// without a filter every new link would be delivered
+	if payload.address.is_none() &&
+		payload.entity.is_none() &&
+		payload.tag.is_none() &&
+		payload.watchlist != Some(true)
+	{
+		return Err(ServerError::MissingInputParams);
+	}
+
+	let address = match payload.address {
+		Some(address) => Some(app.format_address(&address).await?),
+		_ => None,
+	};
+
+	// check that referenced entity & tag exist
+	if let Some(entity) = payload.entity.clone() {
+		if Entity::get_existing_by_id(app.db(), &entity).await?.is_none() {
+			return Err(ServerError::InvalidParam { field: "entity".to_string(), value: entity });
+		}
+	}
+	if let Some(tag) = payload.tag.clone() {
+		if Tag::get_by_id(app.db(), &tag).await?.is_none() {
+			return Err(ServerError::InvalidParam { field: "tag".to_string(), value: tag });
+		}
+	}
+
+	// create new
+	let webhook_id = Webhook::create(
+		app.db(),
+		Webhook::new_model(
+			&payload.url,
+			address,
+			payload.entity,
+			payload.tag,
+			payload.min_hops.map(|v| v as i64),
+			payload.watchlist,
+		),
+	)
+	.await?;
+
+	// return newly created (the only time the signing secret is shown)
+	let webhook = Webhook::get(app.db(), webhook_id).await?.unwrap();
+	Ok(Response { secret: webhook.secret.clone(), webhook }.into())
+}
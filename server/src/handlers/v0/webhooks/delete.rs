@@ -0,0 +1,22 @@
+use axum::{
+	extract::{Path, State},
+	http::StatusCode,
+};
+use std::sync::Arc;
+
+use crate::{errors::ServerError, ServerResult};
+use barreleye_common::{
+	models::{BasicModel, Webhook},
+	App,
+};
+
+pub async fn handler(
+	State(app): State<Arc<App>>,
+	Path(webhook_id): Path<String>,
+) -> ServerResult<StatusCode> {
+	if Webhook::delete_by_id(app.db(), &webhook_id).await? {
+		Ok(StatusCode::NO_CONTENT)
+	} else {
+		Err(ServerError::NotFound)
+	}
+}
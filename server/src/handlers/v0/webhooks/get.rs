@@ -0,0 +1,28 @@
+use axum::{
+	extract::{Path, State},
+	Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::{errors::ServerError, ServerResult};
+use barreleye_common::{
+	models::{BasicModel, Webhook},
+	App,
+};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+	webhook: Webhook,
+}
+
+pub async fn handler(
+	State(app): State<Arc<App>>,
+	Path(webhook_id): Path<String>,
+) -> ServerResult<Json<Response>> {
+	Webhook::get_by_id(app.db(), &webhook_id)
+		.await?
+		.map(|webhook| Response { webhook }.into())
+		.ok_or(ServerError::NotFound)
+}
@@ -0,0 +1,32 @@
+use axum::{extract::State, Json};
+use axum_extra::extract::Query;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::ServerResult;
+use barreleye_common::{
+	models::{BasicModel, Webhook},
+	App,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Payload {
+	offset: Option<u64>,
+	limit: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+	webhooks: Vec<Webhook>,
+}
+
+pub async fn handler(
+	State(app): State<Arc<App>>,
+	Query(payload): Query<Payload>,
+) -> ServerResult<Json<Response>> {
+	let webhooks = Webhook::get_all_paginated(app.db(), payload.offset, payload.limit).await?;
+
+	Ok(Response { webhooks }.into())
+}
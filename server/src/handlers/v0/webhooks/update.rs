@@ -0,0 +1,54 @@
+use axum::{
+	extract::{Path, State},
+	http::StatusCode,
+	Json,
+};
+use hyper::Uri;
+use sea_orm::ActiveModelTrait;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{errors::ServerError, ServerResult};
+use barreleye_common::{
+	models::{optional_set, BasicModel, Webhook, WebhookActiveModel},
+	App,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Payload {
+	url: Option<String>,
+	is_active: Option<bool>,
+}
+
+pub async fn handler(
+	State(app): State<Arc<App>>,
+	Path(webhook_id): Path<String>,
+	Json(payload): Json<Payload>,
+) -> ServerResult<StatusCode> {
+	if Webhook::get_by_id(app.db(), &webhook_id).await?.is_some() {
+		// only absolute http(s) urls can be delivered to
+		if let Some(url) = payload.url.clone() {
+			let is_valid_url = url
+				.parse::<Uri>()
+				.is_ok_and(|uri| matches!(uri.scheme_str(), Some("http" | "https")));
+			if !is_valid_url {
+				return Err(ServerError::InvalidParam { field: "url".to_string(), value: url });
+			}
+		}
+
+		// update
+		let update_data = WebhookActiveModel {
+			url: optional_set(payload.url),
+			is_active: optional_set(payload.is_active),
+			..Default::default()
+		};
+		if update_data.is_changed() {
+			Webhook::update_by_id(app.db(), &webhook_id, update_data).await?;
+		}
+
+		Ok(StatusCode::NO_CONTENT)
+	} else {
+		Err(ServerError::NotFound)
+	}
+}
@@ -71,7 +71,7 @@ impl Server {
 		req: Request<B>,
 		next: Next<B>,
 	) -> ServerResult<Response> {
-		for public_endpoint in vec!["/v0/info", "/v0/upstream"].iter() {
+		for public_endpoint in ["/v0/info", "/v0/upstream", "/v0/downstream"].iter() {
 			if req.uri().to_string().starts_with(public_endpoint) {
 				return Ok(next.run(req).await);
 			}
@@ -85,7 +85,7 @@ impl Server {
 			.map_err(|_| ServerError::Unauthorized)?;
 
 		let token = match authorization.split_once(' ') {
-			Some((name, contents)) if name == "Bearer" => contents.to_string(),
+			Some(("Bearer", contents)) => contents.to_string(),
 			_ => return Err(ServerError::Unauthorized),
 		};
 
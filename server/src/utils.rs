@@ -1,16 +1,34 @@
 use eyre::Result;
 use sea_orm::ColumnTrait;
+use serde::Serialize;
 use std::{
 	collections::{HashMap, HashSet},
 	sync::Arc,
 };
+use uuid::Uuid;
 
 use crate::{errors::ServerError, ServerResult};
 use barreleye_common::{
-	models::{Address, Amount, BasicModel, Entity, EntityColumn, Network, PrimaryId, PrimaryIds},
-	App,
+	chain::U256,
+	models::{
+		Address, Amount, BasicModel, Cluster, Entity, EntityColumn, Link, LinkFilters, Network,
+		PrimaryId, PrimaryIds, Tag, Transfer,
+	},
+	taint::{self, History, TaintModel},
+	App, BlockHeight,
 };
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseExposure {
+	pub network: String,
+	pub asset: Option<String>,
+	pub entity: String,
+	pub amount: String,
+	pub percentage: f64,
+	pub hops: usize, // fewest transfers between the entity and the address
+}
+
 pub async fn get_addresses_from_params(
 	app: Arc<App>,
 	addresses: Vec<String>,
@@ -76,6 +94,263 @@ pub async fn get_networks(app: Arc<App>, addresses: Vec<String>) -> Result<Vec<N
 	Ok(ret)
 }
 
+// get transfers (@TODO ideally this step would be combined with link fetching)
+pub async fn get_transfers(
+	app: Arc<App>,
+	transfer_uuids: Vec<Uuid>,
+) -> Result<HashMap<Uuid, Transfer>> {
+	Ok(Transfer::get_all_by_uuids(&app.warehouse, transfer_uuids)
+		.await?
+		.into_iter()
+		.map(|t| (t.uuid, t))
+		.collect::<HashMap<Uuid, Transfer>>())
+}
+
+// get entities data (address map, entities & their tags) for a list of addresses
+pub async fn get_entities_data(
+	app: Arc<App>,
+	addresses: Vec<String>,
+) -> Result<(HashMap<(PrimaryId, String), PrimaryId>, HashMap<PrimaryId, Entity>, Vec<Tag>)> {
+	let mut entities = HashMap::new();
+	let mut tags = vec![];
+
+	let mut address_map = Address::get_all_by_addresses(app.db(), addresses.clone(), Some(false))
+		.await?
+		.into_iter()
+		.map(|a| ((a.network_id, a.address), a.entity_id))
+		.collect::<HashMap<(PrimaryId, String), PrimaryId>>();
+
+	// the rest inherit the entity of a labeled address from the same cluster
+	let labeled_addresses = address_map.keys().map(|(_, a)| a.clone()).collect::<HashSet<String>>();
+	let unlabeled_addresses = addresses
+		.into_iter()
+		.filter(|address| !labeled_addresses.contains(address))
+		.collect::<Vec<String>>();
+	if !unlabeled_addresses.is_empty() {
+		address_map.extend(get_cluster_address_map(app.clone(), unlabeled_addresses).await?);
+	}
+
+	if !address_map.is_empty() {
+		let entity_ids = address_map.values().copied().collect::<Vec<PrimaryId>>();
+		for entity in Entity::get_all_by_entity_ids(app.db(), entity_ids.into(), Some(false))
+			.await?
+			.into_iter()
+		{
+			entities.insert(entity.entity_id, entity);
+		}
+
+		if !entities.is_empty() {
+			let joined_tags = Tag::get_all_by_entity_ids(
+				app.db(),
+				entities.clone().into_keys().collect::<Vec<PrimaryId>>().into(),
+			)
+			.await?;
+
+			let mut map = HashMap::<PrimaryId, Vec<String>>::new();
+			for joined_tag in joined_tags.iter() {
+				if let Some(ids) = map.get_mut(&joined_tag.entity_id) {
+					ids.push(joined_tag.id.clone());
+				} else {
+					map.insert(joined_tag.entity_id, vec![joined_tag.id.clone()]);
+				}
+			}
+
+			for (entity_id, entity) in entities.iter_mut() {
+				entity.tags = map.get(entity_id).cloned().or(Some(vec![]));
+			}
+
+			tags = joined_tags.into_iter().map(|jt| jt.into()).collect();
+		}
+	}
+
+	Ok((address_map, entities, tags))
+}
+
+// get the share of funds received by `addresses` that can be traced back to each entity
+pub async fn get_exposure(
+	app: Arc<App>,
+	addresses: Vec<String>,
+	filters: LinkFilters,
+	taint_model: TaintModel,
+) -> Result<(Vec<ResponseExposure>, HashMap<PrimaryId, Entity>, Vec<Tag>)> {
+	let links = Link::get_all_by_addresses(&app.warehouse, addresses.clone(), &filters).await?;
+	if links.is_empty() {
+		return Ok((vec![], HashMap::new(), vec![]));
+	}
+
+	let (transfers, totals, entities_data) = tokio::join!(
+		get_transfers(
+			app.clone(),
+			links.iter().flat_map(|l| l.transfer_uuids.iter().map(|u| u.0)).collect(),
+		),
+		Amount::get_all_received_by_addresses(
+			&app.warehouse,
+			addresses,
+			(filters.since, filters.until)
+		),
+		get_entities_data(app.clone(), {
+			let mut from_addresses =
+				links.iter().map(|l| l.from_address.clone()).collect::<Vec<String>>();
+
+			from_addresses.sort_unstable();
+			from_addresses.dedup();
+
+			from_addresses
+		}),
+	);
+
+	let transfers = transfers?;
+	let totals = totals?;
+	let (address_map, entities_map, tags) = entities_data?;
+
+	// fifo needs to know what else went in & out of the addresses in the middle of each path
+	let mut history = vec![];
+	if taint_model == TaintModel::Fifo {
+		let mut intermediaries = HashMap::<PrimaryId, (HashSet<String>, BlockHeight)>::new();
+		for link in links.iter() {
+			for uuid in link.transfer_uuids.iter().skip(1) {
+				if let Some(transfer) = transfers.get(&uuid.0) {
+					let (addresses, max_block_height) =
+						intermediaries.entry(transfer.network_id as PrimaryId).or_default();
+					addresses.insert(transfer.from_address.clone());
+					*max_block_height = (*max_block_height).max(transfer.block_height);
+				}
+			}
+		}
+
+		for (network_id, (addresses, max_block_height)) in intermediaries.into_iter() {
+			history.extend(
+				Transfer::get_all_by_addresses(
+					&app.warehouse,
+					network_id,
+					addresses.into_iter().collect(),
+					max_block_height,
+				)
+				.await?,
+			);
+		}
+	}
+	let history = History::new(history);
+
+	// different paths can end with the same transfer, so only count its most tainted path
+	let mut tainted_transfers = HashMap::<(PrimaryId, Uuid), (U256, usize)>::new();
+	for link in links.into_iter() {
+		let network_id = link.network_id as PrimaryId;
+		if let Some(&entity_id) = address_map.get(&(network_id, link.from_address)) {
+			let path = link
+				.transfer_uuids
+				.iter()
+				.filter_map(|uuid| transfers.get(&uuid.0))
+				.collect::<Vec<&Transfer>>();
+
+			if let Some(last_transfer) =
+				path.last().filter(|_| path.len() == link.transfer_uuids.len())
+			{
+				let amount = taint_model.get_tainted_amount(&path, &history);
+				let tainted = tainted_transfers
+					.entry((entity_id, last_transfer.uuid))
+					.or_insert((amount, path.len()));
+				*tainted = (tainted.0.max(amount), tainted.1.min(path.len()));
+			}
+		}
+	}
+
+	// sum up per entity & received asset
+	let mut exposure_map = HashMap::<(PrimaryId, PrimaryId, String), (U256, usize)>::new();
+	for ((entity_id, uuid), (amount, hops)) in tainted_transfers.into_iter() {
+		let transfer = &transfers[&uuid];
+		let key = (entity_id, transfer.network_id as PrimaryId, transfer.asset_address.clone());
+		let exposure = exposure_map.entry(key).or_insert((U256::zero(), hops));
+		*exposure = (exposure.0 + amount, exposure.1.min(hops));
+	}
+
+	let mut ret = vec![];
+	let n = app.networks.read().await;
+	for ((entity_id, network_id, asset_address), (amount, hops)) in exposure_map.into_iter() {
+		if let (Some(chain), Some(entity)) = (n.get(&network_id), entities_map.get(&entity_id)) {
+			let total =
+				totals.get(&(network_id, asset_address.clone())).copied().unwrap_or_default();
+
+			ret.push(ResponseExposure {
+				network: chain.get_network().id,
+				asset: (!asset_address.is_empty()).then(|| chain.format_address(&asset_address)),
+				entity: entity.id.clone(),
+				amount: amount.to_string(),
+				percentage: taint::get_percentage(amount, total),
+				hops,
+			});
+		}
+	}
+
+	ret.sort_by(|a, b| b.percentage.total_cmp(&a.percentage));
+
+	Ok((ret, entities_map, tags))
+}
+
+// map clustered addresses to the entity of a labeled address in the same cluster
+async fn get_cluster_address_map(
+	app: Arc<App>,
+	addresses: Vec<String>,
+) -> Result<HashMap<(PrimaryId, String), PrimaryId>> {
+	let mut ret = HashMap::new();
+
+	let clusters = Cluster::get_all_by_addresses(&app.warehouse, addresses).await?;
+	if clusters.is_empty() {
+		return Ok(ret);
+	}
+
+	// only members of the matched clusters can be labeled ones
+	let mut cluster_ids = HashMap::<PrimaryId, HashSet<String>>::new();
+	for cluster in clusters.iter() {
+		cluster_ids
+			.entry(cluster.network_id as PrimaryId)
+			.or_default()
+			.insert(cluster.cluster_id.clone());
+	}
+
+	let mut cluster_map = HashMap::new();
+	for (network_id, cluster_ids) in cluster_ids.into_iter() {
+		let mut members = Cluster::get_all_by_cluster_ids(
+			&app.warehouse,
+			network_id,
+			cluster_ids.into_iter().collect(),
+		)
+		.await?;
+
+		let labeled_map = Address::get_all_by_network_id_and_addresses(
+			app.db(),
+			network_id,
+			members.iter().map(|m| m.address.clone()).collect(),
+			Some(false),
+		)
+		.await?
+		.into_iter()
+		.map(|a| (a.address, a.entity_id))
+		.collect::<HashMap<String, PrimaryId>>();
+		if labeled_map.is_empty() {
+			continue;
+		}
+
+		// if a cluster has addresses of different entities, the smallest address wins
+		members.sort_unstable_by(|a, b| b.address.cmp(&a.address));
+
+		for member in members.into_iter() {
+			if let Some(&entity_id) = labeled_map.get(&member.address) {
+				cluster_map.insert((network_id, member.cluster_id), entity_id);
+			}
+		}
+	}
+
+	for cluster in clusters.into_iter() {
+		let network_id = cluster.network_id as PrimaryId;
+		if let Some(&entity_id) = cluster_map.get(&(network_id, cluster.cluster_id)) {
+			ret.insert((network_id, cluster.address), entity_id);
+		}
+	}
+
+	Ok(ret)
+}
+
 pub fn extract_primary_ids(
 	field: &str,
 	mut ids: Vec<String>,
@@ -3,7 +3,8 @@ use std::sync::Arc;
 use tokio::{signal, sync::RwLock, task::JoinSet};
 
 use barreleye_common::{
-	quit, App, AppError, Cache, Db, Progress, ProgressStep, Settings, Warehouse,
+	quit, warehouse::MigrationMode, App, AppError, Cache, Db, Progress, ProgressStep, Settings,
+	Warehouse,
 };
 use barreleye_indexer::Indexer;
 use barreleye_server::Server;
@@ -37,7 +38,31 @@ async fn main() -> Result<()> {
 	}));
 
 	progress.show(ProgressStep::Migrations);
-	warehouse.run_migrations().await?;
+	match settings.warehouse_migrations {
+		MigrationMode::Run => warehouse.run_migrations().await?,
+		MigrationMode::DryRun => {
+			let pending = warehouse.get_pending_migrations().await?;
+			if pending.is_empty() {
+				println!("\nNo pending warehouse migrations");
+			}
+			for (name, statements) in pending.into_iter() {
+				println!("\n-- {name}");
+				for statement in statements.into_iter() {
+					println!("{};", statement.trim());
+				}
+			}
+
+			return Ok(());
+		}
+		MigrationMode::Rollback => {
+			match warehouse.rollback_migration().await? {
+				Some(name) => println!("\nReverted warehouse migration {name}"),
+				None => println!("\nNo warehouse migrations to revert"),
+			}
+
+			return Ok(());
+		}
+	}
 	db.run_migrations().await?;
 
 	let app = Arc::new(App::new(settings.clone(), cache, db, warehouse).await?);